@@ -0,0 +1,373 @@
+// src/address_bar.rs
+// What the address bar does with typed text: decide whether it's an address
+// or a search, fill in a missing scheme, and suggest matching open tabs,
+// bookmarks and history entries while typing.
+use crate::bookmarks::Bookmarks;
+use crate::forms::urlencode_component;
+use crate::history::GlobalHistory;
+use std::path::PathBuf;
+
+// Most suggestions shown below the address bar
+const MAX_SUGGESTIONS: usize = 8;
+
+// Schemes typed addresses may start with; anything else before a colon, as in
+// "localhost:8080", is a host
+const KNOWN_SCHEMES: [&str; 6] = ["http", "https", "about", "file", "data", "mailto"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchEngine {
+    pub name: String,
+    // URL with %s where the encoded search terms go
+    pub template: String,
+}
+
+impl SearchEngine {
+    pub fn search_url(&self, terms: &str) -> String {
+        self.template.replace("%s", &urlencode_component(terms.trim()))
+    }
+}
+
+pub struct SearchEngines {
+    pub engines: Vec<SearchEngine>,
+    // Index of the engine searches go to
+    pub default: usize,
+    // Where the list is saved; None keeps it in memory only
+    file: Option<PathBuf>,
+}
+
+impl Default for SearchEngines {
+    fn default() -> Self {
+        let engine = |name: &str, template: &str| SearchEngine {
+            name: name.to_string(),
+            template: template.to_string(),
+        };
+        Self {
+            // DuckDuckGo's HTML version works without JavaScript
+            engines: vec![
+                engine("DuckDuckGo", "https://html.duckduckgo.com/html/?q=%s"),
+                engine("Google", "https://www.google.com/search?q=%s"),
+                engine("Bing", "https://www.bing.com/search?q=%s"),
+                engine("Wikipedia", "https://en.wikipedia.org/wiki/Special:Search?search=%s"),
+            ],
+            default: 0,
+            file: None,
+        }
+    }
+}
+
+impl SearchEngines {
+    // Engines kept in `file`, or the built-in ones until it's first saved
+    pub fn load(file: PathBuf) -> Self {
+        let mut engines = Self::default();
+        if let Ok(text) = std::fs::read_to_string(&file) {
+            let mut saved = Vec::new();
+            let mut default = 0;
+            for line in text.lines().filter(|line| !line.starts_with('#')) {
+                let mut fields = line.split('\t');
+                let (Some(name), Some(template)) = (fields.next(), fields.next()) else {
+                    continue;
+                };
+                if fields.next() == Some("default") {
+                    default = saved.len();
+                }
+                saved.push(SearchEngine {
+                    name: name.to_string(),
+                    template: template.to_string(),
+                });
+            }
+            if !saved.is_empty() {
+                engines.engines = saved;
+                engines.default = default;
+            }
+        }
+        engines.file = Some(file);
+        engines
+    }
+
+    pub fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let mut text = String::from("# egui_browser search engines: name, template with %s, default\n");
+        for (index, engine) in self.engines.iter().enumerate() {
+            let one_line = |text: &str| text.replace(['\t', '\n', '\r'], " ");
+            let default = if index == self.default { "\tdefault" } else { "" };
+            text.push_str(&format!("{}\t{}{}\n", one_line(&engine.name), one_line(&engine.template), default));
+        }
+        let _ = std::fs::write(file, text);
+    }
+
+    pub fn default_engine(&self) -> Option<&SearchEngine> {
+        self.engines.get(self.default).or(self.engines.first())
+    }
+}
+
+// What typed text means
+#[derive(Clone, Debug, PartialEq)]
+pub enum Destination {
+    Url(String),
+    Search(String),
+}
+
+// Decide whether the text is an address or search terms. Addresses without a
+// scheme get http://; a leading '?' forces a search.
+pub fn classify_input(input: &str) -> Option<Destination> {
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+    if let Some(terms) = input.strip_prefix('?') {
+        return Some(Destination::Search(terms.trim().to_string()));
+    }
+    if let Some((scheme, _)) = input.split_once(':') {
+        if KNOWN_SCHEMES.iter().any(|known| known.eq_ignore_ascii_case(scheme)) {
+            return Some(Destination::Url(input.to_string()));
+        }
+    }
+    if !input.contains(char::is_whitespace) && looks_like_host(input) {
+        return Some(Destination::Url(format!("http://{}", input)));
+    }
+    Some(Destination::Search(input.to_string()))
+}
+
+// The host part of scheme-less text is localhost, an IP address, or a domain
+// name ending in an alphabetic top-level label
+fn looks_like_host(input: &str) -> bool {
+    let authority = input.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = match authority.strip_prefix('[') {
+        Some(ipv6) => return ipv6.contains(']'),
+        None => authority.rsplit_once(':').map_or(authority, |(host, port)| {
+            if port.bytes().all(|byte| byte.is_ascii_digit()) { host } else { authority }
+        }),
+    };
+    if host.eq_ignore_ascii_case("localhost") || host.parse::<std::net::Ipv4Addr>().is_ok() {
+        return true;
+    }
+    let labels: Vec<&str> = host.split('.').collect();
+    let valid_label = |label: &&str| !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-');
+    let top = labels.last().copied().unwrap_or_default();
+    labels.len() >= 2
+        && labels.iter().all(valid_label)
+        && (top.len() >= 2 && top.chars().all(char::is_alphabetic) || top.starts_with("xn--"))
+}
+
+// The URL to load for typed text, searching with the default engine when
+// it isn't an address
+pub fn resolve_input(input: &str, engines: &SearchEngines) -> Option<String> {
+    match classify_input(input)? {
+        Destination::Url(url) => Some(url),
+        Destination::Search(terms) => engines.default_engine().map(|engine| engine.search_url(&terms)),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SuggestionKind {
+    // Load what was typed, as an address or a search
+    Navigate,
+    Search,
+    // Switch to the open tab with this id
+    Tab(u64),
+    Bookmark,
+    History,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    pub kind: SuggestionKind,
+    pub title: String,
+    pub url: String,
+}
+
+// Suggestions for typed text: first what Enter would do, then matching open
+// tabs, bookmarks and history, without repeating a URL. Addresses that start
+// with the text come before ones that merely contain it.
+pub fn suggestions(
+    input: &str,
+    engines: &SearchEngines,
+    history: &GlobalHistory,
+    bookmarks: &Bookmarks,
+    tabs: &[(u64, String, String)],
+) -> Vec<Suggestion> {
+    let query = input.trim().to_lowercase();
+    let mut suggestions = Vec::new();
+    match classify_input(input) {
+        Some(Destination::Url(url)) => suggestions.push(Suggestion {
+            kind: SuggestionKind::Navigate,
+            title: input.trim().to_string(),
+            url,
+        }),
+        Some(Destination::Search(terms)) => {
+            if let Some(engine) = engines.default_engine() {
+                suggestions.push(Suggestion {
+                    kind: SuggestionKind::Search,
+                    title: format!("{} - Search with {}", terms, engine.name),
+                    url: engine.search_url(&terms),
+                });
+            }
+        }
+        None => return suggestions,
+    }
+
+    let mut candidates: Vec<(u8, Suggestion)> = Vec::new();
+    let mut add = |kind: SuggestionKind, title: &str, url: &str, limit: usize| {
+        let count = candidates.iter().filter(|(_, suggestion)| std::mem::discriminant(&suggestion.kind) == std::mem::discriminant(&kind)).count();
+        if count >= limit {
+            return;
+        }
+        if let Some(rank) = match_rank(&query, title, url) {
+            candidates.push((
+                rank,
+                Suggestion {
+                    kind,
+                    title: title.to_string(),
+                    url: url.to_string(),
+                },
+            ));
+        }
+    };
+    for (id, title, url) in tabs {
+        add(SuggestionKind::Tab(*id), title, url, 3);
+    }
+    for bookmark in bookmarks.all() {
+        add(SuggestionKind::Bookmark, &bookmark.title, &bookmark.url, 3);
+    }
+    for entry in history.search(&query) {
+        add(SuggestionKind::History, entry.title.as_deref().unwrap_or_default(), &entry.url, 5);
+    }
+
+    // Stable, so each group keeps its order within a rank
+    candidates.sort_by_key(|(rank, _)| *rank);
+    for (_, suggestion) in candidates {
+        if suggestions.len() >= MAX_SUGGESTIONS {
+            break;
+        }
+        if !suggestions.iter().any(|existing| existing.url == suggestion.url) {
+            suggestions.push(suggestion);
+        }
+    }
+    suggestions
+}
+
+// 0 when the address starts with the query (ignoring scheme and www.), 1 when
+// the address or title contains it
+fn match_rank(query: &str, title: &str, url: &str) -> Option<u8> {
+    let url = url.to_lowercase();
+    let bare = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+    let bare = bare.strip_prefix("www.").unwrap_or(bare);
+    if bare.starts_with(query) || url.starts_with(query) {
+        Some(0)
+    } else if url.contains(query) || title.to_lowercase().contains(query) {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+// Keyboard and popup state of the suggestion dropdown
+#[derive(Default)]
+pub struct AddressBar {
+    pub open: bool,
+    // Highlighted suggestion; None loads the text as typed
+    pub selected: Option<usize>,
+    pub suggestions: Vec<Suggestion>,
+    // The text the suggestions were made for
+    pub typed: String,
+}
+
+impl AddressBar {
+    pub fn close(&mut self) {
+        self.open = false;
+        self.selected = None;
+    }
+
+    // Move the highlight down (+1) or up (-1); above the first suggestion is the typed text
+    pub fn move_selection(&mut self, offset: isize) {
+        let count = self.suggestions.len() as isize;
+        let current = self.selected.map_or(-1, |index| index as isize);
+        let next = (current + offset).clamp(-1, count - 1);
+        self.selected = (next >= 0).then_some(next as usize);
+    }
+
+    pub fn selected_suggestion(&self) -> Option<&Suggestion> {
+        self.selected.and_then(|index| self.suggestions.get(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_addresses_from_searches() {
+        let url = |text: &str| Destination::Url(text.to_string());
+        let search = |text: &str| Destination::Search(text.to_string());
+
+        assert_eq!(classify_input(" example.com "), Some(url("http://example.com")));
+        assert_eq!(classify_input("localhost:8080/test"), Some(url("http://localhost:8080/test")));
+        assert_eq!(classify_input("192.168.0.1"), Some(url("http://192.168.0.1")));
+        assert_eq!(classify_input("[::1]:3000"), Some(url("http://[::1]:3000")));
+        assert_eq!(classify_input("HTTPS://Example.com"), Some(url("HTTPS://Example.com")));
+        assert_eq!(classify_input("about:history"), Some(url("about:history")));
+        assert_eq!(classify_input("rust lang"), Some(search("rust lang")));
+        assert_eq!(classify_input("rust"), Some(search("rust")));
+        assert_eq!(classify_input("version 1.2"), Some(search("version 1.2")));
+        assert_eq!(classify_input("1.5"), Some(search("1.5")));
+        assert_eq!(classify_input("?example.com"), Some(search("example.com")));
+        assert_eq!(classify_input("   "), None);
+
+        let engines = SearchEngines::default();
+        assert_eq!(resolve_input("a&b c", &engines).as_deref(), Some("https://html.duckduckgo.com/html/?q=a%26b+c"));
+    }
+
+    #[test]
+    fn saves_search_engines() {
+        let file = std::env::temp_dir().join(format!("search_engines_{}.txt", std::process::id()));
+        let mut engines = SearchEngines::load(file.clone());
+        engines.engines.push(SearchEngine {
+            name: "Docs".to_string(),
+            template: "https://docs.rs/releases/search?query=%s".to_string(),
+        });
+        engines.default = 4;
+        engines.save();
+
+        let loaded = SearchEngines::load(file.clone());
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(loaded.engines.len(), 5);
+        assert_eq!(loaded.default_engine().unwrap().search_url("serde"), "https://docs.rs/releases/search?query=serde");
+    }
+
+    #[test]
+    fn suggests_tabs_bookmarks_and_history() {
+        let mut history = GlobalHistory::default();
+        history.record_visit("http://www.rust-lang.org/learn", Some("Learn Rust"), 100);
+        history.record_visit("http://blog.test/rust", Some("A blog"), 200);
+        history.record_visit("http://docs.rs/", Some("Docs"), 300);
+        let mut bookmarks = Bookmarks::default();
+        bookmarks.add_bookmark(crate::bookmarks::BAR_ID, "Rust", "http://www.rust-lang.org/", 0);
+        let tabs = [(7, "Rust Playground".to_string(), "https://play.rust-lang.org/".to_string())];
+
+        let found = suggestions("rust", &SearchEngines::default(), &history, &bookmarks, &tabs);
+        let summary: Vec<(SuggestionKind, &str)> = found.iter().map(|suggestion| (suggestion.kind.clone(), suggestion.url.as_str())).collect();
+        assert_eq!(
+            summary,
+            [
+                (SuggestionKind::Search, "https://html.duckduckgo.com/html/?q=rust"),
+                (SuggestionKind::Bookmark, "http://www.rust-lang.org/"),
+                (SuggestionKind::History, "http://www.rust-lang.org/learn"),
+                (SuggestionKind::Tab(7), "https://play.rust-lang.org/"),
+                (SuggestionKind::History, "http://blog.test/rust"),
+            ]
+        );
+
+        let mut bar = AddressBar {
+            suggestions: found,
+            ..Default::default()
+        };
+        bar.move_selection(1);
+        bar.move_selection(1);
+        assert_eq!(bar.selected_suggestion().map(|suggestion| suggestion.kind.clone()), Some(SuggestionKind::Bookmark));
+        bar.move_selection(-1);
+        bar.move_selection(-1);
+        assert_eq!(bar.selected, None);
+    }
+}
@@ -1,119 +1,121 @@
 // src/app.rs
+use crate::cookies::unix_now;
+use crate::document::{escape_html, preprocess_html, AboutPage, Document, HtmlPage, PageContent};
+use crate::address_bar::{resolve_input, suggestions, AddressBar, SearchEngines, Suggestion, SuggestionKind};
+use crate::bookmarks::{Bookmarks, BAR_ID};
+use crate::cookies::CookieJar;
+use crate::history::GlobalHistory;
+use crate::encoding::decode_body;
+use crate::fetch::{fetch_cancellable, HttpClient, Initiator, LoadProgress, PageRequest, ResponseInfo};
+use crate::http_cache::{HttpCache, DEFAULT_MAX_SIZE};
+use crate::mime::{classify, mime_essence, ContentKind};
 use crate::html_renderer::HtmlRenderer;
+use crate::profile::{profile_dir, profile_file};
 use crate::style::create_default_styles;
+use crate::tab::Tab;
 use crate::ui_components;
+use crate::url_resolver::{is_fetchable, scheme_of};
 use eframe::egui;
 use egui::Context;
 use poll_promise::Promise;
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
-use std::io::Read;
+use std::time::{Duration, Instant};
 
-// Store the clicked link URL
+// How long a status bar notice stays up
+const NOTICE_DURATION: Duration = Duration::from_secs(5);
+
+// A link activation waiting to be handled by the browser
+#[derive(Clone)]
+pub struct LinkClick {
+    pub url: String,
+    // Middle-click or Ctrl+click: open in a new background tab
+    pub new_tab: bool,
+}
+
+// Store the clicked link URL, and the one under the pointer for the status bar
 #[derive(Clone, Default)]
 pub struct LinkHandler {
-    pub clicked_link: Arc<Mutex<Option<String>>>,
+    pub clicked_link: Arc<Mutex<Option<LinkClick>>>,
+    pub hovered_link: Arc<Mutex<Option<String>>>,
+    // A clicked link the browser can't load, such as mailto:
+    pub unsupported_link: Arc<Mutex<Option<String>>>,
 }
 
 impl LinkHandler {
     pub fn new() -> Self {
-        Self {
-            clicked_link: Arc::new(Mutex::new(None)),
-        }
+        Self::default()
     }
 
-    pub fn set_link(&self, url: String) {
-        let mut link = self.clicked_link.lock().unwrap();
-        *link = Some(url);
+    pub fn set_hovered(&self, url: String) {
+        *self.hovered_link.lock().unwrap() = Some(url);
     }
 
-    pub fn take_link(&self) -> Option<String> {
-        let mut link = self.clicked_link.lock().unwrap();
-        link.take()
-    }
-}
-
-// Navigation history structure
-struct NavigationHistory {
-    history: Vec<String>,
-    current_index: usize,
-}
-
-impl NavigationHistory {
-    fn new(initial_url: String) -> Self {
-        Self {
-            history: vec![initial_url],
-            current_index: 0,
-        }
+    pub fn set_unsupported(&self, url: String) {
+        *self.unsupported_link.lock().unwrap() = Some(url);
     }
 
-    fn can_go_back(&self) -> bool {
-        self.current_index > 0
+    pub fn take_unsupported(&self) -> Option<String> {
+        self.unsupported_link.lock().unwrap().take()
     }
 
-    fn can_go_forward(&self) -> bool {
-        self.current_index < self.history.len() - 1
+    // The link hovered in the last frame; it has to be hovered again to stay
+    pub fn take_hovered(&self) -> Option<String> {
+        self.hovered_link.lock().unwrap().take()
     }
 
-    fn go_back(&mut self) -> Option<&str> {
-        if self.can_go_back() {
-            self.current_index -= 1;
-            Some(&self.history[self.current_index])
-        } else {
-            None
-        }
-    }
-
-    fn go_forward(&mut self) -> Option<&str> {
-        if self.can_go_forward() {
-            self.current_index += 1;
-            Some(&self.history[self.current_index])
-        } else {
-            None
-        }
-    }
-
-    fn add_url(&mut self, url: String) {
-        // Remove any forward history
-        if self.current_index < self.history.len() - 1 {
-            self.history.truncate(self.current_index + 1);
-        }
-        
-        // Don't add if it's the same as the current URL
-        if self.current_url() == url {
-            return;
-        }
-        
-        self.history.push(url);
-        self.current_index = self.history.len() - 1;
+    pub fn set_link(&self, url: String, new_tab: bool) {
+        let mut link = self.clicked_link.lock().unwrap();
+        *link = Some(LinkClick { url, new_tab });
     }
 
-    fn current_url(&self) -> String {
-        self.history[self.current_index].clone()
+    pub fn take_link(&self) -> Option<LinkClick> {
+        let mut link = self.clicked_link.lock().unwrap();
+        link.take()
     }
 }
 
 // Our application state
 pub struct EguiBrowser {
-    url: String,
-    html_content: Option<String>,
-    error_message: Option<String>,
-    // Promise to store the ongoing HTTP request
-    fetch_promise: Option<Promise<Result<ehttp::Response, String>>>,
+    // Open tabs, each with its own page, history and in-flight request
+    tabs: Vec<Tab>,
+    active_tab: usize,
+    next_tab_id: u64,
     // HTML renderer with styling
     html_renderer: HtmlRenderer,
     // State for showing/hiding raw HTML
     show_raw_html: bool,
     // Link handler for clicked links
     link_handler: LinkHandler,
-    // Navigation history
-    navigation: NavigationHistory,
-    // User agent string
-    user_agent: String,
-    // Image cache: URL -> (texture_id, size)
-    image_cache: HashMap<String, (egui::TextureId, egui::Vec2)>,
+    // User agent and cookie jar, shared by page, stylesheet and image requests
+    client: HttpClient,
+    // Whether the cookie settings window is open
+    show_cookies: bool,
+    // Every page visited, in any tab, shown on about:history
+    history: GlobalHistory,
+    history_view: ui_components::HistoryView,
+    // Bookmark tree, with the bar shown under the address bar
+    bookmarks: Bookmarks,
+    // Where searches typed in the address bar go
+    search_engines: SearchEngines,
+    // Whether the search engine settings window is open
+    show_search_engines: bool,
+    // Suggestion dropdown of the address bar
+    address_bar: AddressBar,
+    // Message shown in the status bar until the given time, e.g. for a link
+    // that can't be followed
+    status_notice: Option<(String, Instant)>,
+    // Image cache: URL -> (texture, size), shared by all tabs
+    image_cache: HashMap<String, (egui::TextureHandle, egui::Vec2)>,
     // Current image fetching promises
-    image_promises: HashMap<String, Promise<Result<ehttp::Response, String>>>,
+    image_promises: HashMap<String, ImageFetch>,
+}
+
+// An image request, made for one navigation of one tab
+struct ImageFetch {
+    tab_id: u64,
+    navigation_id: u64,
+    promise: Promise<Result<ehttp::Response, String>>,
 }
 
 impl Default for EguiBrowser {
@@ -121,16 +123,28 @@ impl Default for EguiBrowser {
         let initial_url = "http://web.simmons.edu/~grovesd/comm244/notes/week3/html-test-page.html".to_string();
         let link_handler = LinkHandler::new();
         let firefox_user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0".to_string();
+        // Cookies are kept in the profile; without one they last until exit
+        let cookies = profile_file("cookies.txt").map(CookieJar::load).unwrap_or_default();
+        let mut client = HttpClient::new(firefox_user_agent, cookies.shared());
+        if let Some(dir) = profile_dir() {
+            client = client.with_cache(HttpCache::open(dir.join("cache"), DEFAULT_MAX_SIZE).shared());
+        }
         Self {
-            url: initial_url.clone(),
-            html_content: None,
-            error_message: None,
-            fetch_promise: None,
+            tabs: vec![Tab::new(0, initial_url)],
+            active_tab: 0,
+            next_tab_id: 1,
             html_renderer: HtmlRenderer::new(create_default_styles(), link_handler.clone()),
             show_raw_html: false,
             link_handler,
-            navigation: NavigationHistory::new(initial_url),
-            user_agent: firefox_user_agent,
+            client,
+            show_cookies: false,
+            history: profile_file("history.txt").map(GlobalHistory::load).unwrap_or_default(),
+            history_view: ui_components::HistoryView::default(),
+            bookmarks: profile_file("bookmarks.html").map(Bookmarks::load).unwrap_or_default(),
+            search_engines: profile_file("search_engines.txt").map(SearchEngines::load).unwrap_or_default(),
+            show_search_engines: false,
+            address_bar: AddressBar::default(),
+            status_notice: None,
             image_cache: HashMap::new(),
             image_promises: HashMap::new(),
         }
@@ -168,6 +182,198 @@ impl EguiBrowser {
         
         ctx.set_style(style);
     }
+    
+    fn active_tab(&mut self) -> &mut Tab {
+        &mut self.tabs[self.active_tab]
+    }
+    
+    // Open a tab for the URL and start loading it
+    fn open_tab(&mut self, ctx: &Context, url: String, activate: bool) {
+        let mut tab = Tab::new(self.next_tab_id, url);
+        self.next_tab_id += 1;
+        if !tab.url.is_empty() {
+            tab.fetch(ctx, &self.client);
+        }
+        
+        // New tabs go right after the current one
+        let index = self.active_tab + 1;
+        self.tabs.insert(index, tab);
+        if activate {
+            self.active_tab = index;
+        }
+    }
+    
+    // Load what the address bar holds: the picked suggestion, or the typed
+    // text as a keyword bookmark ("w foo"), an address or a search
+    fn accept_address(&mut self, ctx: &Context, suggestion: Option<Suggestion>) {
+        self.address_bar.close();
+        self.address_bar.typed.clear();
+        let url = match suggestion {
+            Some(Suggestion { kind: SuggestionKind::Tab(id), .. }) => {
+                // The tab being left shows its own address again
+                let tab = self.active_tab();
+                tab.url = tab.navigation.current_url();
+                if let Some(index) = self.tabs.iter().position(|tab| tab.id == id) {
+                    self.active_tab = index;
+                }
+                return;
+            }
+            Some(suggestion) => suggestion.url,
+            None => {
+                let typed = self.tabs[self.active_tab].url.clone();
+                match self.bookmarks.expand_keyword(&typed).or_else(|| resolve_input(&typed, &self.search_engines)) {
+                    Some(url) => url,
+                    None => return,
+                }
+            }
+        };
+        let client = self.client.clone();
+        self.active_tab().navigate(ctx, url, &client);
+    }
+    
+    // Close a tab, cancelling whatever it's still loading; the last tab is
+    // replaced by an empty one rather than closed
+    fn close_tab(&mut self, index: usize) {
+        self.tabs.remove(index).stop();
+        if self.tabs.is_empty() {
+            self.tabs.push(Tab::new(self.next_tab_id, String::new()));
+            self.next_tab_id += 1;
+        }
+        if self.active_tab > index || self.active_tab >= self.tabs.len() {
+            self.active_tab = self.active_tab.saturating_sub(1);
+        }
+    }
+    
+    // Move a tab to a new position, keeping the same tab active
+    fn move_tab(&mut self, from: usize, to: usize) {
+        if from == to || to >= self.tabs.len() {
+            return;
+        }
+        let active_id = self.tabs[self.active_tab].id;
+        let tab = self.tabs.remove(from);
+        self.tabs.insert(to, tab);
+        self.active_tab = self.tabs.iter().position(|tab| tab.id == active_id).unwrap_or(0);
+    }
+    
+    // Collect finished page loads for every tab, including background ones
+    fn poll_tabs(&mut self, ctx: &Context) {
+        for index in 0..self.tabs.len() {
+            self.tabs[index].poll_stylesheets();
+            let Some(promise) = self.tabs[index].fetch_promise.take() else {
+                continue;
+            };
+            match promise.try_take() {
+                // A response for a navigation since replaced or stopped
+                Ok((navigation_id, _)) if navigation_id != self.tabs[index].navigation_id => {}
+                Ok((_, Ok(response))) => {
+                    let document = self.build_document(ctx, response);
+                    let tab = &mut self.tabs[index];
+                    // Show where redirects ended up
+                    if document.response.url != tab.url {
+                        tab.url = document.response.url.clone();
+                        tab.navigation.replace_current(tab.url.clone());
+                    }
+                    let title = match &document.content {
+                        PageContent::Html(page) => page.title.as_deref(),
+                        _ => None,
+                    };
+                    // Error pages, reloads and back/forward aren't new visits
+                    if tab.new_visit && !document.response.is_error() {
+                        self.history.record_visit(&document.response.url, title, unix_now());
+                    }
+                    tab.set_document(document);
+                    tab.fetch_stylesheets(ctx, &self.client);
+                }
+                Ok((_, Err(err))) => {
+                    self.tabs[index].error_message = Some(format!("Error: {}", err));
+                }
+                Err(promise) => {
+                    // Still loading
+                    self.tabs[index].fetch_promise = Some(promise);
+                }
+            }
+        }
+    }
+    
+    // Tab strip: click to switch, drag or right-click to reorder, middle-click or x to close
+    fn tab_strip(&mut self, ui: &mut egui::Ui, ctx: &Context) {
+        let mut activate = None;
+        let mut close = None;
+        let mut reorder = None;
+        
+        ui.horizontal(|ui| {
+            let mut tab_rects = Vec::with_capacity(self.tabs.len());
+            let mut dragged = None;
+            
+            for (index, tab) in self.tabs.iter().enumerate() {
+                let mut title = tab.title();
+                if title.chars().count() > 24 {
+                    title = format!("{}...", title.chars().take(21).collect::<String>());
+                }
+                if tab.is_loading() {
+                    title = format!("⟳ {}", title);
+                }
+                
+                // Ids follow the tab rather than its position so drags survive reordering
+                let response = ui
+                    .push_id(tab.id, |ui| ui.selectable_label(index == self.active_tab, title))
+                    .inner
+                    .interact(egui::Sense::click_and_drag())
+                    .on_hover_text(tab.url.as_str());
+                tab_rects.push(response.rect);
+                
+                if response.clicked() {
+                    activate = Some(index);
+                }
+                if response.middle_clicked() {
+                    close = Some(index);
+                }
+                if response.dragged() {
+                    dragged = response.interact_pointer_pos().map(|pointer| (index, pointer));
+                }
+                response.context_menu(|ui| {
+                    if ui.add_enabled(index > 0, egui::Button::new("Move Left")).clicked() {
+                        reorder = Some((index, index - 1));
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(index + 1 < self.tabs.len(), egui::Button::new("Move Right")).clicked() {
+                        reorder = Some((index, index + 1));
+                        ui.close_menu();
+                    }
+                    if ui.button("Close Tab").clicked() {
+                        close = Some(index);
+                        ui.close_menu();
+                    }
+                });
+                
+                if ui.small_button("x").on_hover_text("Close tab").clicked() {
+                    close = Some(index);
+                }
+                ui.separator();
+            }
+            
+            // A dragged tab moves to whichever tab the pointer is over
+            if let Some((index, pointer)) = dragged {
+                if let Some(target) = tab_rects.iter().position(|rect| rect.x_range().contains(pointer.x)) {
+                    reorder = Some((index, target));
+                }
+            }
+            
+            if ui.button("+").on_hover_text("New tab (Ctrl+T)").clicked() {
+                self.open_tab(ctx, String::new(), true);
+            }
+        });
+        
+        if let Some((from, to)) = reorder {
+            self.move_tab(from, to);
+        }
+        if let Some(index) = activate {
+            self.active_tab = index;
+        }
+        if let Some(index) = close {
+            self.close_tab(index);
+        }
+    }
 }
 
 impl eframe::App for EguiBrowser {
@@ -184,52 +390,189 @@ impl eframe::App for EguiBrowser {
         unsafe {
             if FIRST_RUN {
                 FIRST_RUN = false;
-                self.fetch_url(ctx.clone());
+                let client = self.client.clone();
+                self.active_tab().fetch(ctx, &client);
             }
         }
         
         // Check if a link was clicked and handle it
-        if let Some(link_url) = self.link_handler.take_link() {
-            self.url = link_url.clone();
-            self.navigation.add_url(link_url);
-            self.fetch_url(ctx.clone());
+        if let Some(link) = self.link_handler.take_link() {
+            if link.new_tab {
+                self.open_tab(ctx, link.url, false);
+            } else {
+                let client = self.client.clone();
+                self.active_tab().follow_link(ctx, link.url, &client);
+            }
+        }
+        
+        // Load the result of a form submitted last frame, once its controls are valid
+        if let Some(request) = self.active_tab().take_form_submission() {
+            let client = self.client.clone();
+            self.active_tab().navigate_to(ctx, request, &client);
+        }
+        self.active_tab().update_form_validity();
+        
+        // Reload (F5 or Ctrl+R), Hard Reload (Ctrl+F5, Shift+F5 or Ctrl+Shift+R)
+        // and Stop (Esc). Esc goes to the address bar or a form field instead
+        // when one has focus.
+        let (reload, hard_reload, stop) = ctx.input(|i| {
+            let reload = i.key_pressed(egui::Key::F5) || (i.modifiers.command && i.key_pressed(egui::Key::R));
+            let hard = i.modifiers.shift || (i.modifiers.command && i.key_pressed(egui::Key::F5));
+            (reload && !hard, reload && hard, i.key_pressed(egui::Key::Escape))
+        });
+        if reload || hard_reload {
+            let client = self.client.clone();
+            self.active_tab().reload(ctx, &client, hard_reload);
+        }
+        if stop && ctx.memory(|memory| memory.focus().is_none()) {
+            self.active_tab().stop();
+        }
+        
+        // Tab keyboard shortcuts
+        let (new_tab, close_tab) = ctx.input(|i| {
+            (
+                i.modifiers.command && i.key_pressed(egui::Key::T),
+                i.modifiers.command && i.key_pressed(egui::Key::W),
+            )
+        });
+        if new_tab {
+            self.open_tab(ctx, String::new(), true);
+        }
+        if close_tab {
+            self.close_tab(self.active_tab);
+        }
+        
+        // Pick up finished page loads in every tab
+        self.poll_tabs(ctx);
+        for index in 0..self.tabs.len() {
+            let pending_images = self.pending_images(&self.tabs[index]);
+            self.tabs[index].finish_load(pending_images);
+        }
+        
+        // Status bar: the link under the pointer, a notice, or how loading is going
+        if let Some(url) = self.link_handler.take_unsupported() {
+            let scheme = scheme_of(&url).unwrap_or_default();
+            self.status_notice = Some((format!("Can't open {}: links: {}", scheme, url), Instant::now() + NOTICE_DURATION));
+        }
+        if self.status_notice.as_ref().is_some_and(|(_, until)| Instant::now() >= *until) {
+            self.status_notice = None;
+        }
+        if let Some((_, until)) = &self.status_notice {
+            ctx.request_repaint_after(until.saturating_duration_since(Instant::now()));
+        }
+        let hovered_link = self.link_handler.take_hovered();
+        let tab = &self.tabs[self.active_tab];
+        let pending_subresources = self.pending_images(tab) + tab.pending_stylesheets();
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            let message = hovered_link.as_deref().or(self.status_notice.as_ref().map(|(notice, _)| notice.as_str()));
+            ui_components::render_status_bar(ui, tab, message, pending_subresources);
+        });
+        
+        // Links and images resolve against the active page's base URL, and its
+        // stylesheets style the elements
+        if let Some(page) = self.tabs[self.active_tab].document.as_ref().and_then(|document| match &document.content {
+            PageContent::Html(page) => Some(page),
+            _ => None,
+        }) {
+            self.html_renderer.set_page(page);
         }
 
+        let client = self.client.clone();
+        let mut history_link = None;
+        let mut open_history = false;
+        let mut bar_link = None;
+        let mut open_bookmarks = false;
+        let address_id = egui::Id::new("address_bar");
+        let mut address_rect = egui::Rect::NOTHING;
+        let mut accept_address = None;
+        
         // Use default (dark) frame for the UI elements
         egui::CentralPanel::default().show(ctx, |ui| {
-            
+            self.tab_strip(ui, ctx);
             
             // URL input field with navigation buttons
             ui.horizontal(|ui| {
+                let can_go_back = self.tabs[self.active_tab].navigation.can_go_back();
+                let can_go_forward = self.tabs[self.active_tab].navigation.can_go_forward();
+                
                 // Back button with text-based arrow
-                if self.nav_button(ui, "<-", self.navigation.can_go_back()) {
-                    if let Some(url) = self.navigation.go_back() {
-                        self.url = url.to_string();
-                        self.fetch_url(ctx.clone());
-                    }
+                if self.nav_button(ui, "<-", can_go_back) {
+                    self.active_tab().go_back(ctx, &client);
                 }
                 
                 // Forward button with text-based arrow
-                if self.nav_button(ui, "->", self.navigation.can_go_forward()) {
-                    if let Some(url) = self.navigation.go_forward() {
-                        self.url = url.to_string();
-                        self.fetch_url(ctx.clone());
+                if self.nav_button(ui, "->", can_go_forward) {
+                    self.active_tab().go_forward(ctx, &client);
+                }
+                
+                // Stop while loading, Reload otherwise; Shift+click reloads
+                // without the cache
+                if self.tabs[self.active_tab].is_loading() {
+                    if self.nav_button(ui, "X", true) {
+                        self.active_tab().stop();
                     }
+                } else if self.nav_button(ui, "⟳", true) {
+                    let hard = ui.input(|i| i.modifiers.shift);
+                    self.active_tab().reload(ctx, &client, hard);
                 }
                 
+                let tab = &mut self.tabs[self.active_tab];
                 ui.label("URL:");
-                let response = ui.text_edit_singleline(&mut self.url);
                 
-                // Load button
+                // Arrow keys move through the suggestions rather than the cursor
+                if self.address_bar.open && ui.memory(|memory| memory.has_focus(address_id)) {
+                    let (down, up) = ui.input_mut(|i| {
+                        (
+                            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                        )
+                    });
+                    if down {
+                        self.address_bar.move_selection(1);
+                    }
+                    if up {
+                        self.address_bar.move_selection(-1);
+                    }
+                }
+                let response = ui.add(egui::TextEdit::singleline(&mut tab.url).id(address_id));
+                address_rect = response.rect;
+                if response.changed() {
+                    self.address_bar.open = true;
+                }
+                
+                // Load button, or Enter in the text field
+                let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if entered {
+                    accept_address = Some(self.address_bar.selected_suggestion().cloned());
+                }
                 if ui.button("Load").clicked() {
-                    self.navigation.add_url(self.url.clone());
-                    self.fetch_url(ctx.clone());
+                    accept_address = Some(None);
                 }
                 
-                // Automatically load when Enter is pressed in the text field
-                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                    self.navigation.add_url(self.url.clone());
-                    self.fetch_url(ctx.clone());
+                // Bookmark the page; for a bookmarked page, open the manager
+                let page_url = tab.document.as_ref().map(|document| document.response.url.clone()).filter(|url| is_fetchable(url));
+                if let Some(url) = page_url {
+                    if self.bookmarks.find_url(&url).is_some() {
+                        if ui.button("Bookmarked").on_hover_text("Edit bookmarks").clicked() {
+                            open_bookmarks = true;
+                        }
+                    } else if ui.button("Bookmark").clicked() {
+                        let title = tab.title();
+                        self.bookmarks.add_bookmark(BAR_ID, &title, &url, unix_now());
+                    }
+                }
+            });
+            
+            // Thin progress line under the URL row while the page loads
+            let tab = &self.tabs[self.active_tab];
+            ui_components::render_progress_line(ui, tab.load_fraction(self.pending_images(tab) + tab.pending_stylesheets()));
+            
+            // Bookmarks bar
+            ui.horizontal(|ui| {
+                bar_link = ui_components::render_bookmarks_bar(ui, &self.bookmarks.bar);
+                ui.separator();
+                if ui.button("Bookmarks").clicked() {
+                    open_bookmarks = true;
                 }
             });
             
@@ -237,237 +580,183 @@ impl eframe::App for EguiBrowser {
             ui.horizontal(|ui| {
                 ui.label("User Agent:");
                 if ui.button("Firefox").clicked() {
-                    self.user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0".to_string();
+                    self.client.user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0".to_string();
                 }
                 if ui.button("Chrome").clicked() {
-                    self.user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36".to_string();
+                    self.client.user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36".to_string();
                 }
                 if ui.button("Safari").clicked() {
-                    self.user_agent = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15".to_string();
+                    self.client.user_agent = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15".to_string();
                 }
                 if ui.button("Edge").clicked() {
-                    self.user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36 Edg/124.0.0.0".to_string();
+                    self.client.user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36 Edg/124.0.0.0".to_string();
+                }
+                ui.separator();
+                ui.toggle_value(&mut self.show_cookies, "Cookies");
+                ui.toggle_value(&mut self.show_search_engines, "Search Engines");
+                if ui.button("History").clicked() {
+                    open_history = true;
                 }
             });
             
             // Show current user agent
-            ui.label(format!("Current: {}", self.user_agent));
+            ui.label(format!("Current: {}", self.client.user_agent));
+            
+            let tab = &self.tabs[self.active_tab];
             
             // Show error message if any
-            if let Some(error) = &self.error_message {
+            if let Some(error) = &tab.error_message {
                 ui.colored_label(egui::Color32::RED, error);
             }
             
-            // Check if the promise is complete
-            if let Some(promise) = &self.fetch_promise {
-                if let Some(result) = promise.ready() {
-                    match result {
-                        Ok(response) => {
-                            // Try to convert the response bytes to a string
-                            match String::from_utf8(response.bytes.clone()) {
-                                Ok(text) => {
-                                    // Preprocess the HTML to remove problematic content
-                                    let processed_html = self.preprocess_html(&text);
-                                    self.html_content = Some(processed_html);
-                                    self.error_message = None;
-                                }
-                                Err(_) => {
-                                    self.error_message = Some("Failed to decode response as UTF-8".to_string());
-                                }
+            if tab.is_loading() {
+                ui.spinner(); // Show a spinner while loading
+            }
+            
+            // Show the page with the viewer for its content type
+            if let Some(document) = &tab.document {
+                if let Some(encoding) = document.encoding_description() {
+                    ui.label(format!("Encoding: {}", encoding));
+                }
+                
+                match &document.content {
+                    PageContent::Html(page) => {
+                        // First display rendered HTML
+                        match &page.parse_error {
+                            None => {
+                                ui_components::render_html_content(ui, document, page, &self.html_renderer, tab.scroll_id());
+                            }
+                            Some(err) => {
+                                ui.colored_label(egui::Color32::RED, err);
                             }
-                            // Clear the promise
-                            self.fetch_promise = None;
-                        }
-                        Err(err) => {
-                            self.error_message = Some(format!("Error: {}", err));
-                            self.fetch_promise = None;
                         }
+                        
+                        // Then display raw HTML below with toggle
+                        ui_components::render_raw_html_view(ui, &page.source, &mut self.show_raw_html);
                     }
-                } else {
-                    ui.spinner(); // Show a spinner while loading
-                }
-            }
-            
-            // Show HTML content
-            if let Some(html) = &self.html_content {
-                // First display rendered HTML
-                match html_parser::Dom::parse(html) {
-                    Ok(dom) => {
-                        ui_components::render_html_content(ui, &dom, &self.html_renderer);
+                    PageContent::Image => {
+                        ui_components::render_image_view(ui, &document.response, self.get_image(&document.response.url), tab.scroll_id());
+                    }
+                    PageContent::PlainText(text) => {
+                        ui_components::render_text_view(ui, text, tab.scroll_id());
                     }
-                    Err(err) => {
-                        ui.colored_label(egui::Color32::RED, format!("Failed to parse HTML: {}", err));
+                    PageContent::Unsupported { mime, bytes } => {
+                        ui_components::render_unsupported_view(ui, &document.response, mime, bytes);
+                    }
+                    PageContent::About(AboutPage::History) => {
+                        history_link = ui_components::render_history_page(ui, &mut self.history, &mut self.history_view, unix_now(), tab.scroll_id());
+                    }
+                    PageContent::About(AboutPage::Bookmarks) => {
+                        history_link = ui_components::render_bookmarks_page(ui, &mut self.bookmarks, tab.scroll_id());
                     }
                 }
-                
-                // Then display raw HTML below with toggle
-                ui_components::render_raw_html_view(ui, html, &mut self.show_raw_html);
+                if !matches!(document.content, PageContent::About(_)) {
+                    ui_components::render_response_info(ui, &document.response);
+                }
             }
         });
-    }
-}
-
-impl EguiBrowser {
-    // Preprocess HTML to remove scripts, styles, and simplify structure
-    fn preprocess_html(&self, html: &str) -> String {
-        // Check if the HTML is too large
-        if html.len() > 1_000_000 {
-            // For very large HTML, do a more aggressive truncation
-            let truncated = &html[0..500_000];
-            if let Some(end_pos) = truncated.rfind("</div>") {
-                // Just return a simplified version
-                return format!("<html><body><h1>Page content simplified</h1><p>The page was too large to display fully.</p>{}</body></html>", 
-                              &truncated[0..end_pos+6]);
-            } else {
-                return "<html><body><h1>Page too large</h1><p>The page was too large to display.</p></body></html>".to_string();
-            }
-        }
-        
-        // Use a more efficient approach for large HTML
-        let mut processed = String::with_capacity(html.len() / 2);
-        let mut in_script = false;
-        let mut in_style = false;
-        let mut skip_until_index = 0;
         
-        // Process the HTML in a single pass
-        let chars: Vec<char> = html.chars().collect();
-        let mut i = 0;
-        
-        while i < chars.len() {
-            if i < skip_until_index {
-                i += 1;
-                continue;
+        // Suggestions for what's being typed, kept open while the pointer is
+        // on them so a click can land
+        if self.address_bar.open && accept_address.is_none() {
+            let typed = self.tabs[self.active_tab].url.clone();
+            if typed != self.address_bar.typed {
+                let tabs: Vec<(u64, String, String)> = self
+                    .tabs
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, tab)| *index != self.active_tab && !tab.url.is_empty())
+                    .map(|(_, tab)| (tab.id, tab.title(), tab.url.clone()))
+                    .collect();
+                self.address_bar.suggestions = suggestions(&typed, &self.search_engines, &self.history, &self.bookmarks, &tabs);
+                self.address_bar.selected = None;
+                self.address_bar.typed = typed;
             }
-            
-            // Check for script start
-            if !in_script && !in_style && i + 7 < chars.len() && 
-               &chars[i..i+7].iter().collect::<String>() == "<script" {
-                in_script = true;
-                // Find the script end
-                let rest = chars[i..].iter().collect::<String>();
-                if let Some(end_pos) = rest.find("</script>") {
-                    skip_until_index = i + end_pos + 9;
-                    i += 1;
-                    continue;
-                }
-            }
-            
-            // Check for style start
-            if !in_script && !in_style && i + 6 < chars.len() && 
-               &chars[i..i+6].iter().collect::<String>() == "<style" {
-                in_style = true;
-                // Find the style end
-                let rest = chars[i..].iter().collect::<String>();
-                if let Some(end_pos) = rest.find("</style>") {
-                    skip_until_index = i + end_pos + 8;
-                    i += 1;
-                    continue;
-                }
-            }
-            
-            // Skip script and style content
-            if in_script {
-                if i + 9 < chars.len() && &chars[i..i+9].iter().collect::<String>() == "</script>" {
-                    in_script = false;
-                    i += 9;
-                } else {
-                    i += 1;
-                }
-                continue;
+            let (clicked, hovered) = ui_components::render_suggestions(ctx, address_rect, &self.address_bar.suggestions, self.address_bar.selected);
+            if let Some(index) = clicked {
+                accept_address = Some(self.address_bar.suggestions.get(index).cloned());
+            } else if !hovered && !ctx.memory(|memory| memory.has_focus(address_id)) {
+                self.address_bar.close();
             }
-            
-            if in_style {
-                if i + 8 < chars.len() && &chars[i..i+8].iter().collect::<String>() == "</style>" {
-                    in_style = false;
-                    i += 8;
-                } else {
-                    i += 1;
-                }
-                continue;
+        }
+        if let Some(suggestion) = accept_address {
+            self.accept_address(ctx, suggestion);
+        }
+        
+        // Pages picked on about:history or about:bookmarks load in its tab
+        if let Some(url) = history_link {
+            self.active_tab().navigate(ctx, url, &client);
+        }
+        if let Some(link) = bar_link {
+            if link.new_tab {
+                self.open_tab(ctx, link.url, false);
+            } else {
+                self.active_tab().navigate(ctx, link.url, &client);
             }
-            
-            // Add current character to processed output
-            processed.push(chars[i]);
-            i += 1;
+        }
+        if open_history || ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::H)) {
+            self.open_tab(ctx, "about:history".to_string(), true);
+        }
+        if open_bookmarks || ctx.input(|i| i.modifiers.command && i.modifiers.shift && i.key_pressed(egui::Key::O)) {
+            self.open_tab(ctx, "about:bookmarks".to_string(), true);
         }
         
-        processed
+        ui_components::render_cookie_settings(ctx, &mut self.show_cookies, &self.client.cookies);
+        ui_components::render_search_engine_settings(ctx, &mut self.show_search_engines, &mut self.search_engines);
     }
+}
 
-    // Start a new HTTP request to fetch the URL with timeout
-    fn fetch_url(&mut self, ctx: Context) {
-        let url = self.url.clone();
-        let user_agent = self.user_agent.clone();
-        
-        // Create request with user agent
-        let mut request = ehttp::Request::get(&url);
-        request.headers.insert("User-Agent".to_string(), user_agent);
+impl EguiBrowser {
+    // Turn a completed response into a document, dispatching on its content type
+    fn build_document(&mut self, ctx: &Context, response: ehttp::Response) -> Document {
+        let info = ResponseInfo::from_response(&response);
         
-        // Add a timeout to prevent freezing
-        let promise = Promise::spawn_thread("fetch_url", move || {
-            // Use a more robust fetching approach with timeout
-            let client = ureq::builder()
-                .timeout_connect(std::time::Duration::from_secs(5))
-                .timeout_read(std::time::Duration::from_secs(10))
-                .build();
-            
-            match client.get(&url)
-                    .set("User-Agent", &request.headers.get("User-Agent").unwrap_or(&String::new()))
-                    .call() {
-                Ok(response) => {
-                    // Save response status before consuming the response
-                    let status = response.status();
-                    let status_text = response.status_text().to_string();
-                    
-                    // Read response body with size limit
-                    let mut bytes = Vec::new();
-                    // Limit to 2MB to prevent memory issues
-                    const MAX_SIZE: usize = 2 * 1024 * 1024;
-                    let mut reader = response.into_reader();
-                    let mut buffer = [0; 8192];
-                    let mut total_read = 0;
-                    
-                    loop {
-                        match reader.read(&mut buffer) {
-                            Ok(0) => break, // EOF
-                            Ok(n) => {
-                                total_read += n;
-                                if total_read <= MAX_SIZE {
-                                    bytes.extend_from_slice(&buffer[..n]);
-                                } else {
-                                    // We've read enough, stop here
-                                    break;
-                                }
-                            }
-                            Err(_) => return Err("Error reading response".to_string()),
-                        }
-                    }
-                    
-                    // Create a simplified response if too large
-                    if total_read > MAX_SIZE {
-                        bytes = "<html><body><h1>Content truncated</h1><p>The page was too large to display fully.</p></body></html>"
-                            .as_bytes()
-                            .to_vec();
+        let mut encoding = None;
+        let content = match classify(info.content_type(), &response.bytes) {
+            ContentKind::Html => {
+                // Decode using the BOM, Content-Type charset or <meta> prescan
+                let decoded = decode_body(&response.bytes, info.content_type());
+                encoding = Some((decoded.encoding, decoded.source));
+                // Error pages with an empty body still get something to show
+                let text = if info.is_error() && decoded.text.trim().is_empty() {
+                    format!(
+                        "<html><body><h1>{}</h1><p>The server returned an error for {}</p></body></html>",
+                        escape_html(&info.status_line()),
+                        escape_html(&info.url)
+                    )
+                } else {
+                    decoded.text
+                };
+                // Preprocess the HTML to remove problematic content
+                let processed_html = preprocess_html(&text);
+                PageContent::Html(HtmlPage::new(&info.url, processed_html, &self.html_renderer))
+            }
+            ContentKind::PlainText => {
+                let decoded = decode_body(&response.bytes, info.content_type());
+                encoding = Some((decoded.encoding, decoded.source));
+                PageContent::PlainText(decoded.text)
+            }
+            ContentKind::Image => {
+                if self.load_image_texture(ctx, &info.url, &response.bytes) {
+                    PageContent::Image
+                } else {
+                    PageContent::Unsupported {
+                        mime: info.content_type().map(mime_essence).unwrap_or_default(),
+                        bytes: response.bytes,
                     }
-                    
-                    // Create ehttp response with all required fields
-                    Ok(ehttp::Response {
-                        url: url.clone(),
-                        status,
-                        status_text,
-                        bytes,
-                        ok: status >= 200 && status < 300,
-                        headers: Default::default(), // Use an empty default header map
-                    })
-                }
-                Err(err) => {
-                    Err(format!("Failed to fetch URL: {}", err))
                 }
             }
-        });
+            ContentKind::Unsupported(mime) => PageContent::Unsupported {
+                mime,
+                bytes: response.bytes,
+            },
+        };
         
-        self.fetch_promise = Some(promise);
-        ctx.request_repaint(); // Request a repaint to show the spinner
+        Document {
+            response: info,
+            content,
+            encoding,
+        }
     }
     
     // Fetch image from URL and add to cache
@@ -477,60 +766,43 @@ impl EguiBrowser {
             return;
         }
         
-        // Resolve relative URLs
-        let full_url = if image_url.starts_with("http") {
-            image_url.clone()
-        } else if image_url.starts_with("//") {
-            // Protocol-relative URL (//example.com/image.png)
-            if self.url.starts_with("https") {
-                format!("https:{}", image_url)
-            } else {
-                format!("http:{}", image_url)
-            }
-        } else if image_url.starts_with('/') {
-            // Absolute path from domain root
-            let base_url = self.url.clone();
-            // Extract domain with protocol (http://example.com)
-            if let Some(protocol_end) = base_url.find("://") {
-                // Extract domain part
-                if let Some(domain_end) = base_url[protocol_end+3..].find('/') {
-                    format!("{}{}", &base_url[..protocol_end+3+domain_end], image_url)
-                } else {
-                    // No path component in base URL
-                    format!("{}{}", base_url, image_url)
-                }
-            } else {
-                // Fallback if URL doesn't have protocol
-                format!("{}{}", base_url, image_url)
-            }
-        } else {
-            // Relative path
-            let base_url = self.url.clone();
-            if let Some(last_slash) = base_url.rfind('/') {
-                // Make sure we're not just getting the protocol slashes
-                if last_slash > 8 {  // Beyond "http://" or "https://"
-                    format!("{}/{}", &base_url[..last_slash], image_url)
-                } else {
-                    // Append to the domain
-                    format!("{}/{}", base_url, image_url)
-                }
-            } else {
-                format!("{}/{}", base_url, image_url)
-            }
-        };
+        // Callers resolve against the document base URL; skip anything we can't fetch
+        if !is_fetchable(&image_url) {
+            return;
+        }
         
-        // Create the request with the user agent
-        let mut request = ehttp::Request::get(&full_url);
-        request.headers.insert("User-Agent".to_string(), self.user_agent.clone());
+        // A stopped page loads no more images until it's reloaded
+        let tab = &self.tabs[self.active_tab];
+        let cancel = tab.cancel_flag();
+        if cancel.is_cancelled() {
+            return;
+        }
+        
+        // Images carry the cookies of the page showing them, not of whatever
+        // is being typed into the address bar
+        let Some(page_url) = tab.document.as_ref().map(|document| document.response.url.clone()) else {
+            return;
+        };
+        let request = PageRequest::get(image_url.clone()).initiated_by(Initiator::Subresource(page_url));
+        let client = self.client.clone();
+        let (tab_id, navigation_id) = (tab.id, tab.navigation_id);
         
         let ctx_clone = ctx.clone();
         let promise = Promise::spawn_thread("fetch_image", move || {
-            let result = ehttp::fetch_blocking(&request);
+            let result = fetch_cancellable(&request, &client, &cancel, &LoadProgress::default());
             ctx_clone.request_repaint();
             result
         });
         
-        self.image_promises.insert(image_url, promise);
+        self.image_promises.insert(image_url, ImageFetch { tab_id, navigation_id, promise });
+    }
+    
+    // Images still being fetched for the tab's current navigation
+    fn pending_images(&self, tab: &Tab) -> usize {
+        self.image_promises
+            .values()
+            .filter(|fetch| fetch.tab_id == tab.id && fetch.navigation_id == tab.navigation_id)
+            .count()
     }
     
     // Process loaded images and add to texture cache
@@ -538,33 +810,22 @@ impl EguiBrowser {
         let mut completed_urls = Vec::new();
         
         // Check all image promises
-        for (url, promise) in &self.image_promises {
-            if let Some(result) = promise.ready() {
+        for (url, fetch) in &self.image_promises {
+            if fetch.promise.ready().is_some() {
                 completed_urls.push(url.clone());
-                
-                match result {
+            }
+        }
+        
+        for url in completed_urls {
+            if let Some(fetch) = self.image_promises.remove(&url) {
+                // Drop images for a tab that has since navigated, stopped or closed
+                let current = self.tabs.iter().any(|tab| tab.id == fetch.tab_id && tab.navigation_id == fetch.navigation_id);
+                if !current {
+                    continue;
+                }
+                match fetch.promise.block_and_take() {
                     Ok(response) => {
-                        // Try to load the image
-                        if let Ok(image) = image::load_from_memory(&response.bytes) {
-                            let image = image.to_rgba8();
-                            let dimensions = image.dimensions();
-                            let image_data = egui::ColorImage::from_rgba_unmultiplied(
-                                [dimensions.0 as usize, dimensions.1 as usize],
-                                &image.into_raw(),
-                            );
-                            
-                            // Add to texture cache
-                            let texture = ctx.load_texture(
-                                url.clone(),
-                                image_data,
-                                Default::default(),
-                            );
-                            
-                            self.image_cache.insert(
-                                url.clone(),
-                                (texture.id(), egui::Vec2::new(dimensions.0 as f32, dimensions.1 as f32)),
-                            );
-                        }
+                        self.load_image_texture(ctx, &url, &response.bytes);
                     }
                     Err(_) => {
                         // Image loading failed - ignore for now
@@ -572,15 +833,32 @@ impl EguiBrowser {
                 }
             }
         }
+    }
+    
+    // Decode image bytes and add them to the texture cache under the given URL
+    fn load_image_texture(&mut self, ctx: &Context, url: &str, bytes: &[u8]) -> bool {
+        let Ok(image) = image::load_from_memory(bytes) else {
+            return false;
+        };
         
-        // Remove completed promises
-        for url in completed_urls {
-            self.image_promises.remove(&url);
-        }
+        let image = image.to_rgba8();
+        let dimensions = image.dimensions();
+        let image_data = egui::ColorImage::from_rgba_unmultiplied(
+            [dimensions.0 as usize, dimensions.1 as usize],
+            &image.into_raw(),
+        );
+        
+        // Add to texture cache; the handle keeps the texture alive
+        let texture = ctx.load_texture(url, image_data, Default::default());
+        self.image_cache.insert(
+            url.to_string(),
+            (texture, egui::Vec2::new(dimensions.0 as f32, dimensions.1 as f32)),
+        );
+        true
     }
     
     // Get image texture if available
     pub fn get_image(&self, url: &str) -> Option<(egui::TextureId, egui::Vec2)> {
-        self.image_cache.get(url).copied()
+        self.image_cache.get(url).map(|(texture, size)| (texture.id(), *size))
     }
 }
\ No newline at end of file
@@ -0,0 +1,624 @@
+// src/bookmarks.rs
+// Bookmarks: a tree of folders under two roots, the bookmarks bar and other
+// bookmarks. They're saved in the Netscape bookmark file format, the HTML
+// every browser imports and exports, so the saved file doubles as an export.
+// Keywords follow Firefox: a bookmark with keyword "w" and URL
+// "https://en.wikipedia.org/wiki/%s" turns "w foo" typed in the address bar
+// into the article for foo.
+use crate::document::escape_html;
+use crate::forms::urlencode_component;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bookmark {
+    // Identifies the bookmark for this run; ids aren't saved
+    pub id: u64,
+    pub title: String,
+    pub url: String,
+    // Empty when the bookmark has no keyword
+    pub keyword: String,
+    // Unix time the bookmark was added
+    pub added: i64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Folder {
+    pub id: u64,
+    pub title: String,
+    pub added: i64,
+    pub children: Vec<BookmarkItem>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BookmarkItem {
+    Bookmark(Bookmark),
+    Folder(Folder),
+}
+
+impl BookmarkItem {
+    pub fn id(&self) -> u64 {
+        match self {
+            BookmarkItem::Bookmark(bookmark) => bookmark.id,
+            BookmarkItem::Folder(folder) => folder.id,
+        }
+    }
+}
+
+pub struct Bookmarks {
+    // Shown under the address bar
+    pub bar: Folder,
+    pub other: Folder,
+    next_id: u64,
+    // Where bookmarks are saved; None keeps them in memory only
+    file: Option<PathBuf>,
+}
+
+// Ids of the two roots, which can't be moved, renamed or deleted
+pub const BAR_ID: u64 = 1;
+pub const OTHER_ID: u64 = 2;
+
+impl Default for Bookmarks {
+    fn default() -> Self {
+        let root = |id: u64, title: &str| Folder {
+            id,
+            title: title.to_string(),
+            added: 0,
+            children: Vec::new(),
+        };
+        Self {
+            bar: root(BAR_ID, "Bookmarks Bar"),
+            other: root(OTHER_ID, "Other Bookmarks"),
+            next_id: OTHER_ID + 1,
+            file: None,
+        }
+    }
+}
+
+impl Bookmarks {
+    // Bookmarks kept in `file`, starting with what's saved there
+    pub fn load(file: PathBuf) -> Self {
+        let mut bookmarks = Self::default();
+        if let Ok(html) = std::fs::read_to_string(&file) {
+            let (bar, other) = parse_bookmark_file(&html);
+            for item in bar {
+                bookmarks.insert(BAR_ID, item);
+            }
+            for item in other {
+                bookmarks.insert(OTHER_ID, item);
+            }
+        }
+        bookmarks.file = Some(file);
+        bookmarks
+    }
+
+    // Write the bookmarks out, e.g. after editing them in place
+    pub fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        // Write a temporary file and rename it, so a crash can't lose bookmarks
+        let temporary = file.with_extension("tmp");
+        if std::fs::write(&temporary, self.export_html()).is_ok() {
+            let _ = std::fs::rename(&temporary, file);
+        }
+    }
+
+    pub fn add_bookmark(&mut self, folder: u64, title: &str, url: &str, now: i64) -> u64 {
+        let bookmark = BookmarkItem::Bookmark(Bookmark {
+            id: 0,
+            title: title.to_string(),
+            url: url.to_string(),
+            keyword: String::new(),
+            added: now,
+        });
+        let id = self.insert(folder, bookmark);
+        self.save();
+        id
+    }
+
+    pub fn add_folder(&mut self, parent: u64, title: &str, now: i64) -> u64 {
+        let folder = BookmarkItem::Folder(Folder {
+            id: 0,
+            title: title.to_string(),
+            added: now,
+            children: Vec::new(),
+        });
+        let id = self.insert(parent, folder);
+        self.save();
+        id
+    }
+
+    // Add an item at the end of a folder, giving it and its children fresh ids
+    fn insert(&mut self, folder: u64, mut item: BookmarkItem) -> u64 {
+        self.assign_ids(&mut item);
+        let id = item.id();
+        let folder = if self.folder_mut(folder).is_some() { folder } else { OTHER_ID };
+        if let Some(folder) = self.folder_mut(folder) {
+            folder.children.push(item);
+        }
+        id
+    }
+
+    fn assign_ids(&mut self, item: &mut BookmarkItem) {
+        self.next_id += 1;
+        match item {
+            BookmarkItem::Bookmark(bookmark) => bookmark.id = self.next_id,
+            BookmarkItem::Folder(folder) => {
+                folder.id = self.next_id;
+                for child in &mut folder.children {
+                    self.assign_ids(child);
+                }
+            }
+        }
+    }
+
+    pub fn folder_mut(&mut self, id: u64) -> Option<&mut Folder> {
+        fn find(folder: &mut Folder, id: u64) -> Option<&mut Folder> {
+            if folder.id == id {
+                return Some(folder);
+            }
+            folder.children.iter_mut().find_map(|child| match child {
+                BookmarkItem::Folder(child) => find(child, id),
+                BookmarkItem::Bookmark(_) => None,
+            })
+        }
+        find(&mut self.bar, id).or_else(|| find(&mut self.other, id))
+    }
+
+    // Every bookmark, in tree order
+    pub fn all(&self) -> Vec<&Bookmark> {
+        fn collect<'a>(items: &'a [BookmarkItem], bookmarks: &mut Vec<&'a Bookmark>) {
+            for item in items {
+                match item {
+                    BookmarkItem::Bookmark(bookmark) => bookmarks.push(bookmark),
+                    BookmarkItem::Folder(folder) => collect(&folder.children, bookmarks),
+                }
+            }
+        }
+        let mut bookmarks = Vec::new();
+        collect(&self.bar.children, &mut bookmarks);
+        collect(&self.other.children, &mut bookmarks);
+        bookmarks
+    }
+
+    // The first bookmark for a URL, to show whether the current page is bookmarked
+    pub fn find_url(&self, url: &str) -> Option<&Bookmark> {
+        self.all().into_iter().find(|bookmark| bookmark.url == url)
+    }
+
+    // Take an item out of the tree, wherever it is
+    fn take(&mut self, id: u64) -> Option<BookmarkItem> {
+        fn take_from(folder: &mut Folder, id: u64) -> Option<BookmarkItem> {
+            if let Some(index) = folder.children.iter().position(|item| item.id() == id) {
+                return Some(folder.children.remove(index));
+            }
+            folder.children.iter_mut().find_map(|child| match child {
+                BookmarkItem::Folder(child) => take_from(child, id),
+                BookmarkItem::Bookmark(_) => None,
+            })
+        }
+        take_from(&mut self.bar, id).or_else(|| take_from(&mut self.other, id))
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        self.take(id);
+        self.save();
+    }
+
+    // Move an item to the end of a folder. A folder can't go inside itself.
+    pub fn move_to(&mut self, id: u64, folder: u64) {
+        let inside_itself = match self.folder_mut(id) {
+            Some(moved) => contains_folder(moved, folder),
+            None => false,
+        };
+        if inside_itself || self.folder_mut(folder).is_none() {
+            return;
+        }
+        if let Some(item) = self.take(id) {
+            if let Some(folder) = self.folder_mut(folder) {
+                folder.children.push(item);
+            }
+        }
+        self.save();
+    }
+
+    // Move an item up (-1) or down (+1) within its folder
+    pub fn move_by(&mut self, id: u64, offset: isize) {
+        fn shift(folder: &mut Folder, id: u64, offset: isize) -> bool {
+            if let Some(index) = folder.children.iter().position(|item| item.id() == id) {
+                let target = index as isize + offset;
+                if (0..folder.children.len() as isize).contains(&target) {
+                    folder.children.swap(index, target as usize);
+                }
+                return true;
+            }
+            folder.children.iter_mut().any(|child| match child {
+                BookmarkItem::Folder(child) => shift(child, id, offset),
+                BookmarkItem::Bookmark(_) => false,
+            })
+        }
+        if shift(&mut self.bar, id, offset) || shift(&mut self.other, id, offset) {
+            self.save();
+        }
+    }
+
+    // Every folder with its depth, for "move to" choices
+    pub fn folders(&self) -> Vec<(u64, String, usize)> {
+        fn collect(folder: &Folder, depth: usize, folders: &mut Vec<(u64, String, usize)>) {
+            folders.push((folder.id, folder.title.clone(), depth));
+            for child in &folder.children {
+                if let BookmarkItem::Folder(child) = child {
+                    collect(child, depth + 1, folders);
+                }
+            }
+        }
+        let mut folders = Vec::new();
+        collect(&self.bar, 0, &mut folders);
+        collect(&self.other, 0, &mut folders);
+        folders
+    }
+
+    // Expand "keyword arguments" typed in the address bar into the keyword
+    // bookmark's URL, with %s replaced by the encoded arguments
+    pub fn expand_keyword(&self, input: &str) -> Option<String> {
+        let input = input.trim();
+        let (keyword, arguments) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let bookmark = self.find_keyword(keyword)?;
+        let arguments = arguments.trim();
+        if bookmark.url.contains("%s") || bookmark.url.contains("%S") {
+            Some(bookmark.url.replace("%s", &urlencode_component(arguments)).replace("%S", arguments))
+        } else {
+            arguments.is_empty().then(|| bookmark.url.clone())
+        }
+    }
+
+    fn find_keyword(&self, keyword: &str) -> Option<&Bookmark> {
+        self.all()
+            .into_iter()
+            .find(|bookmark| !bookmark.keyword.is_empty() && bookmark.keyword.eq_ignore_ascii_case(keyword))
+    }
+
+    // Add the bookmarks of a Netscape bookmark file: its toolbar folder to the
+    // bar and everything else to other bookmarks. Returns how many were added.
+    pub fn import_html(&mut self, html: &str) -> usize {
+        let (bar, other) = parse_bookmark_file(html);
+        let count = bar.iter().chain(&other).map(count_bookmarks).sum();
+        for item in bar {
+            self.insert(BAR_ID, item);
+        }
+        for item in other {
+            self.insert(OTHER_ID, item);
+        }
+        self.save();
+        count
+    }
+
+    // The bookmarks as a Netscape bookmark file. Other bookmarks go at the top
+    // level, as Firefox writes its bookmarks menu.
+    pub fn export_html(&self) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+             <!-- This is an automatically generated file.\n     It will be read and overwritten.\n     DO NOT EDIT! -->\n\
+             <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+             <TITLE>Bookmarks</TITLE>\n<H1>Bookmarks</H1>\n<DL><p>\n",
+        );
+        html.push_str(&format!(
+            "    <DT><H3 ADD_DATE=\"{}\" PERSONAL_TOOLBAR_FOLDER=\"true\">{}</H3>\n",
+            self.bar.added,
+            escape_html(&self.bar.title)
+        ));
+        write_items(&mut html, &self.bar.children, 1);
+        for item in &self.other.children {
+            write_item(&mut html, item, 1);
+        }
+        html.push_str("</DL><p>\n");
+        html
+    }
+}
+
+fn contains_folder(folder: &Folder, id: u64) -> bool {
+    folder.id == id
+        || folder.children.iter().any(|child| match child {
+            BookmarkItem::Folder(child) => contains_folder(child, id),
+            BookmarkItem::Bookmark(_) => false,
+        })
+}
+
+fn count_bookmarks(item: &BookmarkItem) -> usize {
+    match item {
+        BookmarkItem::Bookmark(_) => 1,
+        BookmarkItem::Folder(folder) => folder.children.iter().map(count_bookmarks).sum(),
+    }
+}
+
+fn write_items(html: &mut String, items: &[BookmarkItem], depth: usize) {
+    let indent = "    ".repeat(depth);
+    html.push_str(&format!("{}<DL><p>\n", indent));
+    for item in items {
+        write_item(html, item, depth + 1);
+    }
+    html.push_str(&format!("{}</DL><p>\n", indent));
+}
+
+fn write_item(html: &mut String, item: &BookmarkItem, depth: usize) {
+    let indent = "    ".repeat(depth);
+    match item {
+        BookmarkItem::Bookmark(bookmark) => {
+            let keyword = if bookmark.keyword.is_empty() {
+                String::new()
+            } else {
+                format!(" SHORTCUTURL=\"{}\"", escape_html(&bookmark.keyword))
+            };
+            html.push_str(&format!(
+                "{}<DT><A HREF=\"{}\" ADD_DATE=\"{}\"{}>{}</A>\n",
+                indent,
+                escape_html(&bookmark.url),
+                bookmark.added,
+                keyword,
+                escape_html(&bookmark.title)
+            ));
+        }
+        BookmarkItem::Folder(folder) => {
+            html.push_str(&format!("{}<DT><H3 ADD_DATE=\"{}\">{}</H3>\n", indent, folder.added, escape_html(&folder.title)));
+            write_items(html, &folder.children, depth);
+        }
+    }
+}
+
+// The character references bookmark files use
+fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest.find(';').map(|end| (&rest[1..end], end));
+        let decoded = reference.and_then(|(name, end)| {
+            let character = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                    None => name.strip_prefix('#').and_then(|decimal| decimal.parse().ok()).and_then(char::from_u32),
+                },
+            };
+            character.map(|character| (character, end))
+        });
+        match decoded {
+            Some((character, end)) => {
+                result.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// A tag of a bookmark file: lower-case name, whether it closes, attributes
+// with lower-case names
+struct Tag {
+    name: String,
+    closing: bool,
+    attributes: HashMap<String, String>,
+}
+
+fn parse_tag(source: &str) -> Tag {
+    let closing = source.starts_with('/');
+    let source = source.trim_start_matches('/');
+    let name_end = source.find(|c: char| c.is_whitespace()).unwrap_or(source.len());
+    let mut attributes = HashMap::new();
+    let mut rest = source[name_end..].trim_start();
+    while !rest.is_empty() {
+        let name_end = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (raw, remaining) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = after[1..].find(quote).map_or(after.len(), |end| end + 1);
+                    (&after[1..end], after.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value = unescape(raw);
+            rest = remaining.trim_start();
+        }
+        if !name.is_empty() {
+            attributes.insert(name, value);
+        }
+    }
+    Tag {
+        name: source[..name_end].to_ascii_lowercase(),
+        closing,
+        attributes,
+    }
+}
+
+// A folder heading read from a bookmark file, and whether it's the toolbar folder
+type FolderHeading = (Folder, bool);
+
+// Parse a Netscape bookmark file into the toolbar folder's items and all
+// other top-level items. The format is loose HTML: <DT> is never closed and
+// a folder's <DL> follows its <H3>.
+fn parse_bookmark_file(html: &str) -> (Vec<BookmarkItem>, Vec<BookmarkItem>) {
+    // Open <DL>s: the folder each belongs to (None at the top) and its items
+    let mut stack: Vec<(Option<FolderHeading>, Vec<BookmarkItem>)> = vec![(None, Vec::new())];
+    // A folder heading waiting for its <DL>
+    let mut pending: Option<FolderHeading> = None;
+    let mut bar = Vec::new();
+    let mut rest = html;
+
+    let flush = |pending: &mut Option<FolderHeading>, items: &mut Vec<BookmarkItem>| {
+        if let Some((folder, _)) = pending.take() {
+            items.push(BookmarkItem::Folder(folder));
+        }
+    };
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>').map(|end| start + end) else {
+            break;
+        };
+        let tag = parse_tag(&rest[start + 1..end]);
+        rest = &rest[end + 1..];
+        let added = tag.attributes.get("add_date").and_then(|date| date.parse().ok()).unwrap_or(0);
+
+        match (tag.name.as_str(), tag.closing) {
+            ("h3", false) | ("a", false) => {
+                let close = format!("</{}", tag.name);
+                let text_end = rest
+                    .match_indices('<')
+                    .map(|(index, _)| index)
+                    .find(|&index| rest.get(index..index + close.len()).is_some_and(|tag| tag.eq_ignore_ascii_case(&close)))
+                    .unwrap_or(rest.len());
+                let title = unescape(rest[..text_end].trim());
+                rest = &rest[text_end..];
+                let items = &mut stack.last_mut().unwrap().1;
+                flush(&mut pending, items);
+                if tag.name == "h3" {
+                    let toolbar = tag.attributes.get("personal_toolbar_folder").is_some_and(|value| value.eq_ignore_ascii_case("true"));
+                    pending = Some((Folder { id: 0, title, added, children: Vec::new() }, toolbar));
+                } else if let Some(url) = tag.attributes.get("href").filter(|url| !url.is_empty()) {
+                    items.push(BookmarkItem::Bookmark(Bookmark {
+                        id: 0,
+                        title,
+                        url: url.clone(),
+                        keyword: tag.attributes.get("shortcuturl").cloned().unwrap_or_default(),
+                        added,
+                    }));
+                }
+            }
+            ("dl", false) => stack.push((pending.take(), Vec::new())),
+            ("dl", true) if stack.len() > 1 => {
+                let (folder, mut items) = stack.pop().unwrap();
+                flush(&mut pending, &mut items);
+                let top_level = stack.len() == 2;
+                let parent = &mut stack.last_mut().unwrap().1;
+                match folder {
+                    // The toolbar folder is only special at the top level
+                    Some((_, true)) if top_level && bar.is_empty() => bar = items,
+                    Some((mut folder, _)) => {
+                        folder.children = items;
+                        parent.push(BookmarkItem::Folder(folder));
+                    }
+                    // The file's own top-level list
+                    None => parent.extend(items),
+                }
+            }
+            _ => {}
+        }
+    }
+    // Unclosed lists still count
+    while let Some((folder, mut items)) = stack.pop() {
+        flush(&mut pending, &mut items);
+        match (folder, stack.last_mut()) {
+            (Some((mut folder, _)), Some((_, parent))) => {
+                folder.children = items;
+                parent.push(BookmarkItem::Folder(folder));
+            }
+            (None, Some((_, parent))) => parent.extend(items),
+            (_, None) => return (bar, items),
+        }
+    }
+    (bar, Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIREFOX_EXPORT: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks Menu</H1>
+<DL><p>
+    <DT><A HREF="https://www.rust-lang.org/" ADD_DATE="1700000000">Rust &amp; Friends</A>
+    <DT><H3 ADD_DATE="1700000001" PERSONAL_TOOLBAR_FOLDER="true">Bookmarks Toolbar</H3>
+    <DL><p>
+        <DT><A HREF="https://en.wikipedia.org/wiki/%s" SHORTCUTURL="w">Wikipedia</A>
+        <DT><H3>Empty</H3>
+    </DL><p>
+    <DT><H3 ADD_DATE="1700000002">Docs</H3>
+    <DD>A description
+    <DL><p>
+        <DT><A HREF="https://docs.rs/">Docs.rs</A>
+    </DL><p>
+</DL>
+"#;
+
+    fn titles(items: &[BookmarkItem]) -> Vec<&str> {
+        items
+            .iter()
+            .map(|item| match item {
+                BookmarkItem::Bookmark(bookmark) => bookmark.title.as_str(),
+                BookmarkItem::Folder(folder) => folder.title.as_str(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn imports_and_exports_bookmark_files() {
+        let mut bookmarks = Bookmarks::default();
+        assert_eq!(bookmarks.import_html(FIREFOX_EXPORT), 3);
+        assert_eq!(titles(&bookmarks.bar.children), ["Wikipedia", "Empty"]);
+        assert_eq!(titles(&bookmarks.other.children), ["Rust & Friends", "Docs"]);
+        let BookmarkItem::Folder(docs) = &bookmarks.other.children[1] else {
+            panic!("Docs should be a folder");
+        };
+        assert_eq!((docs.added, titles(&docs.children)), (1700000002, vec!["Docs.rs"]));
+
+        // What we write reads back the same, ids aside
+        let mut reloaded = Bookmarks::default();
+        reloaded.import_html(&bookmarks.export_html());
+        assert_eq!(reloaded.export_html(), bookmarks.export_html());
+        assert!(bookmarks.export_html().contains(r#"<A HREF="https://www.rust-lang.org/" ADD_DATE="1700000000">Rust &amp; Friends</A>"#));
+    }
+
+    #[test]
+    fn expands_keywords() {
+        let mut bookmarks = Bookmarks::default();
+        bookmarks.import_html(FIREFOX_EXPORT);
+        let id = bookmarks.add_bookmark(OTHER_ID, "Home", "https://example.com/", 0);
+        if let Some(BookmarkItem::Bookmark(home)) = bookmarks.other.children.iter_mut().find(|item| item.id() == id) {
+            home.keyword = "home".to_string();
+        }
+
+        assert_eq!(bookmarks.expand_keyword("w rust lang").as_deref(), Some("https://en.wikipedia.org/wiki/rust+lang"));
+        assert_eq!(bookmarks.expand_keyword("W a&b").as_deref(), Some("https://en.wikipedia.org/wiki/a%26b"));
+        assert_eq!(bookmarks.expand_keyword("home").as_deref(), Some("https://example.com/"));
+        assert_eq!(bookmarks.expand_keyword("home extra"), None);
+        assert_eq!(bookmarks.expand_keyword("x foo"), None);
+    }
+
+    #[test]
+    fn moves_and_removes_items() {
+        let mut bookmarks = Bookmarks::default();
+        let folder = bookmarks.add_folder(BAR_ID, "Folder", 0);
+        let nested = bookmarks.add_folder(folder, "Nested", 0);
+        let a = bookmarks.add_bookmark(BAR_ID, "A", "http://a.test/", 0);
+        bookmarks.add_bookmark(BAR_ID, "B", "http://b.test/", 0);
+
+        bookmarks.move_by(a, 1);
+        assert_eq!(titles(&bookmarks.bar.children), ["Folder", "B", "A"]);
+        bookmarks.move_to(a, nested);
+        assert_eq!(bookmarks.find_url("http://a.test/").map(|bookmark| bookmark.id), Some(a));
+        // A folder can't move into its own subfolder
+        bookmarks.move_to(folder, nested);
+        assert_eq!(titles(&bookmarks.bar.children), ["Folder", "B"]);
+        bookmarks.remove(folder);
+        assert_eq!(bookmarks.find_url("http://a.test/"), None);
+        assert_eq!(bookmarks.folders().len(), 2);
+    }
+}
@@ -0,0 +1,627 @@
+// src/cookies.rs
+// The cookie jar, as described in RFC 6265: parsing Set-Cookie headers,
+// picking the cookies each request carries, and keeping persistent cookies
+// on disk between runs. SameSite, the Secure rules and cookie name prefixes
+// follow the RFC 6265bis draft; cookies that don't give SameSite are Lax.
+use crate::fetch::Initiator;
+use crate::dates::{days_from_civil, days_in_month};
+use crate::url_resolver::{host_of, scheme_of, UrlParts};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+// Limits browsers apply, in the spirit of RFC 6265 section 6.1
+const MAX_PER_DOMAIN: usize = 50;
+const MAX_COOKIES: usize = 3000;
+// Longest lifetime a cookie can ask for: 400 days (RFC 6265bis)
+const MAX_LIFETIME: i64 = 400 * 24 * 60 * 60;
+
+// The jar every request of the browser shares
+pub type SharedCookieJar = Arc<Mutex<CookieJar>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SameSite {
+    // Only sent on requests started from the same site
+    Strict,
+    // Also sent when following a link to the site from elsewhere
+    Lax,
+    // Sent with every request
+    None,
+}
+
+impl SameSite {
+    fn name(self) -> &'static str {
+        match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "strict" => Some(SameSite::Strict),
+            "lax" => Some(SameSite::Lax),
+            "none" => Some(SameSite::None),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    // Lower-case, without a leading dot
+    pub domain: String,
+    // Set without a Domain attribute, so only sent to that exact host
+    pub host_only: bool,
+    pub path: String,
+    // Unix time; None for a session cookie, which is gone when the browser exits
+    pub expires: Option<i64>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: SameSite,
+    pub creation: i64,
+    pub last_access: i64,
+}
+
+// A parsed Set-Cookie header (RFC 6265 section 5.2)
+#[derive(Debug, Default, PartialEq)]
+struct SetCookie {
+    name: String,
+    value: String,
+    expires: Option<i64>,
+    max_age: Option<i64>,
+    domain: Option<String>,
+    path: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+#[derive(Default)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+    // Where persistent cookies are saved; None keeps them in memory only
+    file: Option<PathBuf>,
+}
+
+impl CookieJar {
+    // A jar kept in `file`, starting with the cookies saved there
+    pub fn load(file: PathBuf) -> Self {
+        let now = unix_now();
+        let cookies = std::fs::read_to_string(&file)
+            .map(|text| text.lines().filter_map(parse_saved_cookie).filter(|cookie| !cookie.is_expired(now)).collect())
+            .unwrap_or_default();
+        Self { cookies, file: Some(file) }
+    }
+
+    pub fn shared(self) -> SharedCookieJar {
+        Arc::new(Mutex::new(self))
+    }
+
+    // Write the persistent cookies out; session cookies are never saved
+    fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let mut text = String::from("# egui_browser cookies: domain, host-only, path, secure, http-only, samesite, expires, created, name, value\n");
+        for cookie in &self.cookies {
+            let Some(expires) = cookie.expires else {
+                continue;
+            };
+            let flag = |on: bool| if on { "TRUE" } else { "FALSE" };
+            text.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                cookie.domain,
+                flag(cookie.host_only),
+                cookie.path,
+                flag(cookie.secure),
+                flag(cookie.http_only),
+                cookie.same_site.name(),
+                expires,
+                cookie.creation,
+                cookie.name,
+                cookie.value
+            ));
+        }
+        // Write a temporary file and rename it, so a crash can't leave half a jar
+        let temporary = file.with_extension("tmp");
+        if std::fs::write(&temporary, text).is_ok() {
+            let _ = std::fs::rename(&temporary, file);
+        }
+    }
+
+    // Take in the Set-Cookie headers of a response to `url`
+    pub fn store_response(&mut self, url: &str, headers: &[String], initiator: &Initiator, now: i64) {
+        let mut changed = false;
+        for header in headers {
+            changed |= self.store(url, header, initiator, now);
+        }
+        if changed {
+            self.save();
+        }
+    }
+
+    // The storage model of RFC 6265 section 5.3. Returns whether the jar changed.
+    fn store(&mut self, url: &str, header: &str, initiator: &Initiator, now: i64) -> bool {
+        let (Some(set), Some(host)) = (parse_set_cookie(header), host_of(url)) else {
+            return false;
+        };
+        let secure_origin = is_secure_origin(url);
+
+        // Max-Age wins over Expires; either is capped at 400 days
+        let expires = match (set.max_age, set.expires) {
+            (Some(max_age), _) if max_age <= 0 => Some(i64::MIN),
+            (Some(max_age), _) => Some(now.saturating_add(max_age.min(MAX_LIFETIME))),
+            (None, Some(expires)) => Some(expires.min(now + MAX_LIFETIME)),
+            (None, None) => None,
+        };
+
+        // A Domain attribute widens the cookie to subdomains, but only for a
+        // domain the host is in. Single labels stand in for public suffixes.
+        let (domain, host_only) = match set.domain {
+            Some(domain) if domain == host => (domain, false),
+            Some(domain) if !domain_matches(&host, &domain) || !domain.contains('.') => return false,
+            Some(domain) => (domain, false),
+            None => (host, true),
+        };
+        let path = set.path.unwrap_or_else(|| default_path(url));
+        let same_site = set.same_site.unwrap_or(SameSite::Lax);
+
+        // Secure cookies come only from secure origins, SameSite=None ones must
+        // be Secure, and a cross-site subresource can't set same-site cookies
+        if (set.secure && !secure_origin) || (same_site == SameSite::None && !set.secure) {
+            return false;
+        }
+        if let Initiator::Subresource(document) = initiator {
+            if same_site != SameSite::None && !is_same_site(url, document) {
+                return false;
+            }
+        }
+        // Name prefixes promise how the cookie was set
+        let name = set.name.to_ascii_lowercase();
+        if (name.starts_with("__secure-") || name.starts_with("__host-")) && !set.secure {
+            return false;
+        }
+        if name.starts_with("__host-") && (!host_only || path != "/") {
+            return false;
+        }
+
+        let cookie = Cookie {
+            name: set.name,
+            value: set.value,
+            domain,
+            host_only,
+            path,
+            expires,
+            secure: set.secure,
+            http_only: set.http_only,
+            same_site,
+            creation: now,
+            last_access: now,
+        };
+
+        // A replaced cookie keeps its creation time. An insecure origin can't
+        // overwrite a Secure cookie.
+        let existing = self.cookies.iter().position(|old| old.name == cookie.name && old.domain == cookie.domain && old.path == cookie.path);
+        let mut cookie = cookie;
+        if let Some(index) = existing {
+            if self.cookies[index].secure && !secure_origin {
+                return false;
+            }
+            cookie.creation = self.cookies.remove(index).creation;
+        }
+        if cookie.is_expired(now) {
+            return existing.is_some();
+        }
+        self.cookies.push(cookie);
+        self.enforce_limits(now);
+        true
+    }
+
+    // Drop expired cookies, then the least recently used ones over the limits
+    fn enforce_limits(&mut self, now: i64) {
+        self.cookies.retain(|cookie| !cookie.is_expired(now));
+        let mut by_age: Vec<usize> = (0..self.cookies.len()).collect();
+        by_age.sort_by_key(|&index| self.cookies[index].last_access);
+        let mut removed = vec![false; self.cookies.len()];
+        let mut per_domain = std::collections::HashMap::<&str, usize>::new();
+        for cookie in &self.cookies {
+            *per_domain.entry(cookie.domain.as_str()).or_default() += 1;
+        }
+        let mut total = self.cookies.len();
+        for index in by_age {
+            let count = per_domain.get_mut(self.cookies[index].domain.as_str()).unwrap();
+            if *count > MAX_PER_DOMAIN || total > MAX_COOKIES {
+                *count -= 1;
+                total -= 1;
+                removed[index] = true;
+            }
+        }
+        let mut index = 0;
+        self.cookies.retain(|_| {
+            index += 1;
+            !removed[index - 1]
+        });
+    }
+
+    // The Cookie header for a request (RFC 6265 section 5.4), if any cookies
+    // apply. Same-site cookies go only with requests from the same site, or
+    // for Lax ones, with links and GET forms from other sites.
+    pub fn cookie_header(&mut self, url: &str, method: &str, initiator: &Initiator, now: i64) -> Option<String> {
+        let host = host_of(url)?;
+        let path = match UrlParts::parse(url).path {
+            "" => "/",
+            path => path,
+        };
+        let secure_origin = is_secure_origin(url);
+        let same_site = match initiator {
+            Initiator::User => true,
+            Initiator::Page(document) | Initiator::Subresource(document) => is_same_site(url, document),
+        };
+        let top_level_get = !matches!(initiator, Initiator::Subresource(_)) && matches!(method, "GET" | "HEAD");
+
+        self.cookies.retain(|cookie| !cookie.is_expired(now));
+        let mut matching: Vec<&mut Cookie> = self
+            .cookies
+            .iter_mut()
+            .filter(|cookie| {
+                let domain_ok = if cookie.host_only { host == cookie.domain } else { domain_matches(&host, &cookie.domain) };
+                let same_site_ok = same_site
+                    || cookie.same_site == SameSite::None
+                    || (cookie.same_site == SameSite::Lax && top_level_get);
+                domain_ok && path_matches(path, &cookie.path) && (!cookie.secure || secure_origin) && same_site_ok
+            })
+            .collect();
+        if matching.is_empty() {
+            return None;
+        }
+        // Longer paths first, then older cookies
+        matching.sort_by(|a, b| b.path.len().cmp(&a.path.len()).then(a.creation.cmp(&b.creation)));
+        let pairs: Vec<String> = matching
+            .into_iter()
+            .map(|cookie| {
+                cookie.last_access = now;
+                if cookie.name.is_empty() {
+                    cookie.value.clone()
+                } else {
+                    format!("{}={}", cookie.name, cookie.value)
+                }
+            })
+            .collect();
+        Some(pairs.join("; "))
+    }
+
+    // Cookies grouped by site, for the settings panel, sites in order
+    pub fn sites(&self) -> Vec<(String, Vec<Cookie>)> {
+        let mut sites: Vec<(String, Vec<Cookie>)> = Vec::new();
+        let mut cookies = self.cookies.clone();
+        cookies.sort_by(|a, b| site_of(&a.domain).cmp(&site_of(&b.domain)).then(a.name.cmp(&b.name)));
+        for cookie in cookies {
+            let site = site_of(&cookie.domain);
+            match sites.last_mut() {
+                Some((last, group)) if *last == site => group.push(cookie),
+                _ => sites.push((site, vec![cookie])),
+            }
+        }
+        sites
+    }
+
+    pub fn remove(&mut self, cookie: &Cookie) {
+        self.cookies
+            .retain(|kept| !(kept.name == cookie.name && kept.domain == cookie.domain && kept.path == cookie.path));
+        self.save();
+    }
+
+    pub fn remove_site(&mut self, site: &str) {
+        self.cookies.retain(|cookie| site_of(&cookie.domain) != site);
+        self.save();
+    }
+
+    pub fn clear(&mut self) {
+        self.cookies.clear();
+        self.save();
+    }
+}
+
+impl Cookie {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+pub fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+// HTTPS, or the local machine, which is trusted as if it were secure
+fn is_secure_origin(url: &str) -> bool {
+    scheme_of(url).as_deref() == Some("https")
+        || host_of(url).is_some_and(|host| host == "localhost" || host == "127.0.0.1" || host == "[::1]")
+}
+
+// RFC 6265 section 5.2
+fn parse_set_cookie(header: &str) -> Option<SetCookie> {
+    let (pair, attributes) = header.split_once(';').unwrap_or((header, ""));
+    // A pair without '=' is a value with no name (RFC 6265bis)
+    let (name, value) = pair.split_once('=').unwrap_or(("", pair));
+    let (name, value) = (name.trim(), value.trim());
+    if (name.is_empty() && value.is_empty()) || header.chars().any(|c| c.is_control() && c != '\t') {
+        return None;
+    }
+
+    let mut set = SetCookie {
+        name: name.to_string(),
+        value: value.to_string(),
+        ..Default::default()
+    };
+    for attribute in attributes.split(';') {
+        let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+        let value = value.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "expires" => {
+                if let Some(expires) = parse_cookie_date(value) {
+                    set.expires = Some(expires);
+                }
+            }
+            "max-age" => {
+                let digits = value.strip_prefix('-').unwrap_or(value);
+                if !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit()) {
+                    // Too many digits to fit is as good as forever
+                    let seconds = digits.parse::<i64>().unwrap_or(i64::MAX);
+                    set.max_age = Some(if value.starts_with('-') { -seconds } else { seconds });
+                }
+            }
+            "domain" if !value.is_empty() => {
+                set.domain = Some(value.trim_start_matches('.').to_ascii_lowercase());
+            }
+            // A path that doesn't start with '/' means the default path
+            "path" => set.path = value.starts_with('/').then(|| value.to_string()),
+            "secure" => set.secure = true,
+            "httponly" => set.http_only = true,
+            "samesite" => set.same_site = SameSite::parse(value),
+            _ => {}
+        }
+    }
+    Some(set)
+}
+
+// The cookie date format of RFC 6265 section 5.1.1, which accepts the many
+// date styles servers send. HTTP dates (RFC 9110 section 5.6.7) parse too.
+pub fn parse_cookie_date(value: &str) -> Option<i64> {
+    let is_delimiter = |c: char| matches!(c, '\t' | ' '..='/' | ';'..='@' | '['..='`' | '{'..='~');
+    let (mut time, mut day, mut month, mut year) = (None, None, None, None);
+    for token in value.split(is_delimiter).filter(|token| !token.is_empty()) {
+        if time.is_none() {
+            if let Some(parsed) = parse_time(token) {
+                time = Some(parsed);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some(parsed) = leading_number(token, 1, 2) {
+                day = Some(parsed);
+                continue;
+            }
+        }
+        if month.is_none() {
+            if let Some(parsed) = parse_month(token) {
+                month = Some(parsed);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(parsed) = leading_number(token, 2, 4) {
+                year = Some(parsed);
+            }
+        }
+    }
+
+    let ((hour, minute, second), day, month, mut year) = (time?, day?, month?, year?);
+    // Two-digit years: 70-99 are 19xx, 0-69 are 20xx
+    if (70..=99).contains(&year) {
+        year += 1900;
+    } else if year <= 69 {
+        year += 2000;
+    }
+    let valid = year >= 1601
+        && hour <= 23
+        && minute <= 59
+        && second <= 59
+        && day >= 1
+        && day <= days_in_month(year as i32, month);
+    valid.then(|| days_from_civil(year as i64, month, day) * 86_400 + (hour * 3600 + minute * 60 + second) as i64)
+}
+
+// hh:mm:ss, each part one or two digits, optionally followed by non-digits
+fn parse_time(token: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = token.splitn(3, ':');
+    let (hour, minute, second) = (parts.next()?, parts.next()?, parts.next()?);
+    let field = |part: &str, whole: bool| {
+        let digits = part.bytes().take_while(u8::is_ascii_digit).count();
+        let rest_ok = !whole || digits == part.len();
+        ((1..=2).contains(&digits) && rest_ok).then(|| part[..digits].parse().ok()).flatten()
+    };
+    Some((field(hour, true)?, field(minute, true)?, field(second, false)?))
+}
+
+// A number of min to max digits at the start of a token, not followed by another digit
+fn leading_number(token: &str, min: usize, max: usize) -> Option<u32> {
+    let digits = token.bytes().take_while(u8::is_ascii_digit).count();
+    (min..=max).contains(&digits).then(|| token[..digits].parse().ok()).flatten()
+}
+
+fn parse_month(token: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let prefix = token.get(..3)?.to_ascii_lowercase();
+    MONTHS.iter().position(|month| *month == prefix).map(|index| index as u32 + 1)
+}
+
+// RFC 6265 section 5.1.3: the host is the domain or a subdomain of it.
+// IP addresses only match themselves.
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && !host.starts_with('[')
+            && !host.bytes().all(|byte| byte.is_ascii_digit() || byte == b'.'))
+}
+
+// RFC 6265 section 5.1.4: the directory of the request path
+fn default_path(url: &str) -> String {
+    let path = UrlParts::parse(url).path;
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(last) => path[..last].to_string(),
+    }
+}
+
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+// The site a host belongs to: its last two labels, or three under common
+// country second-level domains like co.uk. Without the public suffix list
+// this is an approximation; IP addresses are their own site.
+pub fn site_of(host: &str) -> String {
+    if host.starts_with('[') || host.bytes().all(|byte| byte.is_ascii_digit() || byte == b'.') {
+        return host.to_string();
+    }
+    let labels: Vec<&str> = host.split('.').collect();
+    let second_level = ["co", "com", "net", "org", "gov", "ac", "edu"];
+    let count = match labels.as_slice() {
+        [.., second, top] if top.len() == 2 && second_level.contains(second) => 3,
+        _ => 2,
+    };
+    labels[labels.len().saturating_sub(count)..].join(".")
+}
+
+fn is_same_site(url: &str, other: &str) -> bool {
+    match (host_of(url), host_of(other)) {
+        (Some(host), Some(other)) => site_of(&host) == site_of(&other),
+        _ => false,
+    }
+}
+
+// A line of the saved jar
+fn parse_saved_cookie(line: &str) -> Option<Cookie> {
+    if line.starts_with('#') {
+        return None;
+    }
+    let fields: Vec<&str> = line.splitn(10, '\t').collect();
+    let [domain, host_only, path, secure, http_only, same_site, expires, creation, name, value] = fields[..] else {
+        return None;
+    };
+    let creation = creation.parse().ok()?;
+    Some(Cookie {
+        name: name.to_string(),
+        value: value.to_string(),
+        domain: domain.to_string(),
+        host_only: host_only == "TRUE",
+        path: path.to_string(),
+        expires: Some(expires.parse().ok()?),
+        secure: secure == "TRUE",
+        http_only: http_only == "TRUE",
+        same_site: SameSite::parse(same_site)?,
+        creation,
+        last_access: creation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn jar_with(url: &str, headers: &[&str]) -> CookieJar {
+        let mut jar = CookieJar::default();
+        let headers: Vec<String> = headers.iter().map(|header| header.to_string()).collect();
+        jar.store_response(url, &headers, &Initiator::User, NOW);
+        jar
+    }
+
+    #[test]
+    fn parses_cookie_dates() {
+        assert_eq!(parse_cookie_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(784111777));
+        assert_eq!(parse_cookie_date("Sunday, 06-Nov-94 08:49:37 GMT"), Some(784111777));
+        assert_eq!(parse_cookie_date("Sun Nov  6 08:49:37 1994"), Some(784111777));
+        assert_eq!(parse_cookie_date("Wed, 31 Feb 2024 00:00:00 GMT"), None);
+        assert_eq!(parse_cookie_date("no date here"), None);
+    }
+
+    #[test]
+    fn matches_domains_and_paths() {
+        let mut jar = jar_with(
+            "http://www.example.com/account/login",
+            &[
+                "sid=1; Path=/",
+                "pref=dark; Domain=.example.com; Path=/; Max-Age=3600",
+                "here=1",
+                "other=1; Domain=other.com",
+                "tld=1; Domain=com",
+            ],
+        );
+        let header = |jar: &mut CookieJar, url: &str| jar.cookie_header(url, "GET", &Initiator::User, NOW + 1);
+
+        // `here` defaults to the /account directory; host-only cookies stay on www
+        assert_eq!(header(&mut jar, "http://www.example.com/account/x").as_deref(), Some("here=1; sid=1; pref=dark"));
+        assert_eq!(header(&mut jar, "http://www.example.com/accounts").as_deref(), Some("sid=1; pref=dark"));
+        assert_eq!(header(&mut jar, "http://shop.example.com/").as_deref(), Some("pref=dark"));
+        assert_eq!(header(&mut jar, "http://other.com/"), None);
+        // Max-Age runs out
+        assert_eq!(jar.cookie_header("http://shop.example.com/", "GET", &Initiator::User, NOW + 3600), None);
+    }
+
+    #[test]
+    fn applies_secure_and_same_site_rules() {
+        let mut jar = jar_with(
+            "https://example.com/",
+            &["lax=1", "strict=1; SameSite=Strict", "none=1; SameSite=None; Secure", "loose=1; SameSite=None", "secret=1; Secure"],
+        );
+        let mut header = |url: &str, method: &str, initiator: Initiator| jar.cookie_header(url, method, &initiator, NOW);
+        let elsewhere = || Initiator::Page("https://news.test/article".to_string());
+
+        assert_eq!(header("http://example.com/", "GET", Initiator::User).as_deref(), Some("lax=1; strict=1"));
+        assert_eq!(header("https://example.com/", "GET", elsewhere()).as_deref(), Some("lax=1; none=1; secret=1"));
+        assert_eq!(header("https://example.com/", "POST", elsewhere()).as_deref(), Some("none=1"));
+        assert_eq!(
+            header("https://example.com/", "GET", Initiator::Subresource("https://news.test/".to_string())).as_deref(),
+            Some("none=1")
+        );
+        assert_eq!(
+            header("https://example.com/", "GET", Initiator::Page("https://www.example.com/".to_string())).as_deref(),
+            Some("lax=1; strict=1; none=1; secret=1")
+        );
+    }
+
+    #[test]
+    fn replaces_and_deletes_cookies() {
+        let mut jar = jar_with("http://example.com/", &["a=1", "b=2; Expires=Thu, 01 Jan 2099 00:00:00 GMT"]);
+        let headers = vec!["a=3".to_string(), "b=; Max-Age=0".to_string(), "__Host-x=1; Secure; Path=/".to_string()];
+        jar.store_response("http://example.com/", &headers, &Initiator::User, NOW + 5);
+
+        assert_eq!(jar.cookie_header("http://example.com/", "GET", &Initiator::User, NOW + 6).as_deref(), Some("a=3"));
+        assert_eq!(jar.cookies[0].creation, NOW);
+    }
+
+    #[test]
+    fn saves_persistent_cookies() {
+        let file = std::env::temp_dir().join(format!("cookies_{}.txt", std::process::id()));
+        let mut jar = CookieJar { file: Some(file.clone()), ..Default::default() };
+        let headers = vec!["session=1".to_string(), "kept=yes; Max-Age=600; HttpOnly; SameSite=Strict".to_string()];
+        jar.store_response("http://example.com/", &headers, &Initiator::User, unix_now());
+
+        let loaded = CookieJar::load(file.clone());
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(loaded.cookies.len(), 1);
+        assert_eq!((loaded.cookies[0].name.as_str(), loaded.cookies[0].same_site), ("kept", SameSite::Strict));
+        assert!(loaded.cookies[0].http_only);
+        assert_eq!(loaded.sites()[0].0, "example.com");
+    }
+}
@@ -0,0 +1,701 @@
+// src/css.rs
+// A small CSS parser: stylesheets from <style> elements and linked files are
+// split into rules with type/class/id selectors joined by descendant and child
+// combinators. Anything fancier (pseudo-classes, attribute selectors, sibling
+// combinators) drops the rule, as a browser would for a selector it can't parse.
+use egui::Color32;
+use std::rc::Rc;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stylesheet {
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub selectors: Vec<Selector>,
+    pub declarations: Vec<Declaration>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Declaration {
+    // Lowercased property name
+    pub property: String,
+    // Value with any !important removed
+    pub value: String,
+    pub important: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selector {
+    // The compound that must match the element itself
+    subject: Compound,
+    // The rest of the selector from right to left, each with the combinator
+    // that joins it to the compound on its right
+    ancestors: Vec<(Combinator, Compound)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Compound {
+    // None for `*` or a compound without a type selector
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+// (id selectors, class selectors, type selectors)
+pub type Specificity = (u32, u32, u32);
+
+// The parts of an element selectors look at
+#[derive(Clone, Debug, Default)]
+pub struct ElementData {
+    pub tag: String,
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+}
+
+impl ElementData {
+    pub fn from_element(element: &html_parser::Element) -> Self {
+        Self {
+            tag: element.name.to_ascii_lowercase(),
+            id: element.id.clone(),
+            classes: element.classes.clone(),
+        }
+    }
+}
+
+pub fn parse_stylesheet(css: &str) -> Stylesheet {
+    let css = strip_comments(css);
+    let mut rules = Vec::new();
+    parse_rules(&css, &mut rules);
+    Stylesheet { rules }
+}
+
+// Parse a declaration block (the inside of `{ ... }`, or a style attribute)
+pub fn parse_declarations(block: &str) -> Vec<Declaration> {
+    split_top_level(block, ';')
+        .into_iter()
+        .filter_map(|declaration| {
+            let (property, value) = declaration.split_once(':')?;
+            let property = property.trim().to_ascii_lowercase();
+            let mut value = value.trim();
+
+            let mut important = false;
+            if let Some(bang) = value.rfind('!') {
+                if value[bang + 1..].trim().eq_ignore_ascii_case("important") {
+                    important = true;
+                    value = value[..bang].trim_end();
+                }
+            }
+
+            if property.is_empty() || value.is_empty() {
+                return None;
+            }
+            Some(Declaration {
+                property,
+                value: value.to_string(),
+                important,
+            })
+        })
+        .collect()
+}
+
+// Declarations from all sheets that apply to an element, in the order they
+// should be applied: normal before !important, then by specificity, then by
+// source order. `ancestors` runs from the root down to the element's parent.
+pub fn matching_declarations<'a>(
+    stylesheets: &'a [Rc<Stylesheet>],
+    element: &ElementData,
+    ancestors: &[ElementData],
+) -> Vec<&'a Declaration> {
+    let mut matched = Vec::new();
+    let mut order = 0;
+    for sheet in stylesheets {
+        for rule in &sheet.rules {
+            order += 1;
+            let specificity = rule
+                .selectors
+                .iter()
+                .filter(|selector| selector.matches(element, ancestors))
+                .map(Selector::specificity)
+                .max();
+            if let Some(specificity) = specificity {
+                for declaration in &rule.declarations {
+                    matched.push((declaration.important, specificity, order, declaration));
+                }
+            }
+        }
+    }
+
+    // Stable sort keeps declaration order within a rule
+    matched.sort_by_key(|(important, specificity, order, _)| (*important, *specificity, *order));
+    matched.into_iter().map(|(_, _, _, declaration)| declaration).collect()
+}
+
+impl Selector {
+    pub fn parse(text: &str) -> Option<Self> {
+        // Put spaces around `>` so every combinator is its own token
+        let spaced = text.replace('>', " > ");
+        let mut compounds = Vec::new();
+        let mut combinator = Combinator::Descendant;
+        for token in spaced.split_whitespace() {
+            if token == ">" {
+                if compounds.is_empty() || combinator == Combinator::Child {
+                    return None;
+                }
+                combinator = Combinator::Child;
+            } else {
+                compounds.push((combinator, parse_compound(token)?));
+                combinator = Combinator::Descendant;
+            }
+        }
+        // A trailing combinator has nothing to apply to
+        if combinator == Combinator::Child {
+            return None;
+        }
+
+        // Each compound was stored with the combinator on its left; walking
+        // right to left, that combinator joins the next compound to this one
+        let (mut joining, subject) = compounds.pop()?;
+        let mut ancestors = Vec::new();
+        while let Some((before, compound)) = compounds.pop() {
+            ancestors.push((joining, compound));
+            joining = before;
+        }
+        Some(Self { subject, ancestors })
+    }
+
+    pub fn specificity(&self) -> Specificity {
+        std::iter::once(&self.subject)
+            .chain(self.ancestors.iter().map(|(_, compound)| compound))
+            .fold((0, 0, 0), |(ids, classes, tags), compound| {
+                (
+                    ids + compound.id.is_some() as u32,
+                    classes + compound.classes.len() as u32,
+                    tags + compound.tag.is_some() as u32,
+                )
+            })
+    }
+
+    pub fn matches(&self, element: &ElementData, ancestors: &[ElementData]) -> bool {
+        self.subject.matches(element) && matches_ancestors(&self.ancestors, ancestors)
+    }
+}
+
+// Match the remaining compounds against the ancestor chain, nearest parent last
+fn matches_ancestors(compounds: &[(Combinator, Compound)], ancestors: &[ElementData]) -> bool {
+    let Some(((combinator, compound), rest)) = compounds.split_first() else {
+        return true;
+    };
+    match combinator {
+        Combinator::Child => match ancestors.split_last() {
+            Some((parent, above)) => compound.matches(parent) && matches_ancestors(rest, above),
+            None => false,
+        },
+        Combinator::Descendant => (0..ancestors.len())
+            .rev()
+            .any(|i| compound.matches(&ancestors[i]) && matches_ancestors(rest, &ancestors[..i])),
+    }
+}
+
+impl Compound {
+    fn matches(&self, element: &ElementData) -> bool {
+        if let Some(tag) = &self.tag {
+            if *tag != element.tag {
+                return false;
+            }
+        }
+        if let Some(id) = &self.id {
+            if element.id.as_ref() != Some(id) {
+                return false;
+            }
+        }
+        self.classes.iter().all(|class| element.classes.contains(class))
+    }
+}
+
+// Parse one compound selector like `div.note#intro` or `*`
+fn parse_compound(token: &str) -> Option<Compound> {
+    let mut compound = Compound::default();
+    let mut rest = token;
+
+    if let Some(after) = rest.strip_prefix('*') {
+        rest = after;
+    } else {
+        let name = take_identifier(rest);
+        if !name.is_empty() {
+            compound.tag = Some(name.to_ascii_lowercase());
+            rest = &rest[name.len()..];
+        }
+    }
+
+    while let Some(marker) = rest.chars().next() {
+        // Pseudo-classes, attribute selectors, sibling combinators and stray characters
+        if marker != '.' && marker != '#' {
+            return None;
+        }
+        let name = take_identifier(&rest[1..]);
+        if name.is_empty() {
+            return None;
+        }
+        if marker == '.' {
+            compound.classes.push(name.to_string());
+        } else {
+            // Two different ids can never match, but that's still a valid selector
+            compound.id = Some(name.to_string());
+        }
+        rest = &rest[1 + name.len()..];
+    }
+    Some(compound)
+}
+
+fn take_identifier(text: &str) -> &str {
+    let end = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+// Parse a sequence of rules and at-rules into `rules`
+fn parse_rules(css: &str, rules: &mut Vec<Rule>) {
+    let mut rest = css;
+    loop {
+        rest = rest.trim_start();
+        // Old pages wrap their styles in HTML comments
+        if let Some(after) = rest.strip_prefix("<!--").or_else(|| rest.strip_prefix("-->")) {
+            rest = after;
+            continue;
+        }
+        if rest.is_empty() {
+            return;
+        }
+
+        let Some(end) = find_top_level(rest, &['{', ';']) else {
+            return;
+        };
+        let prelude = rest[..end].trim();
+
+        // Statements like `@import url(...);` or `@charset "utf-8";`
+        if rest.as_bytes()[end] == b';' {
+            rest = &rest[end + 1..];
+            continue;
+        }
+
+        let block_start = end + 1;
+        let block_end = find_block_end(rest, block_start);
+        let block = &rest[block_start..block_end];
+        rest = rest.get(block_end + 1..).unwrap_or("");
+
+        if let Some(at_rule) = prelude.strip_prefix('@') {
+            let (name, condition) = at_rule.split_once(char::is_whitespace).unwrap_or((at_rule, ""));
+            if name.eq_ignore_ascii_case("media") && media_applies(condition) {
+                parse_rules(block, rules);
+            }
+            // @font-face, @keyframes, @supports and friends are skipped
+            continue;
+        }
+
+        // One bad selector invalidates the whole rule
+        let selectors: Option<Vec<Selector>> = split_top_level(prelude, ',').into_iter().map(Selector::parse).collect();
+        if let Some(selectors) = selectors {
+            if !selectors.is_empty() {
+                rules.push(Rule {
+                    selectors,
+                    declarations: parse_declarations(block),
+                });
+            }
+        }
+    }
+}
+
+// Whether a media query list applies to a screen
+pub fn media_applies(condition: &str) -> bool {
+    let condition = condition.trim().to_ascii_lowercase();
+    condition.is_empty()
+        || condition.split(',').any(|query| {
+            let query = query.trim();
+            !query.starts_with("not ")
+                && !query.starts_with("print")
+                && !query.starts_with("speech")
+                && !query.starts_with("only print")
+        })
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        out.push_str(&rest[..start]);
+        match rest[start + 2..].find("*/") {
+            Some(end) => rest = &rest[start + 2 + end + 2..],
+            None => return out,
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// Index of the first of `targets` outside quotes, parentheses and brackets
+fn find_top_level(text: &str, targets: &[char]) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth -= 1,
+            (None, _) if depth == 0 && targets.contains(&c) => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+// Index of the `}` closing the block that starts at `start`, or the end of the
+// text for an unclosed block
+fn find_block_end(text: &str, start: usize) -> usize {
+    let mut depth = 1;
+    let mut quote = None;
+    for (i, c) in text[start..].char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '{') => depth += 1,
+            (None, '}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return start + i;
+                }
+            }
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+// Split on `separator` where it isn't quoted or inside parentheses
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(index) = find_top_level(rest, &[separator]) {
+        parts.push(rest[..index].trim());
+        rest = &rest[index + 1..];
+    }
+    parts.push(rest.trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+// Parse a CSS color: names, #hex, rgb()/rgba() and hsl()/hsla()
+pub fn parse_color(value: &str) -> Option<Color32> {
+    let value = value.trim().to_ascii_lowercase();
+    if let Some(hex) = value.strip_prefix('#') {
+        return parse_hex_color(hex);
+    }
+    if let Some((function, arguments)) = value.strip_suffix(')').and_then(|v| v.split_once('(')) {
+        let arguments: Vec<&str> = arguments
+            .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .collect();
+        if arguments.len() < 3 {
+            return None;
+        }
+        let alpha = match arguments.get(3) {
+            Some(alpha) => parse_number_or_percentage(alpha, 1.0)?,
+            None => 1.0,
+        };
+        let [r, g, b] = match function.trim() {
+            "rgb" | "rgba" => [
+                parse_number_or_percentage(arguments[0], 255.0)?,
+                parse_number_or_percentage(arguments[1], 255.0)?,
+                parse_number_or_percentage(arguments[2], 255.0)?,
+            ],
+            "hsl" | "hsla" => {
+                let hue = arguments[0].trim_end_matches("deg").parse::<f32>().ok()?;
+                let saturation = parse_number_or_percentage(arguments[1], 1.0)?;
+                let lightness = parse_number_or_percentage(arguments[2], 1.0)?;
+                hsl_to_rgb(hue, saturation, lightness)
+            }
+            _ => return None,
+        };
+        let channel = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+        return Some(Color32::from_rgba_unmultiplied(
+            channel(r),
+            channel(g),
+            channel(b),
+            channel(alpha * 255.0),
+        ));
+    }
+    named_color(&value)
+}
+
+// A number, or a percentage of `full`
+fn parse_number_or_percentage(text: &str, full: f32) -> Option<f32> {
+    match text.strip_suffix('%') {
+        Some(percent) => percent.parse::<f32>().ok().map(|p| p / 100.0 * full),
+        None => text.parse::<f32>().ok(),
+    }
+}
+
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> [f32; 3] {
+    let saturation = saturation.clamp(0.0, 1.0);
+    let lightness = lightness.clamp(0.0, 1.0);
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    [(r + m) * 255.0, (g + m) * 255.0, (b + m) * 255.0]
+}
+
+fn parse_hex_color(hex: &str) -> Option<Color32> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|d| d * 17);
+    let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    match hex.len() {
+        3 => Some(Color32::from_rgb(digit(0)?, digit(1)?, digit(2)?)),
+        4 => Some(Color32::from_rgba_unmultiplied(digit(0)?, digit(1)?, digit(2)?, digit(3)?)),
+        6 => Some(Color32::from_rgb(pair(0)?, pair(2)?, pair(4)?)),
+        8 => Some(Color32::from_rgba_unmultiplied(pair(0)?, pair(2)?, pair(4)?, pair(6)?)),
+        _ => None,
+    }
+}
+
+fn named_color(name: &str) -> Option<Color32> {
+    let rgb = match name {
+        "transparent" => return Some(Color32::TRANSPARENT),
+        "black" => 0x000000,
+        "silver" => 0xc0c0c0,
+        "gray" | "grey" => 0x808080,
+        "white" => 0xffffff,
+        "maroon" => 0x800000,
+        "red" => 0xff0000,
+        "purple" => 0x800080,
+        "fuchsia" | "magenta" => 0xff00ff,
+        "green" => 0x008000,
+        "lime" => 0x00ff00,
+        "olive" => 0x808000,
+        "yellow" => 0xffff00,
+        "navy" => 0x000080,
+        "blue" => 0x0000ff,
+        "teal" => 0x008080,
+        "aqua" | "cyan" => 0x00ffff,
+        "orange" => 0xffa500,
+        "darkgray" | "darkgrey" => 0xa9a9a9,
+        "lightgray" | "lightgrey" => 0xd3d3d3,
+        "dimgray" | "dimgrey" => 0x696969,
+        "gainsboro" => 0xdcdcdc,
+        "whitesmoke" => 0xf5f5f5,
+        "brown" => 0xa52a2a,
+        "crimson" => 0xdc143c,
+        "darkred" => 0x8b0000,
+        "firebrick" => 0xb22222,
+        "tomato" => 0xff6347,
+        "coral" => 0xff7f50,
+        "salmon" => 0xfa8072,
+        "pink" => 0xffc0cb,
+        "hotpink" => 0xff69b4,
+        "gold" => 0xffd700,
+        "khaki" => 0xf0e68c,
+        "beige" => 0xf5f5dc,
+        "ivory" => 0xfffff0,
+        "lightyellow" => 0xffffe0,
+        "darkgreen" => 0x006400,
+        "forestgreen" => 0x228b22,
+        "seagreen" => 0x2e8b57,
+        "lightgreen" => 0x90ee90,
+        "darkblue" => 0x00008b,
+        "mediumblue" => 0x0000cd,
+        "royalblue" => 0x4169e1,
+        "steelblue" => 0x4682b4,
+        "dodgerblue" => 0x1e90ff,
+        "skyblue" => 0x87ceeb,
+        "lightblue" => 0xadd8e6,
+        "aliceblue" => 0xf0f8ff,
+        "indigo" => 0x4b0082,
+        "violet" => 0xee82ee,
+        "orchid" => 0xda70d6,
+        "plum" => 0xdda0dd,
+        "lavender" => 0xe6e6fa,
+        "chocolate" => 0xd2691e,
+        "tan" => 0xd2b48c,
+        "wheat" => 0xf5deb3,
+        "slategray" | "slategrey" => 0x708090,
+        _ => return None,
+    };
+    Some(Color32::from_rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
+
+// Parse a length in pixels. `font_size` is what em units are relative to;
+// percentages aren't lengths here and return None.
+pub fn parse_length(value: &str, font_size: f32) -> Option<f32> {
+    let value = value.trim().to_ascii_lowercase();
+    if value == "0" {
+        return Some(0.0);
+    }
+    let number_end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(value.len());
+    let number = value[..number_end].parse::<f32>().ok()?;
+    let pixels = match &value[number_end..] {
+        "px" => number,
+        "pt" => number * 4.0 / 3.0,
+        "pc" => number * 16.0,
+        "in" => number * 96.0,
+        "cm" => number * 96.0 / 2.54,
+        "mm" => number * 96.0 / 25.4,
+        "em" => number * font_size,
+        "rem" => number * crate::style::DEFAULT_FONT_SIZE,
+        "ex" | "ch" => number * font_size / 2.0,
+        _ => return None,
+    };
+    Some(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(tag: &str, id: Option<&str>, classes: &[&str]) -> ElementData {
+        ElementData {
+            tag: tag.to_string(),
+            id: id.map(str::to_string),
+            classes: classes.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parses_rules_and_declarations() {
+        let sheet = parse_stylesheet(
+            "/* heading */ h1, h2 { color: red; font-size: 20px !important }\n\
+             @media print { p { color: black } }\n\
+             @media screen and (min-width: 10px) { p { margin: 0 } }\n\
+             @import url(\"other.css\");\n\
+             a:hover { color: blue }\n\
+             .note{background:#eee}",
+        );
+
+        let summary: Vec<(usize, Vec<&str>)> = sheet
+            .rules
+            .iter()
+            .map(|rule| (rule.selectors.len(), rule.declarations.iter().map(|d| d.property.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, vec!["color", "font-size"]),
+                (1, vec!["margin"]),
+                (1, vec!["background"]),
+            ]
+        );
+        assert!(sheet.rules[0].declarations[1].important);
+        assert_eq!(sheet.rules[0].declarations[1].value, "20px");
+    }
+
+    #[test]
+    fn declarations_keep_parenthesized_semicolons() {
+        let declarations = parse_declarations("background: url(data:image/png;base64,AAAA) ; color : Red");
+        assert_eq!(declarations.len(), 2);
+        assert_eq!(declarations[0].value, "url(data:image/png;base64,AAAA)");
+        assert_eq!(declarations[1].property, "color");
+    }
+
+    #[test]
+    fn matches_descendant_and_child_selectors() {
+        let body = element("body", None, &[]);
+        let div = element("div", Some("main"), &["content", "wide"]);
+        let section = element("section", None, &[]);
+        let p = element("p", None, &["note"]);
+
+        let matches = |selector: &str, ancestors: &[ElementData]| {
+            Selector::parse(selector).unwrap().matches(&p, ancestors)
+        };
+        let chain = [body.clone(), div.clone(), section.clone()];
+
+        assert!(matches("p", &chain));
+        assert!(matches("p.note", &chain));
+        assert!(matches("*", &chain));
+        assert!(matches("div p", &chain));
+        assert!(matches("body #main.content p", &chain));
+        assert!(matches("section > p", &chain));
+        assert!(matches("div>section>p", &chain));
+        assert!(!matches("div > p", &chain));
+        assert!(!matches("p.other", &chain));
+        assert!(!matches("article p", &chain));
+        assert!(!matches("section div p", &chain));
+    }
+
+    #[test]
+    fn rejects_unsupported_selectors() {
+        assert!(Selector::parse("a:hover").is_none());
+        assert!(Selector::parse("input[type=text]").is_none());
+        assert!(Selector::parse("h1 + p").is_none());
+        assert!(Selector::parse("> p").is_none());
+        assert!(Selector::parse("div >").is_none());
+        assert!(Selector::parse("p★").is_none());
+        assert!(Selector::parse("p.★").is_none());
+        assert_eq!(parse_stylesheet("p★ { color: red } p { color: blue }").rules.len(), 1);
+    }
+
+    #[test]
+    fn specificity_counts_ids_classes_and_types() {
+        assert_eq!(Selector::parse("#a .b.c p").unwrap().specificity(), (1, 2, 1));
+        assert_eq!(Selector::parse("*").unwrap().specificity(), (0, 0, 0));
+    }
+
+    #[test]
+    fn cascade_orders_by_importance_specificity_and_source() {
+        let sheets = vec![Rc::new(parse_stylesheet(
+            "p.note { color: green } p { color: red !important } p { color: blue } * { color: gray }",
+        ))];
+        let p = element("p", None, &["note"]);
+        let values: Vec<&str> = matching_declarations(&sheets, &p, &[])
+            .iter()
+            .map(|declaration| declaration.value.as_str())
+            .collect();
+        assert_eq!(values, vec!["gray", "blue", "green", "red"]);
+    }
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(parse_color("red"), Some(Color32::from_rgb(255, 0, 0)));
+        assert_eq!(parse_color("#0a0"), Some(Color32::from_rgb(0, 170, 0)));
+        assert_eq!(parse_color("#123456"), Some(Color32::from_rgb(0x12, 0x34, 0x56)));
+        assert_eq!(parse_color("rgb(10, 20, 30)"), Some(Color32::from_rgb(10, 20, 30)));
+        assert_eq!(parse_color("rgb(100% 0% 0%)"), Some(Color32::from_rgb(255, 0, 0)));
+        assert_eq!(parse_color("hsl(120, 100%, 25%)"), Some(Color32::from_rgb(0, 128, 0)));
+        assert_eq!(
+            parse_color("rgba(255, 0, 0, 0.5)"),
+            Some(Color32::from_rgba_unmultiplied(255, 0, 0, 128))
+        );
+        assert_eq!(parse_color("notacolor"), None);
+    }
+
+    #[test]
+    fn parses_lengths() {
+        assert_eq!(parse_length("12px", 14.0), Some(12.0));
+        assert_eq!(parse_length("12pt", 14.0), Some(16.0));
+        assert_eq!(parse_length("2em", 10.0), Some(20.0));
+        assert_eq!(parse_length("0", 10.0), Some(0.0));
+        assert_eq!(parse_length("50%", 10.0), None);
+        assert_eq!(parse_length("auto", 10.0), None);
+    }
+}
@@ -0,0 +1,397 @@
+// src/document.rs
+use crate::css::{media_applies, parse_stylesheet, Stylesheet};
+use crate::encoding::EncodingSource;
+use crate::fetch::ResponseInfo;
+use crate::forms::FormState;
+use crate::html_renderer::HtmlRenderer;
+use crate::url_resolver::{document_base_url, resolve_url};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Source of HtmlPage ids
+static NEXT_PAGE_ID: AtomicU64 = AtomicU64::new(1);
+
+// A loaded page: the response is classified, decoded and (for HTML) parsed and
+// filtered once per navigation, not on every repaint
+pub struct Document {
+    // Status, headers and final URL of the response this page came from
+    pub response: ResponseInfo,
+    // What the viewer shows
+    pub content: PageContent,
+    // Character encoding text content was decoded with, and how it was chosen
+    pub encoding: Option<(&'static encoding_rs::Encoding, EncodingSource)>,
+}
+
+// Content of a page, by the viewer that displays it
+pub enum PageContent {
+    Html(HtmlPage),
+    // The texture lives in the browser's image cache, keyed by the response URL
+    Image,
+    PlainText(String),
+    Unsupported { mime: String, bytes: Vec<u8> },
+    // A page the browser draws itself
+    About(AboutPage),
+}
+
+// Pages at about: URLs, which are never fetched
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AboutPage {
+    History,
+    Bookmarks,
+}
+
+impl AboutPage {
+    pub fn from_url(url: &str) -> Option<Self> {
+        match url.trim().to_ascii_lowercase().as_str() {
+            "about:history" => Some(AboutPage::History),
+            "about:bookmarks" => Some(AboutPage::Bookmarks),
+            _ => None,
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            AboutPage::History => "History",
+            AboutPage::Bookmarks => "Bookmarks",
+        }
+    }
+}
+
+impl Document {
+    pub fn about(page: AboutPage, url: &str) -> Self {
+        Self {
+            response: ResponseInfo {
+                url: url.trim().to_string(),
+                status: 200,
+                status_text: "OK".to_string(),
+                headers: Default::default(),
+            },
+            content: PageContent::About(page),
+            encoding: None,
+        }
+    }
+}
+
+pub struct HtmlPage {
+    // Address the document was loaded from; forms without an action submit here
+    pub url: String,
+    // Preprocessed HTML source, shown in the raw HTML view
+    pub source: String,
+    // Base URL that href/src attributes resolve against
+    pub base_url: String,
+    // Contents of <title>, if any
+    pub title: Option<String>,
+    // The <html> and <body> elements enclosing the body nodes, without their
+    // children; styles set on them are inherited by the page
+    pub body_path: Vec<html_parser::Element>,
+    // Filtered body nodes ready for rendering
+    pub body: Vec<html_parser::Node>,
+    // Author stylesheets in document order
+    pub stylesheets: Vec<PageStylesheet>,
+    // What the user has entered into the page's form controls; shared with
+    // the renderer, which reads and updates it while drawing the controls
+    pub form_state: Rc<RefCell<FormState>>,
+    // Set when the source could not be parsed
+    pub parse_error: Option<String>,
+    // Unique to this page, so the renderer knows when its cached styles are
+    // for another one
+    pub id: u64,
+    // Bumped whenever a linked stylesheet arrives
+    pub stylesheet_version: u64,
+}
+
+// A <style> element or <link rel="stylesheet">
+pub enum PageStylesheet {
+    Embedded(Rc<Stylesheet>),
+    // Resolved URL; the sheet is filled in once the fetch completes
+    Linked { url: String, sheet: Option<Rc<Stylesheet>> },
+}
+
+// Text made safe to put in generated markup, as content or a quoted attribute
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Preprocess HTML to remove scripts and simplify structure. <style> elements
+// are kept so their rules can be collected; they're dropped from the body later.
+pub fn preprocess_html(html: &str) -> String {
+    // Check if the HTML is too large
+    if html.len() > 1_000_000 {
+        // For very large HTML, do a more aggressive truncation
+        let truncated = &html[0..500_000];
+        if let Some(end_pos) = truncated.rfind("</div>") {
+            // Just return a simplified version
+            return format!("<html><body><h1>Page content simplified</h1><p>The page was too large to display fully.</p>{}</body></html>", 
+                          &truncated[0..end_pos+6]);
+        } else {
+            return "<html><body><h1>Page too large</h1><p>The page was too large to display.</p></body></html>".to_string();
+        }
+    }
+    
+    // Use a more efficient approach for large HTML
+    let mut processed = String::with_capacity(html.len() / 2);
+    let mut in_script = false;
+    let mut skip_until_index = 0;
+    
+    // Process the HTML in a single pass
+    let chars: Vec<char> = html.chars().collect();
+    let mut i = 0;
+    
+    while i < chars.len() {
+        if i < skip_until_index {
+            i += 1;
+            continue;
+        }
+        
+        // Check for script start
+        if !in_script && i + 7 < chars.len() && 
+           &chars[i..i+7].iter().collect::<String>() == "<script" {
+            // Find the script end and skip past it; an unclosed script hides the rest
+            let rest = chars[i..].iter().collect::<String>();
+            if let Some(end_pos) = rest.find("</script>") {
+                skip_until_index = i + rest[..end_pos].chars().count() + 9;
+                i += 1;
+                continue;
+            }
+            in_script = true;
+        }
+        
+        // Skip script content
+        if in_script {
+            if i + 9 < chars.len() && &chars[i..i+9].iter().collect::<String>() == "</script>" {
+                in_script = false;
+                i += 9;
+            } else {
+                i += 1;
+            }
+            continue;
+        }
+        
+        // Add current character to processed output
+        processed.push(chars[i]);
+        i += 1;
+    }
+    
+    processed
+}
+
+impl HtmlPage {
+    pub fn new(url: &str, source: String, html_renderer: &HtmlRenderer) -> Self {
+        match html_parser::Dom::parse(&source) {
+            Ok(dom) => {
+                let base_url = document_base_url(url, &dom.children);
+                let title = find_title(&dom.children);
+                let body = Self::derive_body(&dom, html_renderer);
+                let mut body_path = Vec::new();
+                find_body_path(&dom.children, &mut body_path);
+                let mut stylesheets = Vec::new();
+                find_stylesheets(&dom.children, &base_url, &mut stylesheets);
+                Self {
+                    url: url.to_string(),
+                    source,
+                    base_url,
+                    title,
+                    body_path,
+                    body,
+                    stylesheets,
+                    form_state: Rc::default(),
+                    parse_error: None,
+                    id: NEXT_PAGE_ID.fetch_add(1, Ordering::Relaxed),
+                    stylesheet_version: 0,
+                }
+            }
+            Err(err) => Self {
+                url: url.to_string(),
+                source,
+                base_url: url.to_string(),
+                title: None,
+                body_path: Vec::new(),
+                body: Vec::new(),
+                stylesheets: Vec::new(),
+                form_state: Rc::default(),
+                parse_error: Some(format!("Failed to parse HTML: {}", err)),
+                id: NEXT_PAGE_ID.fetch_add(1, Ordering::Relaxed),
+                stylesheet_version: 0,
+            },
+        }
+    }
+
+    // Stylesheets that are ready, in cascade order
+    pub fn loaded_stylesheets(&self) -> Vec<Rc<Stylesheet>> {
+        self.stylesheets
+            .iter()
+            .filter_map(|stylesheet| match stylesheet {
+                PageStylesheet::Embedded(sheet) => Some(sheet.clone()),
+                PageStylesheet::Linked { sheet, .. } => sheet.clone(),
+            })
+            .collect()
+    }
+
+    // URLs of linked stylesheets that still need fetching
+    pub fn pending_stylesheets(&self) -> Vec<String> {
+        self.stylesheets
+            .iter()
+            .filter_map(|stylesheet| match stylesheet {
+                PageStylesheet::Linked { url, sheet: None } => Some(url.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Fill in every link to `url` with its fetched stylesheet
+    pub fn set_linked_stylesheet(&mut self, url: &str, stylesheet: Stylesheet) {
+        let stylesheet = Rc::new(stylesheet);
+        self.stylesheet_version += 1;
+        for entry in &mut self.stylesheets {
+            if let PageStylesheet::Linked { url: link_url, sheet } = entry {
+                if link_url == url {
+                    *sheet = Some(stylesheet.clone());
+                }
+            }
+        }
+    }
+
+    // Find the body and filter out script/style content
+    fn derive_body(dom: &html_parser::Dom, html_renderer: &HtmlRenderer) -> Vec<html_parser::Node> {
+        match html_renderer.find_body_element(&dom.children) {
+            Some(filtered_body) => filtered_body,
+            // If no body tag is found, filter everything
+            None => html_renderer.filter_nodes(&dom.children),
+        }
+    }
+}
+
+// Elements from the root down to <body>; left empty when there is no body
+fn find_body_path(nodes: &[html_parser::Node], path: &mut Vec<html_parser::Element>) -> bool {
+    for node in nodes {
+        if let html_parser::Node::Element(element) = node {
+            // Cloning the whole element would copy the entire subtree
+            path.push(html_parser::Element {
+                id: element.id.clone(),
+                name: element.name.clone(),
+                variant: element.variant.clone(),
+                attributes: element.attributes.clone(),
+                classes: element.classes.clone(),
+                children: Vec::new(),
+                source_span: element.source_span.clone(),
+            });
+            if element.name.eq_ignore_ascii_case("body") || find_body_path(&element.children, path) {
+                return true;
+            }
+            path.pop();
+        }
+    }
+    false
+}
+
+// Collect <style> and <link rel="stylesheet"> elements in document order
+fn find_stylesheets(nodes: &[html_parser::Node], base_url: &str, stylesheets: &mut Vec<PageStylesheet>) {
+    for node in nodes {
+        let html_parser::Node::Element(element) = node else {
+            continue;
+        };
+        let attribute = |name: &str| element.attributes.get(name).cloned().flatten().unwrap_or_default();
+        // Sheets for print and other media don't apply on screen
+        if !media_applies(&attribute("media")) {
+            continue;
+        }
+
+        if element.name.eq_ignore_ascii_case("style") {
+            let css: String = element
+                .children
+                .iter()
+                .filter_map(|child| match child {
+                    html_parser::Node::Text(text) => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect();
+            stylesheets.push(PageStylesheet::Embedded(Rc::new(parse_stylesheet(&css))));
+        } else if element.name.eq_ignore_ascii_case("link") {
+            let rel = attribute("rel").to_ascii_lowercase();
+            let rel: Vec<&str> = rel.split_whitespace().collect();
+            let href = attribute("href");
+            // Alternate stylesheets are only used when the user picks them
+            if rel.contains(&"stylesheet") && !rel.contains(&"alternate") && !href.trim().is_empty() {
+                stylesheets.push(PageStylesheet::Linked {
+                    url: resolve_url(base_url, href.trim()),
+                    sheet: None,
+                });
+            }
+        } else {
+            find_stylesheets(&element.children, base_url, stylesheets);
+        }
+    }
+}
+
+// Text of the first <title> element, with whitespace collapsed
+fn find_title(nodes: &[html_parser::Node]) -> Option<String> {
+    for node in nodes {
+        if let html_parser::Node::Element(element) = node {
+            if element.name.eq_ignore_ascii_case("title") {
+                let text: Vec<&str> = element
+                    .children
+                    .iter()
+                    .filter_map(|child| match child {
+                        html_parser::Node::Text(text) => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .flat_map(str::split_whitespace)
+                    .collect();
+                return if text.is_empty() { None } else { Some(text.join(" ")) };
+            }
+            if let Some(title) = find_title(&element.children) {
+                return Some(title);
+            }
+        }
+    }
+    None
+}
+
+impl Document {
+    // Human-readable description of the detected encoding, e.g. "Shift_JIS (from <meta> tag)"
+    pub fn encoding_description(&self) -> Option<String> {
+        self.encoding
+            .map(|(encoding, source)| format!("{} (from {})", encoding.name(), source.describe()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::LinkHandler;
+    use crate::style::create_default_styles;
+
+    #[test]
+    fn preprocessing_removes_every_script() {
+        let html = "<head><script>a</script></head><body><p>é</p><script>b</script><p>after</p></body>";
+        assert_eq!(preprocess_html(html), "<head></head><body><p>é</p><p>after</p></body>");
+    }
+
+    #[test]
+    fn escapes_markup_characters() {
+        assert_eq!(escape_html(r#"<b title="x">Tom & Jerry</b>"#), "&lt;b title=&quot;x&quot;&gt;Tom &amp; Jerry&lt;/b&gt;");
+    }
+
+    #[test]
+    fn collects_stylesheets_in_document_order() {
+        let renderer = HtmlRenderer::new(create_default_styles(), LinkHandler::new());
+        let source = r#"<html><head>
+            <link rel="stylesheet" href="/main.css">
+            <style>p > b { color: red }</style>
+            <link rel="alternate stylesheet" href="alt.css">
+            <link rel="stylesheet" href="print.css" media="print">
+            </head><body><style>h1 { color: blue }</style><p>Text</p></body></html>"#;
+        let mut page = HtmlPage::new("http://example.com/dir/page.html", preprocess_html(source), &renderer);
+
+        assert_eq!(page.stylesheets.len(), 3);
+        assert_eq!(page.pending_stylesheets(), vec!["http://example.com/main.css".to_string()]);
+        // Only the embedded sheets are ready, and <style> is not part of the body
+        assert_eq!(page.loaded_stylesheets().len(), 2);
+        assert_eq!(page.body.len(), 1);
+
+        page.set_linked_stylesheet("http://example.com/main.css", Stylesheet::default());
+        assert!(page.pending_stylesheets().is_empty());
+        assert_eq!(page.loaded_stylesheets().len(), 3);
+    }
+}
@@ -0,0 +1,216 @@
+// src/encoding.rs
+// Character encoding detection for fetched pages, following the order browsers use:
+// byte order mark, then the Content-Type charset, then a <meta> prescan, then the
+// windows-1252 fallback.
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+// The HTML spec only looks at the first 1024 bytes when prescanning for <meta>
+const PRESCAN_LIMIT: usize = 1024;
+
+// Where the encoding of a page was determined from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EncodingSource {
+    ByteOrderMark,
+    ContentType,
+    MetaTag,
+    Fallback,
+}
+
+impl EncodingSource {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            EncodingSource::ByteOrderMark => "byte order mark",
+            EncodingSource::ContentType => "Content-Type header",
+            EncodingSource::MetaTag => "<meta> tag",
+            EncodingSource::Fallback => "default",
+        }
+    }
+}
+
+// A response body decoded to text
+pub struct DecodedBody {
+    pub text: String,
+    pub encoding: &'static Encoding,
+    pub source: EncodingSource,
+}
+
+// Decode a response body, using the Content-Type header value if there is one
+pub fn decode_body(bytes: &[u8], content_type: Option<&str>) -> DecodedBody {
+    let (encoding, source) = detect_encoding(bytes, content_type);
+    // decode() strips a BOM that matches the chosen encoding and replaces malformed
+    // sequences with U+FFFD instead of failing
+    let (text, _, _) = encoding.decode(bytes);
+    DecodedBody {
+        text: text.into_owned(),
+        encoding,
+        source,
+    }
+}
+
+pub fn detect_encoding(bytes: &[u8], content_type: Option<&str>) -> (&'static Encoding, EncodingSource) {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return (encoding, EncodingSource::ByteOrderMark);
+    }
+
+    if let Some(encoding) = content_type
+        .and_then(charset_from_content_type)
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+    {
+        return (encoding, EncodingSource::ContentType);
+    }
+
+    if let Some(encoding) = prescan_meta(&bytes[..bytes.len().min(PRESCAN_LIMIT)]) {
+        return (encoding, EncodingSource::MetaTag);
+    }
+
+    (WINDOWS_1252, EncodingSource::Fallback)
+}
+
+// Extract the charset parameter from a Content-Type value such as
+// `text/html; charset="Shift_JIS"`
+pub fn charset_from_content_type(content_type: &str) -> Option<String> {
+    let lower = content_type.to_ascii_lowercase();
+    let start = lower.find("charset")? + "charset".len();
+    let rest = lower[start..].trim_start();
+    let rest = rest.strip_prefix('=')?.trim_start();
+
+    let value = if let Some(quoted) = rest.strip_prefix('"').or_else(|| rest.strip_prefix('\'')) {
+        quoted.split(['"', '\'']).next().unwrap_or("")
+    } else {
+        rest.split(|c: char| c == ';' || c.is_ascii_whitespace()).next().unwrap_or("")
+    };
+
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+// Look for <meta charset=...> or <meta http-equiv="Content-Type" content="...charset=...">
+fn prescan_meta(bytes: &[u8]) -> Option<&'static Encoding> {
+    // The prescan only cares about ASCII, so a lossy lowercase view is enough
+    let head = String::from_utf8_lossy(bytes).to_ascii_lowercase();
+    let mut search_from = 0;
+
+    while let Some(offset) = head[search_from..].find("<meta") {
+        let tag_start = search_from + offset;
+        let tag_end = head[tag_start..].find('>').map(|i| tag_start + i).unwrap_or(head.len());
+        let tag = &head[tag_start + "<meta".len()..tag_end];
+        search_from = tag_end;
+
+        let label = if let Some(charset) = attribute_value(tag, "charset") {
+            Some(charset)
+        } else if attribute_value(tag, "http-equiv").as_deref() == Some("content-type") {
+            attribute_value(tag, "content").and_then(|content| charset_from_content_type(&content))
+        } else {
+            None
+        };
+
+        if let Some(encoding) = label.and_then(|label| Encoding::for_label(label.trim().as_bytes())) {
+            // A document that could be read as ASCII to find this tag can't be UTF-16
+            if encoding == UTF_16LE || encoding == UTF_16BE {
+                return Some(UTF_8);
+            }
+            if encoding.name() == "x-user-defined" {
+                return Some(WINDOWS_1252);
+            }
+            return Some(encoding);
+        }
+    }
+
+    None
+}
+
+// Find an attribute value inside the (lowercased) text of a tag
+fn attribute_value(tag: &str, name: &str) -> Option<String> {
+    let mut search_from = 0;
+    while let Some(offset) = tag[search_from..].find(name) {
+        let start = search_from + offset;
+        search_from = start + name.len();
+
+        // Must be a whole attribute name, not part of another one
+        let preceded_ok = tag[..start]
+            .chars()
+            .last()
+            .is_none_or(|c| c.is_ascii_whitespace() || matches!(c, '/' | '"' | '\''));
+        let rest = tag[search_from..].trim_start();
+        if !preceded_ok || !rest.starts_with('=') {
+            continue;
+        }
+
+        let rest = rest[1..].trim_start();
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            quoted.split('"').next().unwrap_or("")
+        } else if let Some(quoted) = rest.strip_prefix('\'') {
+            quoted.split('\'').next().unwrap_or("")
+        } else {
+            rest.split(|c: char| c.is_ascii_whitespace() || matches!(c, '/' | '"' | '\''))
+                .next()
+                .unwrap_or("")
+        };
+        return Some(value.to_string());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{GBK, SHIFT_JIS};
+
+    #[test]
+    fn byte_order_mark_wins() {
+        let bytes = b"\xEF\xBB\xBF<p>caf\xC3\xA9</p>";
+        let decoded = decode_body(bytes, Some("text/html; charset=iso-8859-1"));
+        assert_eq!(decoded.encoding, UTF_8);
+        assert_eq!(decoded.source, EncodingSource::ByteOrderMark);
+        assert_eq!(decoded.text, "<p>café</p>");
+    }
+
+    #[test]
+    fn uses_content_type_charset() {
+        let (encoding, source) = detect_encoding(b"<p>x</p>", Some("text/html; charset=\"Shift_JIS\""));
+        assert_eq!(encoding, SHIFT_JIS);
+        assert_eq!(source, EncodingSource::ContentType);
+    }
+
+    #[test]
+    fn prescans_meta_charset() {
+        let bytes = b"<html><head><meta charset=gbk><title>\xC4\xE3\xBA\xC3</title>";
+        let decoded = decode_body(bytes, Some("text/html"));
+        assert_eq!(decoded.encoding, GBK);
+        assert_eq!(decoded.source, EncodingSource::MetaTag);
+        assert!(decoded.text.contains("你好"));
+    }
+
+    #[test]
+    fn prescans_http_equiv() {
+        let bytes = b"<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=ISO-8859-1\">";
+        let (encoding, source) = detect_encoding(bytes, None);
+        // iso-8859-1 is an alias of windows-1252 in the Encoding Standard
+        assert_eq!(encoding, WINDOWS_1252);
+        assert_eq!(source, EncodingSource::MetaTag);
+    }
+
+    #[test]
+    fn meta_utf16_means_utf8() {
+        let (encoding, _) = detect_encoding(b"<meta charset=\"utf-16\">", None);
+        assert_eq!(encoding, UTF_8);
+    }
+
+    #[test]
+    fn falls_back_to_windows_1252() {
+        let decoded = decode_body(b"<p>caf\xE9 \x93quoted\x94</p>", None);
+        assert_eq!(decoded.encoding, WINDOWS_1252);
+        assert_eq!(decoded.source, EncodingSource::Fallback);
+        assert_eq!(decoded.text, "<p>café \u{201C}quoted\u{201D}</p>");
+    }
+
+    #[test]
+    fn parses_charset_parameter() {
+        assert_eq!(charset_from_content_type("text/html;charset=UTF-8").as_deref(), Some("utf-8"));
+        assert_eq!(charset_from_content_type("text/html; charset='euc-kr'").as_deref(), Some("euc-kr"));
+        assert_eq!(charset_from_content_type("text/html"), None);
+    }
+}
@@ -0,0 +1,556 @@
+// src/fetch.rs
+use crate::cookies::{unix_now, SharedCookieJar};
+use crate::http_cache::{validators, SharedHttpCache};
+use crate::url_resolver::{host_of, resolve_url};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Limit page bodies to 2MB to prevent memory issues
+const MAX_PAGE_SIZE: usize = 2 * 1024 * 1024;
+// Redirects followed before giving up, as browsers do
+const MAX_REDIRECTS: usize = 20;
+
+// Metadata of a completed response, kept alongside the document it produced
+#[derive(Clone, Debug)]
+pub struct ResponseInfo {
+    // The URL we ended up at after following redirects
+    pub url: String,
+    pub status: u16,
+    pub status_text: String,
+    // All header names are lower-case
+    pub headers: BTreeMap<String, String>,
+}
+
+impl ResponseInfo {
+    pub fn from_response(response: &ehttp::Response) -> Self {
+        Self {
+            url: response.url.clone(),
+            status: response.status,
+            status_text: response.status_text.clone(),
+            headers: response.headers.clone(),
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(|s| s.as_str())
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.header("content-type")
+    }
+
+    // 4xx and 5xx responses are shown as error pages
+    pub fn is_error(&self) -> bool {
+        self.status >= 400
+    }
+
+    // e.g. "404 Not Found"
+    pub fn status_line(&self) -> String {
+        format!("{} {}", self.status, self.status_text)
+    }
+}
+
+// Who started a request, which decides the SameSite cookies it carries
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Initiator {
+    // Typed in the address bar, or history navigation
+    #[default]
+    User,
+    // A link followed or form submitted on the document at this URL
+    Page(String),
+    // An image or stylesheet of the document at this URL
+    Subresource(String),
+}
+
+// How a request uses the HTTP cache, named after the Fetch standard's modes
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CacheMode {
+    // Fresh responses come from the cache; stale ones are revalidated
+    #[default]
+    Default,
+    // Any stored response is used, however stale, as back and forward do
+    ForceCache,
+    // Stored responses are always revalidated first, as Reload does
+    NoCache,
+    // The cache isn't read, only updated with the response, as Hard Reload does
+    Reload,
+}
+
+// Lets whoever started a request stop it: the fetch gives up before the next
+// redirect or chunk of the body, dropping the connection. A connect, TLS
+// handshake or read already blocked isn't interrupted; it ends at the
+// client's timeouts. Clones share the flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+const CANCELLED: &str = "Request cancelled";
+
+// How far a request has got, for the status bar
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum LoadPhase {
+    #[default]
+    Starting,
+    // Looking up the host's addresses
+    Resolving(String),
+    // Connecting, or waiting for the response on a reused connection
+    Connecting(String),
+    // Reading the body; the total is from Content-Length
+    Receiving { received: usize, total: Option<usize> },
+}
+
+impl LoadPhase {
+    // e.g. "Receiving 12 KB of 40 KB"; both round up, so a whole body reads
+    // as all of it
+    pub fn description(&self) -> String {
+        match self {
+            LoadPhase::Starting => "Starting".to_string(),
+            LoadPhase::Resolving(host) => format!("Resolving {}", host),
+            LoadPhase::Connecting(host) => format!("Connecting to {}", host),
+            LoadPhase::Receiving { received, total: Some(total) } => format!("Receiving {} KB of {} KB", received.div_ceil(1024), total.div_ceil(1024)),
+            LoadPhase::Receiving { received, total: None } => format!("Receiving {} KB", received.div_ceil(1024)),
+        }
+    }
+
+    // Share of the response read so far, when its length is known
+    pub fn fraction(&self) -> Option<f32> {
+        match self {
+            LoadPhase::Receiving { received, total: Some(total) } if *total > 0 => Some((*received as f32 / *total as f32).min(1.0)),
+            _ => None,
+        }
+    }
+}
+
+// The phase of a request, updated by the thread making it. Clones share it.
+#[derive(Clone, Debug, Default)]
+pub struct LoadProgress(Arc<Mutex<LoadPhase>>);
+
+impl LoadProgress {
+    pub fn phase(&self) -> LoadPhase {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn set(&self, phase: LoadPhase) {
+        *self.0.lock().unwrap() = phase;
+    }
+}
+
+thread_local! {
+    // Progress of the request this thread is sending, so the resolver, which
+    // the agent shares between threads, can report lookups to it
+    static CURRENT_PROGRESS: RefCell<Option<LoadProgress>> = const { RefCell::new(None) };
+}
+
+// Host lookup that reports itself, taking "host:port" as ureq passes it
+fn resolve_reporting(netloc: &str) -> std::io::Result<Vec<SocketAddr>> {
+    let host = netloc.rsplit_once(':').map_or(netloc, |(host, _)| host).to_string();
+    let report = |phase: LoadPhase| CURRENT_PROGRESS.with(|progress| progress.borrow().as_ref().map(|progress| progress.set(phase)));
+    report(LoadPhase::Resolving(host.clone()));
+    let addresses = netloc.to_socket_addrs().map(Iterator::collect);
+    report(LoadPhase::Connecting(host));
+    addresses
+}
+
+// A page load: a GET, or a POST when there's a body, as form submissions make
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageRequest {
+    pub url: String,
+    pub body: Option<RequestBody>,
+    pub initiator: Initiator,
+    pub cache_mode: CacheMode,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RequestBody {
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+impl PageRequest {
+    pub fn get(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            body: None,
+            initiator: Initiator::User,
+            cache_mode: CacheMode::Default,
+        }
+    }
+
+    pub fn method(&self) -> &'static str {
+        if self.body.is_some() { "POST" } else { "GET" }
+    }
+
+    pub fn initiated_by(mut self, initiator: Initiator) -> Self {
+        self.initiator = initiator;
+        self
+    }
+
+    pub fn with_cache_mode(mut self, cache_mode: CacheMode) -> Self {
+        self.cache_mode = cache_mode;
+        self
+    }
+}
+
+// What every request of the browser shares: the user agent it sends, the
+// cookie jar and the HTTP cache. Cloning gives a handle to the same jar, cache
+// and connection pool.
+#[derive(Clone)]
+pub struct HttpClient {
+    pub user_agent: String,
+    pub cookies: SharedCookieJar,
+    // None to always go to the network
+    pub cache: Option<SharedHttpCache>,
+    agent: ureq::Agent,
+}
+
+impl HttpClient {
+    pub fn new(user_agent: impl Into<String>, cookies: SharedCookieJar) -> Self {
+        // Redirects are followed by hand so every hop stores and sends cookies.
+        // The timeouts also bound how long a cancelled request can stay blocked.
+        let agent = ureq::builder()
+            .timeout_connect(Duration::from_secs(5))
+            .timeout_read(Duration::from_secs(10))
+            .timeout_write(Duration::from_secs(10))
+            .redirects(0)
+            .resolver(resolve_reporting)
+            .build();
+        Self {
+            user_agent: user_agent.into(),
+            cookies,
+            cache: None,
+            agent,
+        }
+    }
+
+    pub fn with_cache(mut self, cache: SharedHttpCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+}
+
+// Fetch a page with timeouts, keeping status, headers and final URL. Error statuses
+// are returned as responses (with ok = false) so their body can still be rendered.
+pub fn fetch_page(url: &str, client: &HttpClient) -> Result<ehttp::Response, String> {
+    fetch_request(&PageRequest::get(url), client)
+}
+
+pub fn fetch_request(request: &PageRequest, client: &HttpClient) -> Result<ehttp::Response, String> {
+    fetch_cancellable(request, client, &CancelFlag::default(), &LoadProgress::default())
+}
+
+// fetch_request that stops with an error once `cancel` is set, and reports
+// each hop's phase to `progress`
+pub fn fetch_cancellable(request: &PageRequest, client: &HttpClient, cancel: &CancelFlag, progress: &LoadProgress) -> Result<ehttp::Response, String> {
+    let mut request = request.clone();
+
+    for _ in 0..=MAX_REDIRECTS {
+        if cancel.is_cancelled() {
+            return Err(CANCELLED.to_string());
+        }
+        let response = fetch_once(&request, client, cancel, progress)?;
+        let location = response.headers.get("location").map(|location| resolve_url(&request.url, location));
+        match location {
+            Some(location) if matches!(response.status, 301 | 302 | 303 | 307 | 308) => {
+                // 303, and 301/302 after a POST, turn into a GET (RFC 9110 section 15.4)
+                if response.status == 303 || (matches!(response.status, 301 | 302) && request.body.is_some()) {
+                    request.body = None;
+                }
+                request.url = location;
+            }
+            _ => return Ok(response),
+        }
+    }
+    Err("Failed to fetch URL: too many redirects".to_string())
+}
+
+// One request without following redirects: answered from the cache when it
+// can be, otherwise sent with cookies and any validators for a stale entry
+fn fetch_once(request: &PageRequest, client: &HttpClient, cancel: &CancelFlag, progress: &LoadProgress) -> Result<ehttp::Response, String> {
+    let (url, method) = (request.url.as_str(), request.method());
+    let cookie = client.cookies.lock().unwrap().cookie_header(url, method, &request.initiator, unix_now());
+    // Stored responses are only used for requests that would send the same
+    // values of the headers they vary on
+    let request_headers = [("user-agent", client.user_agent.clone()), ("cookie", cookie.clone().unwrap_or_default())];
+
+    let cache = client.cache.as_ref();
+    let mut conditional = Vec::new();
+    if let (Some(cache), None) = (cache, &request.body) {
+        let hit = match request.cache_mode {
+            CacheMode::Reload => None,
+            _ => cache.lock().unwrap().lookup(url, &request_headers, unix_now()),
+        };
+        if let Some(hit) = hit {
+            match request.cache_mode {
+                CacheMode::ForceCache => return Ok(hit.response),
+                CacheMode::Default if hit.fresh => return Ok(hit.response),
+                _ => conditional = validators(&hit.response),
+            }
+        }
+    }
+
+    let mut call = client.agent.request(method, url).set("User-Agent", &client.user_agent);
+    if let Some(cookie) = &cookie {
+        call = call.set("Cookie", cookie);
+    }
+    for (name, value) in &conditional {
+        call = call.set(name, value);
+    }
+    // Reloads also ask caches between us and the server to check with it
+    match request.cache_mode {
+        CacheMode::NoCache => call = call.set("Cache-Control", "max-age=0"),
+        CacheMode::Reload => call = call.set("Cache-Control", "no-cache").set("Pragma", "no-cache"),
+        _ => {}
+    }
+    let request_time = unix_now();
+    progress.set(LoadPhase::Connecting(host_of(url).unwrap_or_default()));
+    CURRENT_PROGRESS.with(|current| *current.borrow_mut() = Some(progress.clone()));
+    let result = match &request.body {
+        Some(body) => call.set("Content-Type", &body.content_type).send_bytes(&body.bytes),
+        None => call.call(),
+    };
+    CURRENT_PROGRESS.with(|current| *current.borrow_mut() = None);
+    let response = match result {
+        Ok(response) => response,
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(err) => return Err(format!("Failed to fetch URL: {}", err)),
+    };
+
+    let set_cookies: Vec<String> = response.all("set-cookie").into_iter().map(str::to_string).collect();
+    client.cookies.lock().unwrap().store_response(url, &set_cookies, &request.initiator, unix_now());
+    let (response, complete) = into_ehttp_response(response, cancel, progress)?;
+
+    let Some(cache) = cache else {
+        return Ok(response);
+    };
+    let mut cache = cache.lock().unwrap();
+    let response_time = unix_now();
+    if request.body.is_some() {
+        // A POST may change what the URL shows (RFC 9111 section 4.4)
+        if response.status < 400 {
+            cache.invalidate(url);
+        }
+    } else if response.status == 304 && !conditional.is_empty() {
+        if let Some(updated) = cache.revalidated(url, &response, request_time, response_time) {
+            return Ok(updated);
+        }
+    } else if complete {
+        cache.store(&request_headers, &response, request_time, response_time);
+    }
+    Ok(response)
+}
+
+// Convert a ureq response into an ehttp one, reading the body with a size limit.
+// Also returns whether the whole body was read.
+fn into_ehttp_response(response: ureq::Response, cancel: &CancelFlag, progress: &LoadProgress) -> Result<(ehttp::Response, bool), String> {
+    // Save response metadata before consuming the response
+    let url = response.get_url().to_string();
+    let status = response.status();
+    let status_text = response.status_text().to_string();
+    let mut headers = BTreeMap::new();
+    for name in response.headers_names() {
+        // Repeated headers are combined as described in RFC 9110 section 5.3
+        let values = response.all(&name);
+        if !values.is_empty() {
+            headers.insert(name.to_ascii_lowercase(), values.join(", "));
+        }
+    }
+
+    let total = headers.get("content-length").and_then(|length| length.parse().ok());
+    progress.set(LoadPhase::Receiving { received: 0, total });
+    let mut bytes = Vec::new();
+    let mut reader = response.into_reader();
+    let mut buffer = [0; 8192];
+    let mut total_read = 0;
+
+    loop {
+        // Checked between reads; a read waiting on the server runs until it
+        // returns data or the read timeout
+        if cancel.is_cancelled() {
+            return Err(CANCELLED.to_string());
+        }
+        match reader.read(&mut buffer) {
+            Ok(0) => break, // EOF
+            Ok(n) => {
+                total_read += n;
+                progress.set(LoadPhase::Receiving { received: total_read, total });
+                if total_read <= MAX_PAGE_SIZE {
+                    bytes.extend_from_slice(&buffer[..n]);
+                } else {
+                    // We've read enough, stop here
+                    break;
+                }
+            }
+            Err(_) => return Err("Error reading response".to_string()),
+        }
+    }
+
+    // Create a simplified response if too large
+    if total_read > MAX_PAGE_SIZE {
+        bytes = "<html><body><h1>Content truncated</h1><p>The page was too large to display fully.</p></body></html>"
+            .as_bytes()
+            .to_vec();
+        headers.insert("content-type".to_string(), "text/html; charset=utf-8".to_string());
+    }
+
+    let response = ehttp::Response {
+        url,
+        ok: (200..300).contains(&status),
+        status,
+        status_text,
+        headers,
+        bytes,
+    };
+    Ok((response, total_read <= MAX_PAGE_SIZE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cookies::CookieJar;
+    use crate::http_cache::HttpCache;
+    use crate::test_server::{TestResponse, TestServer};
+
+    #[test]
+    fn keeps_cookies_across_redirects() {
+        let server = TestServer::start(|request| match request.target.as_str() {
+            "/login" => TestResponse {
+                status: 303,
+                headers: vec![
+                    ("Set-Cookie".to_string(), "session=abc; Path=/; HttpOnly".to_string()),
+                    ("Set-Cookie".to_string(), "theme=dark; Max-Age=60".to_string()),
+                    ("Location".to_string(), "/home".to_string()),
+                ],
+                body: Vec::new(),
+            },
+            _ => TestResponse::html("<p>Home</p>"),
+        });
+        let client = HttpClient::new("test", CookieJar::default().shared());
+        let login = PageRequest {
+            body: Some(RequestBody {
+                content_type: "application/x-www-form-urlencoded".to_string(),
+                bytes: b"user=me".to_vec(),
+            }),
+            ..PageRequest::get(format!("{}/login", server.url)).initiated_by(Initiator::Page(format!("{}/", server.url)))
+        };
+
+        let response = fetch_request(&login, &client).unwrap();
+        assert_eq!(response.url, format!("{}/home", server.url));
+        fetch_page(&format!("{}/image.png", server.url), &client).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].header("cookie"), None);
+        // The 303 turns the POST into a GET, which already carries the cookies
+        assert_eq!((requests[1].method.as_str(), requests[1].header("cookie")), ("GET", Some("session=abc; theme=dark")));
+        assert_eq!(requests[2].header("cookie"), Some("session=abc; theme=dark"));
+    }
+
+    #[test]
+    fn revalidates_cached_pages() {
+        let server = TestServer::start(|request| match request.header("if-none-match") {
+            Some("\"v1\"") => TestResponse {
+                status: 304,
+                headers: vec![("ETag".to_string(), "\"v1\"".to_string())],
+                body: Vec::new(),
+            },
+            _ => {
+                let mut response = TestResponse::html("<p>Cached</p>");
+                response.headers.push(("ETag".to_string(), "\"v1\"".to_string()));
+                response.headers.push(("Cache-Control".to_string(), "no-cache".to_string()));
+                response
+            }
+        });
+        let dir = std::env::temp_dir().join(format!("fetch_cache_{}", std::process::id()));
+        let cache = HttpCache::open(dir.clone(), 1024 * 1024).shared();
+        let client = HttpClient::new("test", CookieJar::default().shared()).with_cache(cache);
+        let url = format!("{}/page", server.url);
+
+        fetch_page(&url, &client).unwrap();
+        let revalidated = fetch_page(&url, &client).unwrap();
+        // Back and forward don't ask the server at all
+        let history = fetch_request(&PageRequest::get(url.clone()).with_cache_mode(CacheMode::ForceCache), &client).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!((revalidated.status, revalidated.bytes.as_slice()), (200, b"<p>Cached</p>".as_slice()));
+        assert_eq!(history.bytes, b"<p>Cached</p>");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].header("if-none-match"), None);
+        assert_eq!(requests[1].header("if-none-match"), Some("\"v1\""));
+    }
+
+    #[test]
+    fn reloads_revalidate_or_bypass_the_cache() {
+        let server = TestServer::start(|request| match request.header("if-none-match") {
+            Some("\"v1\"") => TestResponse {
+                status: 304,
+                headers: Vec::new(),
+                body: Vec::new(),
+            },
+            _ => {
+                let mut response = TestResponse::html("<p>Fresh</p>");
+                response.headers.push(("ETag".to_string(), "\"v1\"".to_string()));
+                response.headers.push(("Cache-Control".to_string(), "max-age=3600".to_string()));
+                response
+            }
+        });
+        let dir = std::env::temp_dir().join(format!("fetch_reload_{}", std::process::id()));
+        let cache = HttpCache::open(dir.clone(), 1024 * 1024).shared();
+        let client = HttpClient::new("test", CookieJar::default().shared()).with_cache(cache);
+        let url = format!("{}/page", server.url);
+        let load = |mode| fetch_request(&PageRequest::get(url.clone()).with_cache_mode(mode), &client).unwrap();
+
+        load(CacheMode::Default);
+        // Fresh, so only the reloads go to the server
+        load(CacheMode::Default);
+        let reloaded = load(CacheMode::NoCache);
+        let hard = load(CacheMode::Reload);
+        std::fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!((reloaded.status, hard.status), (200, 200));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!((requests[1].header("if-none-match"), requests[1].header("cache-control")), (Some("\"v1\""), Some("max-age=0")));
+        assert_eq!((requests[2].header("if-none-match"), requests[2].header("pragma")), (None, Some("no-cache")));
+    }
+
+    #[test]
+    fn cancelled_requests_stop() {
+        let server = TestServer::start(|_| TestResponse::html("<p>Never shown</p>"));
+        let client = HttpClient::new("test", CookieJar::default().shared());
+        let cancel = CancelFlag::default();
+        cancel.cancel();
+
+        let result = fetch_cancellable(&PageRequest::get(format!("{}/", server.url)), &client, &cancel, &LoadProgress::default());
+        assert_eq!(result.unwrap_err(), CANCELLED);
+        assert!(server.requests().is_empty());
+    }
+
+    #[test]
+    fn reports_the_received_length() {
+        let server = TestServer::start(|_| TestResponse::html(&"x".repeat(3000)));
+        let client = HttpClient::new("test", CookieJar::default().shared());
+        let progress = LoadProgress::default();
+        fetch_cancellable(&PageRequest::get(format!("{}/", server.url)), &client, &CancelFlag::default(), &progress).unwrap();
+
+        let phase = progress.phase();
+        assert_eq!(phase, LoadPhase::Receiving { received: 3000, total: Some(3000) });
+        assert_eq!((phase.description().as_str(), phase.fraction()), ("Receiving 3 KB of 3 KB", Some(1.0)));
+        let partial = LoadPhase::Receiving { received: 1500, total: Some(3000) };
+        assert_eq!(partial.description(), "Receiving 2 KB of 3 KB");
+        assert_eq!(LoadPhase::Resolving("a.test".to_string()).description(), "Resolving a.test");
+    }
+}
@@ -0,0 +1,1134 @@
+// src/forms.rs
+// HTML forms: the state of a document's controls, and turning a submitted
+// form into a request. Controls are keyed by where their element starts in
+// the source, which stays the same for as long as the document is shown.
+use crate::document::HtmlPage;
+use crate::fetch::{Initiator, PageRequest, RequestBody};
+use crate::html_renderer::text_processor::{get_attribute, get_text_content};
+use crate::dates::{days_in_month, MAX_YEAR};
+use crate::url_resolver::{is_fetchable, resolve_url, scheme_of};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+// Source position (line, column) of an element's start tag
+pub type ControlKey = (usize, usize);
+
+pub fn control_key(element: &html_parser::Element) -> ControlKey {
+    (element.source_span.start_line, element.source_span.start_column)
+}
+
+// What the user has done to a control; controls they haven't touched show the
+// value from their markup
+#[derive(Clone, Debug, PartialEq)]
+pub enum ControlValue {
+    Text(String),
+    Checked(bool),
+    // Indices into the select's options
+    Selected(Vec<usize>),
+    Files(Vec<PathBuf>),
+}
+
+// Which form a submission is for: the form a control sits in, or the one its
+// `form` attribute names
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FormRef {
+    Key(ControlKey),
+    Id(String),
+}
+
+// A form the user submitted, waiting for the browser to load its response
+#[derive(Clone, Debug, PartialEq)]
+pub struct Submission {
+    pub form: FormRef,
+    // The button that submitted it, if any; Enter in a text field has none
+    pub submitter: Option<ControlKey>,
+}
+
+// The control a <label> stands for: the one its `for` attribute names, or
+// else the first one inside it
+#[derive(Clone, Debug, PartialEq)]
+pub enum LabelTarget {
+    Id(String),
+    Key(ControlKey),
+}
+
+impl LabelTarget {
+    fn targets(&self, control: &html_parser::Element) -> bool {
+        match self {
+            LabelTarget::Id(id) => control.id.as_deref() == Some(id.as_str()),
+            LabelTarget::Key(key) => control_key(control) == *key,
+        }
+    }
+}
+
+pub fn label_target(label: &html_parser::Element) -> Option<LabelTarget> {
+    match label.attributes.get("for") {
+        Some(id) => Some(LabelTarget::Id(id.clone().unwrap_or_default())),
+        None => find_element(&label.children, &is_control).map(|control| LabelTarget::Key(control_key(control))),
+    }
+}
+
+// Radio buttons with the same name in the same form; checking one unchecks the rest
+type RadioGroup = (Option<FormRef>, String);
+
+#[derive(Default)]
+pub struct FormState {
+    values: HashMap<ControlKey, ControlValue>,
+    // The radio button the user checked in each group
+    radio_groups: HashMap<RadioGroup, ControlKey>,
+    pub submission: Option<Submission>,
+    // A label the user clicked; its control acts as if clicked itself when
+    // it is next drawn
+    pub activation: Option<LabelTarget>,
+    // Validation message of every control whose value breaks its constraints
+    invalid: HashMap<ControlKey, String>,
+    // Whether `invalid` is up to date with the values
+    validated: bool,
+    // Controls the user has changed; only those are marked invalid, until a
+    // submission is blocked and all of them are
+    edited: HashSet<ControlKey>,
+    submit_blocked: bool,
+    // The control a blocked submission stopped at, whose message stays up
+    // until the user changes something
+    pub reported: Option<ControlKey>,
+    // Set when the page should scroll to the reported control
+    pub scroll_to_reported: bool,
+}
+
+impl FormState {
+    // Current text of a text field or textarea
+    pub fn text(&self, element: &html_parser::Element) -> String {
+        match self.values.get(&control_key(element)) {
+            Some(ControlValue::Text(text)) => text.clone(),
+            _ if element.name.eq_ignore_ascii_case("textarea") => get_text_content(&element.children),
+            _ => get_attribute(element, "value", ""),
+        }
+    }
+
+    pub fn set_text(&mut self, element: &html_parser::Element, text: String) {
+        self.edit(element);
+        self.values.insert(control_key(element), ControlValue::Text(text));
+    }
+
+    // Whether a checkbox is checked
+    pub fn checked(&self, element: &html_parser::Element) -> bool {
+        match self.values.get(&control_key(element)) {
+            Some(ControlValue::Checked(checked)) => *checked,
+            _ => element.attributes.contains_key("checked"),
+        }
+    }
+
+    pub fn set_checked(&mut self, element: &html_parser::Element, checked: bool) {
+        self.edit(element);
+        self.values.insert(control_key(element), ControlValue::Checked(checked));
+    }
+
+    // Whether a radio button owned by `form` is checked
+    pub fn radio_checked(&self, element: &html_parser::Element, form: Option<&FormRef>) -> bool {
+        match self.radio_groups.get(&radio_group(element, form)) {
+            Some(key) => *key == control_key(element),
+            None => element.attributes.contains_key("checked"),
+        }
+    }
+
+    pub fn check_radio(&mut self, element: &html_parser::Element, form: Option<&FormRef>) {
+        self.edit(element);
+        self.radio_groups.insert(radio_group(element, form), control_key(element));
+    }
+
+    // Indices of a select's chosen options. Untouched, that's the options
+    // marked selected; a single-choice select keeps only the last of them, or
+    // falls back to its first enabled option.
+    pub fn selected(&self, select: &html_parser::Element) -> Vec<usize> {
+        if let Some(ControlValue::Selected(selected)) = self.values.get(&control_key(select)) {
+            return selected.clone();
+        }
+        let options = select_options(select);
+        let mut selected: Vec<usize> = (0..options.len())
+            .filter(|&index| options[index].element.attributes.contains_key("selected"))
+            .collect();
+        if !is_multiple(select) {
+            selected = match selected.last() {
+                Some(&last) => vec![last],
+                None => options.iter().position(|option| !option.disabled).into_iter().collect(),
+            };
+        }
+        selected
+    }
+
+    pub fn set_selected(&mut self, select: &html_parser::Element, selected: Vec<usize>) {
+        self.edit(select);
+        self.values.insert(control_key(select), ControlValue::Selected(selected));
+    }
+
+    // Files chosen in a file input
+    pub fn files(&self, element: &html_parser::Element) -> Vec<PathBuf> {
+        match self.values.get(&control_key(element)) {
+            Some(ControlValue::Files(files)) => files.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn set_files(&mut self, element: &html_parser::Element, files: Vec<PathBuf>) {
+        self.edit(element);
+        self.values.insert(control_key(element), ControlValue::Files(files));
+    }
+
+    fn edit(&mut self, element: &html_parser::Element) {
+        self.edited.insert(control_key(element));
+        self.validated = false;
+        self.reported = None;
+    }
+
+    // The message to show on a control that should be marked invalid
+    pub fn validation_message(&self, element: &html_parser::Element) -> Option<&str> {
+        let key = control_key(element);
+        let shown = self.submit_blocked || self.edited.contains(&key);
+        self.invalid.get(&key).filter(|_| shown).map(String::as_str)
+    }
+
+    // Whether a label for this control was clicked; the click is used up
+    pub fn take_activation(&mut self, control: &html_parser::Element) -> bool {
+        let activated = self.activation.as_ref().is_some_and(|target| target.targets(control));
+        if activated {
+            self.activation = None;
+        }
+        activated
+    }
+}
+
+fn radio_group(element: &html_parser::Element, form: Option<&FormRef>) -> RadioGroup {
+    (form.cloned(), get_attribute(element, "name", ""))
+}
+
+// An <option> of a select, with the <optgroup> it's in
+pub struct SelectOption<'a> {
+    pub element: &'a html_parser::Element,
+    pub group: Option<&'a html_parser::Element>,
+    // Disabled itself or by its group
+    pub disabled: bool,
+}
+
+pub fn select_options(select: &html_parser::Element) -> Vec<SelectOption<'_>> {
+    let mut options = Vec::new();
+    for node in &select.children {
+        let html_parser::Node::Element(element) = node else {
+            continue;
+        };
+        if element.name.eq_ignore_ascii_case("option") {
+            let disabled = element.attributes.contains_key("disabled");
+            options.push(SelectOption { element, group: None, disabled });
+        } else if element.name.eq_ignore_ascii_case("optgroup") {
+            for child in &element.children {
+                if let html_parser::Node::Element(option) = child {
+                    if option.name.eq_ignore_ascii_case("option") {
+                        let disabled = element.attributes.contains_key("disabled") || option.attributes.contains_key("disabled");
+                        options.push(SelectOption { element: option, group: Some(element), disabled });
+                    }
+                }
+            }
+        }
+    }
+    options
+}
+
+pub fn is_multiple(select: &html_parser::Element) -> bool {
+    select.attributes.contains_key("multiple")
+}
+
+// An option's label attribute, or else its text
+pub fn option_label(option: &html_parser::Element) -> String {
+    match option.attributes.get("label").cloned().flatten() {
+        Some(label) if !label.is_empty() => label,
+        _ => get_text_content(&option.children),
+    }
+}
+
+pub fn input_type(input: &html_parser::Element) -> String {
+    get_attribute(input, "type", "text").to_lowercase()
+}
+
+// Bounds of a number or range input. Step is None for step="any".
+pub struct NumberBounds {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub step: Option<f64>,
+    // Decimal places in min and step, which values are rounded to
+    pub decimals: usize,
+}
+
+pub fn number_bounds(input: &html_parser::Element) -> NumberBounds {
+    let number = |name: &str| {
+        let value = get_attribute(input, name, "");
+        value.trim().parse::<f64>().ok().filter(|value| value.is_finite()).map(|number| (number, value))
+    };
+    let decimals = |value: &str| value.trim().split_once('.').map_or(0, |(_, fraction)| fraction.len()).min(10);
+    let min = number("min");
+    let step = if get_attribute(input, "step", "").trim().eq_ignore_ascii_case("any") {
+        None
+    } else {
+        Some(number("step").filter(|(step, _)| *step > 0.0).unwrap_or((1.0, String::new())))
+    };
+    NumberBounds {
+        decimals: decimals(min.as_ref().map_or("", |(_, text)| text)).max(decimals(step.as_ref().map_or("", |(_, text)| text))),
+        min: min.map(|(min, _)| min),
+        max: number("max").map(|(max, _)| max),
+        step: step.map(|(step, _)| step),
+    }
+}
+
+impl NumberBounds {
+    // Clamp a value into the bounds and round it to the nearest step from min
+    pub fn constrain(&self, value: f64) -> f64 {
+        let mut value = value;
+        if let Some(min) = self.min {
+            value = value.max(min);
+        }
+        if let Some(max) = self.max {
+            value = value.min(max);
+        }
+        if let Some(step) = self.step {
+            let base = self.min.unwrap_or(0.0);
+            value = base + ((value - base) / step).round() * step;
+            if self.max.is_some_and(|max| value > max) {
+                value -= step;
+            }
+        }
+        let scale = 10f64.powi(self.decimals as i32);
+        (value * scale).round() / scale
+    }
+}
+
+// A range input's value: a number within min..max, defaulting to 0..100, and
+// halfway when it has none
+pub fn range_value(input: &html_parser::Element, value: &str) -> f64 {
+    let mut bounds = number_bounds(input);
+    let min = bounds.min.unwrap_or(0.0);
+    let max = bounds.max.unwrap_or(100.0).max(min);
+    (bounds.min, bounds.max) = (Some(min), Some(max));
+    let value = value.trim().parse::<f64>().ok().filter(|value| value.is_finite());
+    bounds.constrain(value.unwrap_or(min + (max - min) / 2.0))
+}
+
+// Shortest form of a number, e.g. 3 rather than 3.0
+pub fn format_number(value: f64) -> String {
+    format!("{}", value)
+}
+
+// A simple color, #rrggbb
+pub fn parse_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.trim().strip_prefix('#')?;
+    if hex.len() != 6 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+pub fn format_color([red, green, blue]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", red, green, blue)
+}
+
+// A valid date string, yyyy-mm-dd
+pub fn parse_date(value: &str) -> Option<(i32, u32, u32)> {
+    let mut parts = value.trim().splitn(3, '-');
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+    let digits = |part: &str, length: usize| part.len() >= length && part.bytes().all(|byte| byte.is_ascii_digit());
+    if !digits(year, 4) || !digits(month, 2) || month.len() != 2 || !digits(day, 2) || day.len() != 2 {
+        return None;
+    }
+    let (year, month, day) = (year.parse().ok()?, month.parse().ok()?, day.parse().ok()?);
+    let valid = (1..=MAX_YEAR).contains(&year) && (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day);
+    valid.then_some((year, month, day))
+}
+
+pub fn format_date((year, month, day): (i32, u32, u32)) -> String {
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// The value an input submits, cleaned up as its type requires: numbers and
+// dates that don't parse are dropped, colors fall back to black, sliders
+// always hold a number in range and single-line text loses its line breaks
+pub fn sanitize_value(input: &html_parser::Element, value: &str) -> String {
+    match input_type(input).as_str() {
+        "number" => match value.trim().parse::<f64>() {
+            Ok(number) if number.is_finite() => value.trim().to_string(),
+            _ => String::new(),
+        },
+        "range" => format_number(range_value(input, value)),
+        "color" => format_color(parse_color(value).unwrap_or_default()),
+        "date" => parse_date(value).map(format_date).unwrap_or_default(),
+        _ => value.replace(['\r', '\n'], ""),
+    }
+}
+
+// Recheck every control's constraints if any value changed since last time
+pub fn update_validity(page: &HtmlPage, state: &mut FormState) {
+    if state.validated {
+        return;
+    }
+    let mut controls = Vec::new();
+    walk_controls(&page.body, None, false, &mut controls);
+    // A required radio button is satisfied by any button in its group
+    let checked_groups: HashSet<RadioGroup> = controls
+        .iter()
+        .filter(|control| input_type(control.element) == "radio" && state.radio_checked(control.element, control.owner.as_ref()))
+        .map(|control| radio_group(control.element, control.owner.as_ref()))
+        .collect();
+    state.invalid = controls
+        .iter()
+        .filter_map(|control| {
+            let message = validate(control, state, &checked_groups)?;
+            Some((control_key(control.element), message))
+        })
+        .collect();
+    state.validated = true;
+}
+
+// Interactive validation before a submission: the first control of the form
+// that fails its constraints, which stops the submission. Forms marked
+// novalidate and buttons marked formnovalidate skip it.
+pub fn blocking_control(page: &HtmlPage, submission: &Submission, state: &mut FormState) -> Option<ControlKey> {
+    let form = find_form(&page.body, &submission.form)?;
+    let submitter = submission
+        .submitter
+        .and_then(|key| find_element(&page.body, &|element| control_key(element) == key));
+    if form.attributes.contains_key("novalidate") || submitter.is_some_and(|submitter| submitter.attributes.contains_key("formnovalidate")) {
+        return None;
+    }
+    update_validity(page, state);
+    let blocking = owned_controls(&page.body, form)
+        .iter()
+        .map(|control| control_key(control.element))
+        .find(|key| state.invalid.contains_key(key))?;
+    state.submit_blocked = true;
+    state.reported = Some(blocking);
+    state.scroll_to_reported = true;
+    Some(blocking)
+}
+
+// Why a control's value breaks its constraints, in the words browsers use.
+// Disabled, readonly and button controls are barred from validation.
+fn validate(control: &Control, state: &FormState, checked_groups: &HashSet<RadioGroup>) -> Option<String> {
+    let element = control.element;
+    if control.disabled {
+        return None;
+    }
+    let required = element.attributes.contains_key("required");
+    let readonly = element.attributes.contains_key("readonly");
+    match element.name.to_lowercase().as_str() {
+        "textarea" if !readonly => validate_text(element, &state.text(element), required),
+        "select" => {
+            let options = select_options(element);
+            let selected = state.selected(element);
+            // A first option with an empty value is a placeholder, like "Choose one"
+            let placeholder = !is_multiple(element)
+                && get_attribute(element, "size", "1").parse::<usize>().unwrap_or(1) <= 1
+                && options.first().is_some_and(|option| option.group.is_none() && option_value(option.element).is_empty());
+            let missing = selected.is_empty() || (placeholder && selected == [0]);
+            (required && missing).then(|| "Please select an item in the list.".to_string())
+        }
+        "input" => match input_type(element).as_str() {
+            "hidden" | "submit" | "image" | "reset" | "button" | "range" | "color" => None,
+            "checkbox" => (required && !state.checked(element)).then(|| "Please check this box if you want to proceed.".to_string()),
+            "radio" => {
+                let group = radio_group(element, control.owner.as_ref());
+                (required && !checked_groups.contains(&group)).then(|| "Please select one of these options.".to_string())
+            }
+            "file" => (required && state.files(element).is_empty()).then(|| "Please select a file.".to_string()),
+            _ if readonly => None,
+            "number" => {
+                let value = sanitize_value(element, &state.text(element));
+                let number = value.parse::<f64>().ok();
+                let limit = |name: &str| get_attribute(element, name, "").trim().parse::<f64>().ok();
+                if value.is_empty() {
+                    required.then(|| "Please fill out this field.".to_string())
+                } else {
+                    range_message(number, limit("min"), limit("max"), |limit| format_number(*limit))
+                }
+            }
+            "date" => {
+                let value = sanitize_value(element, &state.text(element));
+                let limit = |name: &str| parse_date(&get_attribute(element, name, ""));
+                if value.is_empty() {
+                    required.then(|| "Please fill out this field.".to_string())
+                } else {
+                    range_message(parse_date(&value), limit("min"), limit("max"), |date| format_date(*date))
+                }
+            }
+            _ => validate_text(element, &state.text(element), required),
+        },
+        _ => None,
+    }
+}
+
+fn range_message<T: PartialOrd>(value: Option<T>, min: Option<T>, max: Option<T>, format: impl Fn(&T) -> String) -> Option<String> {
+    let value = value?;
+    if let Some(min) = min.filter(|min| value < *min) {
+        return Some(format!("Value must be greater than or equal to {}.", format(&min)));
+    }
+    max.filter(|max| value > *max)
+        .map(|max| format!("Value must be less than or equal to {}.", format(&max)))
+}
+
+// Constraints on text: required, type=email and url, pattern, minlength and maxlength
+fn validate_text(element: &html_parser::Element, value: &str, required: bool) -> Option<String> {
+    if value.is_empty() {
+        return required.then(|| "Please fill out this field.".to_string());
+    }
+    let textarea = element.name.eq_ignore_ascii_case("textarea");
+    let input_type = input_type(element);
+    // Lengths count UTF-16 code units, as in JavaScript
+    let length = value.encode_utf16().count();
+    let limit = |name: &str| get_attribute(element, name, "").trim().parse::<usize>().ok();
+
+    if !textarea && input_type == "email" {
+        let multiple = element.attributes.contains_key("multiple");
+        let addresses: Vec<&str> = if multiple { value.split(',').map(str::trim).collect() } else { vec![value] };
+        if !addresses.iter().all(|address| is_valid_email(address)) {
+            return Some("Please enter an email address.".to_string());
+        }
+    }
+    if !textarea && input_type == "url" && !is_valid_url(value) {
+        return Some("Please enter a URL.".to_string());
+    }
+    let pattern = get_attribute(element, "pattern", "");
+    if !textarea && !pattern.is_empty() && !matches_pattern(&pattern, value) {
+        let title = get_attribute(element, "title", "");
+        return Some(if title.is_empty() {
+            "Please match the requested format.".to_string()
+        } else {
+            format!("Please match the requested format: {}", title)
+        });
+    }
+    if let Some(min) = limit("minlength").filter(|min| length < *min) {
+        return Some(format!(
+            "Please lengthen this text to {} characters or more (you are currently using {} characters).",
+            min, length
+        ));
+    }
+    limit("maxlength").filter(|max| length > *max).map(|max| {
+        format!(
+            "Please shorten this text to {} characters or less (you are currently using {} characters).",
+            max, length
+        )
+    })
+}
+
+// The valid e-mail address syntax from the HTML standard
+fn is_valid_email(address: &str) -> bool {
+    let Some((local, domain)) = address.split_once('@') else {
+        return false;
+    };
+    let local_ok = !local.is_empty() && local.chars().all(|c| c.is_ascii_alphanumeric() || ".!#$%&'*+/=?^_`{|}~-".contains(c));
+    let label_ok = |label: &str| {
+        (1..=63).contains(&label.len())
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+    };
+    local_ok && domain.split('.').all(label_ok)
+}
+
+// An absolute URL: a scheme followed by something, with no spaces
+fn is_valid_url(value: &str) -> bool {
+    scheme_of(value).is_some() && value.len() > value.find(':').map_or(0, |colon| colon + 1) && !value.contains(char::is_whitespace)
+}
+
+// `pattern` must match the whole value. A pattern that doesn't compile is ignored.
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    match regex::Regex::new(&format!("^(?:{})$", pattern)) {
+        Ok(regex) => regex.is_match(value),
+        Err(_) => true,
+    }
+}
+
+// How the form data set is written into the request body
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    UrlEncoded,
+    Multipart,
+    TextPlain,
+}
+
+// Build the request a submission makes, following the HTML form submission
+// algorithm: the submitter's formaction, formmethod and formenctype override
+// the form's own attributes. None when the form is gone or the action can't
+// be loaded.
+pub fn submission_request(page: &HtmlPage, submission: &Submission, state: &FormState) -> Option<PageRequest> {
+    let form = find_form(&page.body, &submission.form)?;
+    let submitter = submission
+        .submitter
+        .and_then(|key| find_element(&page.body, &|element| control_key(element) == key));
+    let attribute = |form_name: &str, name: &str| {
+        submitter
+            .and_then(|submitter| submitter.attributes.get(form_name).cloned().flatten())
+            .or_else(|| form.attributes.get(name).cloned().flatten())
+            .unwrap_or_default()
+    };
+
+    let action = attribute("formaction", "action");
+    let url = if action.trim().is_empty() {
+        page.url.clone()
+    } else {
+        resolve_url(&page.base_url, action.trim())
+    };
+    if !is_fetchable(&url) {
+        return None;
+    }
+
+    let entries = form_data(&page.body, form, submission.submitter, state);
+    let initiator = Initiator::Page(page.url.clone());
+    if !attribute("formmethod", "method").trim().eq_ignore_ascii_case("post") {
+        return Some(PageRequest::get(with_query(&url, &urlencode(&entries))).initiated_by(initiator));
+    }
+
+    let body = match encoding(&attribute("formenctype", "enctype")) {
+        Encoding::UrlEncoded => RequestBody {
+            content_type: "application/x-www-form-urlencoded".to_string(),
+            bytes: urlencode(&entries).into_bytes(),
+        },
+        Encoding::Multipart => {
+            let boundary = multipart_boundary();
+            RequestBody {
+                content_type: format!("multipart/form-data; boundary={}", boundary),
+                bytes: multipart(&entries, &boundary),
+            }
+        }
+        Encoding::TextPlain => RequestBody {
+            content_type: "text/plain;charset=UTF-8".to_string(),
+            bytes: entries.iter().map(|(name, value)| format!("{}={}\r\n", name, value.text())).collect::<String>().into_bytes(),
+        },
+    };
+    Some(PageRequest {
+        body: Some(body),
+        ..PageRequest::get(url).initiated_by(initiator)
+    })
+}
+
+fn encoding(enctype: &str) -> Encoding {
+    match enctype.trim().to_ascii_lowercase().as_str() {
+        "multipart/form-data" => Encoding::Multipart,
+        "text/plain" => Encoding::TextPlain,
+        _ => Encoding::UrlEncoded,
+    }
+}
+
+fn find_form<'a>(nodes: &'a [html_parser::Node], form: &FormRef) -> Option<&'a html_parser::Element> {
+    find_element(nodes, &|element| {
+        element.name.eq_ignore_ascii_case("form")
+            && match form {
+                FormRef::Key(key) => control_key(element) == *key,
+                FormRef::Id(id) => element.id.as_deref() == Some(id.as_str()),
+            }
+    })
+}
+
+fn find_element<'a>(
+    nodes: &'a [html_parser::Node],
+    matches: &dyn Fn(&html_parser::Element) -> bool,
+) -> Option<&'a html_parser::Element> {
+    nodes.iter().find_map(|node| match node {
+        html_parser::Node::Element(element) if matches(element) => Some(element),
+        html_parser::Node::Element(element) => find_element(&element.children, matches),
+        _ => None,
+    })
+}
+
+pub fn is_control(element: &html_parser::Element) -> bool {
+    matches!(element.name.to_lowercase().as_str(), "input" | "button" | "select" | "textarea")
+}
+
+// Index of a fieldset's first <legend> child, whose controls a disabled
+// fieldset leaves enabled
+pub fn first_legend(children: &[html_parser::Node]) -> Option<usize> {
+    children
+        .iter()
+        .position(|child| matches!(child, html_parser::Node::Element(element) if element.name.eq_ignore_ascii_case("legend")))
+}
+
+// A control in the document, with the form it belongs to and whether it is
+// disabled, by its own attribute or a fieldset's
+struct Control<'a> {
+    element: &'a html_parser::Element,
+    owner: Option<FormRef>,
+    disabled: bool,
+}
+
+// Every control in tree order. A control belongs to the form its `form`
+// attribute names, or else to the form it is in.
+fn walk_controls<'a>(nodes: &'a [html_parser::Node], form: Option<ControlKey>, disabled: bool, controls: &mut Vec<Control<'a>>) {
+    for node in nodes {
+        let html_parser::Node::Element(element) = node else {
+            continue;
+        };
+        let form = if element.name.eq_ignore_ascii_case("form") { Some(control_key(element)) } else { form };
+        if is_control(element) {
+            let owner = match element.attributes.get("form").cloned().flatten() {
+                Some(id) => Some(FormRef::Id(id)),
+                None => form.map(FormRef::Key),
+            };
+            let disabled = disabled || element.attributes.contains_key("disabled");
+            controls.push(Control { element, owner, disabled });
+        }
+        if element.name.eq_ignore_ascii_case("fieldset") && element.attributes.contains_key("disabled") {
+            let legend = first_legend(&element.children);
+            for (index, child) in element.children.iter().enumerate() {
+                let disabled = disabled || legend != Some(index);
+                walk_controls(std::slice::from_ref(child), form, disabled, controls);
+            }
+        } else {
+            walk_controls(&element.children, form, disabled, controls);
+        }
+    }
+}
+
+// The controls a form owns, in tree order
+fn owned_controls<'a>(nodes: &'a [html_parser::Node], form: &html_parser::Element) -> Vec<Control<'a>> {
+    let mut controls = Vec::new();
+    walk_controls(nodes, None, false, &mut controls);
+    controls.retain(|control| match &control.owner {
+        Some(FormRef::Key(key)) => *key == control_key(form),
+        Some(FormRef::Id(id)) => form.id.as_deref() == Some(id.as_str()),
+        None => false,
+    });
+    controls
+}
+
+// A value in the form data set
+#[derive(Clone, Debug, PartialEq)]
+pub enum EntryValue {
+    Text(String),
+    // An empty file input still sends a part, without a file
+    File(Option<PathBuf>),
+}
+
+impl EntryValue {
+    // The value as text; files are sent by name outside multipart bodies
+    fn text(&self) -> String {
+        match self {
+            EntryValue::Text(text) => text.clone(),
+            EntryValue::File(path) => path.as_deref().map(file_name).unwrap_or_default(),
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+// The form data set: a name and value for each control that counts. Disabled
+// and unnamed controls, unchecked boxes and buttons other than the submitter
+// are left out.
+fn form_data(
+    nodes: &[html_parser::Node],
+    form: &html_parser::Element,
+    submitter: Option<ControlKey>,
+    state: &FormState,
+) -> Vec<(String, EntryValue)> {
+    let mut entries = Vec::new();
+    for Control { element: control, owner, disabled } in owned_controls(nodes, form) {
+        let name = get_attribute(control, "name", "");
+        if name.is_empty() || disabled {
+            continue;
+        }
+        let is_submitter = submitter == Some(control_key(control));
+        let value = || get_attribute(control, "value", "");
+        match control.name.to_lowercase().as_str() {
+            "input" => match input_type(control).as_str() {
+                "checkbox" => {
+                    if state.checked(control) {
+                        entries.push(text_entry(name, get_attribute(control, "value", "on")));
+                    }
+                }
+                "radio" => {
+                    if state.radio_checked(control, owner.as_ref()) {
+                        entries.push(text_entry(name, get_attribute(control, "value", "on")));
+                    }
+                }
+                "file" => {
+                    let files = state.files(control);
+                    if files.is_empty() {
+                        entries.push((name, EntryValue::File(None)));
+                    } else {
+                        for file in files {
+                            entries.push((name.clone(), EntryValue::File(Some(file))));
+                        }
+                    }
+                }
+                "submit" if is_submitter => entries.push(text_entry(name, value())),
+                "image" if is_submitter => {
+                    entries.push(text_entry(format!("{}.x", name), "0".to_string()));
+                    entries.push(text_entry(format!("{}.y", name), "0".to_string()));
+                }
+                "submit" | "image" | "button" | "reset" => {}
+                _ => entries.push(text_entry(name, sanitize_value(control, &state.text(control)))),
+            },
+            "button" => {
+                let kind = get_attribute(control, "type", "submit").to_lowercase();
+                if is_submitter && kind == "submit" {
+                    entries.push(text_entry(name, value()));
+                }
+            }
+            "textarea" => entries.push(text_entry(name, state.text(control))),
+            "select" => {
+                let options = select_options(control);
+                for index in state.selected(control) {
+                    if let Some(option) = options.get(index).filter(|option| !option.disabled) {
+                        entries.push(text_entry(name.clone(), option_value(option.element)));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    // Line breaks are sent as CRLF
+    entries
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                EntryValue::Text(text) => EntryValue::Text(normalize_newlines(&text)),
+                file => file,
+            };
+            (normalize_newlines(&name), value)
+        })
+        .collect()
+}
+
+fn text_entry(name: String, value: String) -> (String, EntryValue) {
+    (name, EntryValue::Text(value))
+}
+
+// An option's value attribute, or else its text
+fn option_value(option: &html_parser::Element) -> String {
+    match option.attributes.get("value") {
+        Some(value) => value.clone().unwrap_or_default(),
+        None => get_text_content(&option.children),
+    }
+}
+
+fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n").replace('\n', "\r\n")
+}
+
+// application/x-www-form-urlencoded: UTF-8, percent-encoded, spaces as +
+pub fn urlencode(entries: &[(String, EntryValue)]) -> String {
+    entries
+        .iter()
+        .map(|(name, value)| format!("{}={}", urlencode_component(name), urlencode_component(&value.text())))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+// One name or value of a urlencoded query, also used for search terms
+pub fn urlencode_component(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => encoded.push(byte as char),
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+// multipart/form-data (RFC 7578), one part per entry. Files are read when
+// the body is built; one that can't be read is sent empty.
+pub fn multipart(entries: &[(String, EntryValue)], boundary: &str) -> Vec<u8> {
+    // Quotes and line breaks can't appear in quoted names
+    let escape = |name: &str| name.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A");
+    let mut body = Vec::new();
+    for (name, value) in entries {
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        body.extend_from_slice(format!("Content-Disposition: form-data; name=\"{}\"", escape(name)).as_bytes());
+        match value {
+            EntryValue::Text(text) => {
+                body.extend_from_slice(b"\r\n\r\n");
+                body.extend_from_slice(text.as_bytes());
+            }
+            EntryValue::File(path) => {
+                let file_name = escape(&value.text());
+                body.extend_from_slice(format!("; filename=\"{}\"\r\n", file_name).as_bytes());
+                body.extend_from_slice(b"Content-Type: application/octet-stream\r\n\r\n");
+                if let Some(path) = path {
+                    body.extend_from_slice(&std::fs::read(path).unwrap_or_default());
+                }
+            }
+        }
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+// A boundary unlikely to turn up in the data
+fn multipart_boundary() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    format!("----EguiBrowserFormBoundary{:x}", nanos)
+}
+
+// The action URL with its query replaced by the form data; any fragment stays
+fn with_query(url: &str, query: &str) -> String {
+    let (url, fragment) = match url.split_once('#') {
+        Some((url, fragment)) => (url, Some(fragment)),
+        None => (url, None),
+    };
+    let base = url.split_once('?').map_or(url, |(base, _)| base);
+    let mut result = format!("{}?{}", base, query);
+    if let Some(fragment) = fragment {
+        result.push('#');
+        result.push_str(fragment);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::LinkHandler;
+    use crate::cookies::CookieJar;
+    use crate::fetch::{fetch_request, HttpClient};
+    use crate::html_renderer::HtmlRenderer;
+    use crate::style::create_default_styles;
+    use crate::test_server::{TestResponse, TestServer};
+
+    fn page(url: &str, html: &str) -> HtmlPage {
+        let renderer = HtmlRenderer::new(create_default_styles(), LinkHandler::new());
+        HtmlPage::new(url, html.to_string(), &renderer)
+    }
+
+    fn element<'a>(page: &'a HtmlPage, name: &str) -> &'a html_parser::Element {
+        find_element(&page.body, &|element| get_attribute(element, "name", "") == name).unwrap()
+    }
+
+    fn first_form(page: &HtmlPage) -> FormRef {
+        FormRef::Key(control_key(find_form_by_tag(&page.body)))
+    }
+
+    fn find_form_by_tag(nodes: &[html_parser::Node]) -> &html_parser::Element {
+        find_element(nodes, &|element| element.name == "form").unwrap()
+    }
+
+    const SEARCH_FORM: &str = r#"<html><body>
+        <form action="/search?old=1#results">
+          <input name="q" value="default">
+          <input type="checkbox" name="safe" checked>
+          <input type="checkbox" name="images" value="yes">
+          <input name="disabled" value="x" disabled>
+          <input type="hidden" name="lang" value="en">
+          <select name="sort"><option value="new">Newest</option><option selected>Oldest</option></select>
+          <button name="go" value="1">Search</button>
+          <button name="other" value="2">Other</button>
+        </form>
+        <input name="outside" value="o" form="missing">
+        </body></html>"#;
+
+    #[test]
+    fn submits_successful_controls_with_get() {
+        let page = page("http://example.com/dir/page.html", SEARCH_FORM);
+        let mut state = FormState::default();
+        state.set_text(element(&page, "q"), "rust forms & more".to_string());
+        state.set_checked(element(&page, "images"), true);
+        let submission = Submission {
+            form: first_form(&page),
+            submitter: Some(control_key(element(&page, "go"))),
+        };
+
+        let request = submission_request(&page, &submission, &state).unwrap();
+        assert_eq!(
+            request.url,
+            "http://example.com/search?q=rust+forms+%26+more&safe=on&images=yes&lang=en&sort=Oldest&go=1#results"
+        );
+        assert_eq!(request.method(), "GET");
+    }
+
+    #[test]
+    fn encodes_multipart_bodies() {
+        let path = std::env::temp_dir().join(format!("form_upload_{}.txt", std::process::id()));
+        std::fs::write(&path, "file body").unwrap();
+        let entries = vec![
+            ("a\"b".to_string(), EntryValue::Text("line1\r\nline2".to_string())),
+            ("é".to_string(), EntryValue::Text(String::new())),
+            ("upload".to_string(), EntryValue::File(Some(path.clone()))),
+            ("none".to_string(), EntryValue::File(None)),
+        ];
+        let body = String::from_utf8(multipart(&entries, "XYZ")).unwrap();
+        let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            body,
+            format!(
+                "--XYZ\r\nContent-Disposition: form-data; name=\"a%22b\"\r\n\r\nline1\r\nline2\r\n\
+                 --XYZ\r\nContent-Disposition: form-data; name=\"é\"\r\n\r\n\r\n\
+                 --XYZ\r\nContent-Disposition: form-data; name=\"upload\"; filename=\"{}\"\r\n\
+                 Content-Type: application/octet-stream\r\n\r\nfile body\r\n\
+                 --XYZ\r\nContent-Disposition: form-data; name=\"none\"; filename=\"\"\r\n\
+                 Content-Type: application/octet-stream\r\n\r\n\r\n--XYZ--\r\n",
+                file_name
+            )
+        );
+        assert_eq!(urlencode(&entries[..2]), "a%22b=line1%0D%0Aline2&%C3%A9=");
+    }
+
+    #[test]
+    fn collects_radios_selects_and_fieldsets() {
+        let page = page(
+            "http://example.com/",
+            r##"<form>
+              <input type="radio" name="size" value="s" checked><input type="radio" name="size" value="l">
+              <select name="one"><option>A</option><option selected>B</option><option selected>C</option></select>
+              <select name="many" multiple><optgroup label="G" disabled><option selected>X</option></optgroup><option selected>Y</option></select>
+              <fieldset disabled><legend><input name="in_legend" value="1"></legend><input name="inside" value="2"></fieldset>
+              <input type="range" name="level" min="0" max="10" step="3">
+              <input type="number" name="count" value="many">
+              <input type="color" name="shade" value="#ABCDEF">
+              <input type="date" name="day" value="2023-02-29">
+            </form>"##,
+        );
+        let form = first_form(&page);
+        let mut state = FormState::default();
+        let entries = |state: &FormState| {
+            let Some(PageRequest { url, .. }) = submission_request(&page, &Submission { form: form.clone(), submitter: None }, state) else {
+                panic!("no request");
+            };
+            url.split_once('?').unwrap().1.to_string()
+        };
+        assert_eq!(entries(&state), "size=s&one=C&many=Y&in_legend=1&level=6&count=&shade=%23abcdef&day=");
+
+        // Checking a radio unchecks the rest of its group
+        let large = find_element(&page.body, &|element| get_attribute(element, "value", "") == "l").unwrap();
+        state.check_radio(large, Some(&form));
+        state.set_selected(element(&page, "one"), vec![0]);
+        state.set_text(element(&page, "level"), "11".to_string());
+        assert_eq!(entries(&state), "size=l&one=A&many=Y&in_legend=1&level=9&count=&shade=%23abcdef&day=");
+    }
+
+    #[test]
+    fn constrains_numbers_and_dates() {
+        let page = page("http://example.com/", r#"<input name="n" min="0.5" max="2" step="0.25">"#);
+        let bounds = number_bounds(element(&page, "n"));
+        assert_eq!(bounds.constrain(0.1), 0.5);
+        assert_eq!(bounds.constrain(1.3), 1.25);
+        assert_eq!(bounds.constrain(5.0), 2.0);
+        assert_eq!(format_number(1.0), "1");
+
+        assert_eq!(parse_date("2024-02-29"), Some((2024, 2, 29)));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2024-2-01"), None);
+        assert_eq!(parse_date("275760-09-13"), Some((275_760, 9, 13)));
+        assert_eq!(parse_date("275761-01-01"), None);
+        assert_eq!(parse_date("2147483647-12-01"), None);
+    }
+
+    #[test]
+    fn validates_constraints() {
+        let page = page(
+            "http://example.com/",
+            r#"<form>
+              <input name="name" required>
+              <input name="code" pattern="[A-Z]{3}" value="abc" title="Three capitals">
+              <input name="short" minlength="3" value="ab">
+              <input name="mail" type="email" value="me@example">
+              <input name="mails" type="email" multiple value="a@b.c, x">
+              <input name="site" type="url" value="example.com">
+              <input name="count" type="number" min="1" max="5" value="9">
+              <input name="day" type="date" min="2024-01-01" value="2023-12-31">
+              <input name="fixed" required readonly>
+              <select name="pick" required><option value="">Choose</option><option>A</option></select>
+              <input type="radio" name="plan" value="a" required><input type="radio" name="plan" value="b">
+              <input type="checkbox" name="terms" required>
+            </form>"#,
+        );
+        let mut state = FormState::default();
+        update_validity(&page, &mut state);
+        let message = |state: &FormState, name: &str| state.invalid.get(&control_key(element(&page, name))).cloned();
+
+        assert_eq!(message(&state, "name").as_deref(), Some("Please fill out this field."));
+        assert_eq!(message(&state, "code").as_deref(), Some("Please match the requested format: Three capitals"));
+        assert!(message(&state, "short").unwrap().starts_with("Please lengthen this text to 3 characters"));
+        assert_eq!(message(&state, "mail"), None);
+        assert_eq!(message(&state, "mails").as_deref(), Some("Please enter an email address."));
+        assert_eq!(message(&state, "site").as_deref(), Some("Please enter a URL."));
+        assert_eq!(message(&state, "count").as_deref(), Some("Value must be less than or equal to 5."));
+        assert_eq!(message(&state, "day").as_deref(), Some("Value must be greater than or equal to 2024-01-01."));
+        assert_eq!(message(&state, "fixed"), None);
+        assert_eq!(message(&state, "pick").as_deref(), Some("Please select an item in the list."));
+        assert_eq!(message(&state, "plan").as_deref(), Some("Please select one of these options."));
+        assert!(message(&state, "terms").is_some());
+
+        // Messages show once the user edits a control
+        assert_eq!(state.validation_message(element(&page, "code")), None);
+        state.set_text(element(&page, "code"), "ABC".to_string());
+        state.set_text(element(&page, "name"), "x".to_string());
+        update_validity(&page, &mut state);
+        assert_eq!(message(&state, "code"), None);
+        assert_eq!(state.validation_message(element(&page, "name")), None);
+        state.set_text(element(&page, "short"), "a".to_string());
+        update_validity(&page, &mut state);
+        assert!(state.validation_message(element(&page, "short")).is_some());
+
+        // A blocked submission reports the first invalid control
+        let submission = Submission { form: first_form(&page), submitter: None };
+        assert_eq!(blocking_control(&page, &submission, &mut state), Some(control_key(element(&page, "short"))));
+        assert!(state.validation_message(element(&page, "site")).is_some());
+    }
+
+    #[test]
+    fn novalidate_skips_validation() {
+        let page = page(
+            "http://example.com/",
+            r#"<form><input name="a" required><button name="save" formnovalidate>Save</button></form>
+               <form novalidate><input name="b" required></form>"#,
+        );
+        let mut state = FormState::default();
+        let forms: Vec<FormRef> = ["a", "b"]
+            .iter()
+            .map(|name| {
+                let form = find_element(&page.body, &|form| form.name == "form" && find_element(&form.children, &|control| get_attribute(control, "name", "") == *name).is_some());
+                FormRef::Key(control_key(form.unwrap()))
+            })
+            .collect();
+
+        let submission = Submission { form: forms[0].clone(), submitter: None };
+        assert!(blocking_control(&page, &submission, &mut state).is_some());
+        let submitter = Some(control_key(element(&page, "save")));
+        assert_eq!(blocking_control(&page, &Submission { submitter, ..submission }, &mut state), None);
+        let submission = Submission { form: forms[1].clone(), submitter: None };
+        assert_eq!(blocking_control(&page, &submission, &mut state), None);
+    }
+
+    #[test]
+    fn posts_to_a_server() {
+        let server = TestServer::start(|_| TestResponse::html("<p>Thanks</p>"));
+        let html = r#"<form id="f" method="post" action="/submit"><textarea name="comment">Hi</textarea></form>
+            <input name="extra" value="outside" form="f">
+            <button form="f" formenctype="multipart/form-data" name="send">Send</button>"#;
+        let page = page(&format!("{}/form.html", server.url), html);
+        let mut state = FormState::default();
+        state.set_text(element(&page, "comment"), "two\nlines".to_string());
+        let client = HttpClient::new("test", CookieJar::default().shared());
+
+        // Enter in a field: urlencoded, with controls associated by id
+        let submission = Submission { form: FormRef::Id("f".to_string()), submitter: None };
+        let request = submission_request(&page, &submission, &state).unwrap();
+        let response = fetch_request(&request, &client).unwrap();
+        assert_eq!(String::from_utf8_lossy(&response.bytes), "<p>Thanks</p>");
+
+        // The button overrides the encoding
+        let submitter = Some(control_key(element(&page, "send")));
+        let request = submission_request(&page, &Submission { submitter, ..submission }, &state).unwrap();
+        fetch_request(&request, &client).unwrap();
+
+        let requests = server.requests();
+        assert_eq!((requests[0].method.as_str(), requests[0].target.as_str()), ("POST", "/submit"));
+        assert_eq!(requests[0].header("content-type"), Some("application/x-www-form-urlencoded"));
+        assert_eq!(requests[0].body, b"comment=two%0D%0Alines&extra=outside");
+        assert!(requests[1].header("content-type").unwrap().starts_with("multipart/form-data; boundary="));
+        let body = String::from_utf8(requests[1].body.clone()).unwrap();
+        assert!(body.contains("name=\"send\"\r\n\r\n\r\n"));
+        assert!(body.contains("name=\"comment\"\r\n\r\ntwo\r\nlines\r\n"));
+    }
+}
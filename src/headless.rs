@@ -0,0 +1,480 @@
+// src/headless.rs
+// Render a page to a PNG without opening a window: egui runs on an offscreen
+// Context, its output is tessellated as usual and the meshes are rasterized on
+// the CPU, so this works on machines without a display or GPU.
+use crate::app::LinkHandler;
+use crate::css::parse_stylesheet;
+use crate::document::{escape_html, preprocess_html, HtmlPage};
+use crate::encoding::decode_body;
+use crate::cookies::CookieJar;
+use crate::fetch::{fetch_page, HttpClient, ResponseInfo};
+use crate::html_renderer::HtmlRenderer;
+use crate::mime::{classify, ContentKind};
+use crate::style::create_default_styles;
+use crate::url_resolver::{decode_data_url, file_url_from_path, file_url_to_path, is_fetchable, resolve_url};
+use egui::epaint::{ClippedPrimitive, ImageData, Primitive};
+use egui::{Color32, ColorImage, Context, FullOutput, Pos2, Rect, TextureId, Vec2};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const DEFAULT_WIDTH: f32 = 900.0;
+// Pages taller than this are cut off
+const MAX_HEIGHT: f32 = 16_000.0;
+// Largest --width or --height accepted, which bounds the image buffer
+const MAX_DIMENSION: f32 = 16_384.0;
+
+// Options for `--screenshot <input> <output.png> [--width N] [--height N]`
+#[derive(Debug, PartialEq)]
+pub struct ScreenshotArgs {
+    // File path or http(s) URL
+    pub input: String,
+    pub output: PathBuf,
+    pub width: f32,
+    // Fit the page height when not given
+    pub height: Option<f32>,
+}
+
+impl ScreenshotArgs {
+    // Parse the arguments after the program name; None when not in screenshot mode
+    pub fn parse(args: &[String]) -> Option<Result<Self, String>> {
+        let position = args.iter().position(|arg| arg == "--screenshot")?;
+        Some(Self::parse_screenshot(args, position))
+    }
+
+    fn parse_screenshot(args: &[String], position: usize) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut width = DEFAULT_WIDTH;
+        let mut height = None;
+
+        let mut iter = args.iter().enumerate().filter(|(i, _)| *i != position).map(|(_, arg)| arg);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--width" => width = parse_dimension("--width", iter.next())?,
+                "--height" => height = Some(parse_dimension("--height", iter.next())?),
+                _ => positional.push(arg.clone()),
+            }
+        }
+
+        match positional.as_slice() {
+            [input, output] => Ok(Self {
+                input: input.clone(),
+                output: PathBuf::from(output),
+                width,
+                height,
+            }),
+            _ => Err("usage: egui_browser --screenshot <file-or-url> <output.png> [--width N] [--height N]".to_string()),
+        }
+    }
+}
+
+fn parse_dimension(flag: &str, value: Option<&String>) -> Result<f32, String> {
+    match value.map(|v| v.parse::<f32>()) {
+        Some(Ok(v)) if (1.0..=MAX_DIMENSION).contains(&v) => Ok(v),
+        _ => Err(format!("{} expects a number from 1 to {}", flag, MAX_DIMENSION)),
+    }
+}
+
+pub fn run_screenshot(args: &ScreenshotArgs) -> Result<(), String> {
+    // Cookies last only as long as the screenshot run
+    let client = HttpClient::new("egui_browser (headless)", CookieJar::default().shared());
+    let (url, response) = load_input(&args.input, &client)?;
+    let info = ResponseInfo::from_response(&response);
+    let decoded = decode_body(&response.bytes, info.content_type());
+
+    // Plain text is wrapped so it renders like the text viewer would
+    let source = match classify(info.content_type(), &response.bytes) {
+        ContentKind::Html => preprocess_html(&decoded.text),
+        ContentKind::PlainText => format!("<html><body><pre>{}</pre></body></html>", escape_html(&decoded.text)),
+        ContentKind::Image | ContentKind::Unsupported(_) => {
+            return Err(format!("{} is not an HTML or text document", args.input))
+        }
+    };
+
+    let mut html_renderer = HtmlRenderer::new(create_default_styles(), LinkHandler::new());
+    let mut page = HtmlPage::new(&url, source, &html_renderer);
+    if let Some(err) = &page.parse_error {
+        return Err(err.clone());
+    }
+    load_stylesheets(&mut page, &client);
+    html_renderer.set_page(&page);
+
+    let ctx = Context::default();
+    load_images(&ctx, &mut html_renderer, &page, Some(&client));
+    let mut rasterizer = Rasterizer::default();
+    let (output, size) = render_page(&ctx, &mut rasterizer, &html_renderer, &page, args.width, args.height);
+    let primitives = ctx.tessellate(output.shapes, output.pixels_per_point);
+    let image = rasterizer.paint(&primitives, size);
+
+    image.save(&args.output).map_err(|err| format!("Failed to write {}: {}", args.output.display(), err))
+}
+
+// Read a local file or fetch a URL into a response
+fn load_input(input: &str, client: &HttpClient) -> Result<(String, ehttp::Response), String> {
+    if is_fetchable(input) {
+        let response = fetch_page(input, client)?;
+        return Ok((response.url.clone(), response));
+    }
+
+    let path = std::fs::canonicalize(input).map_err(|err| format!("Failed to open {}: {}", input, err))?;
+    let bytes = std::fs::read(&path).map_err(|err| format!("Failed to read {}: {}", input, err))?;
+    let url = file_url_from_path(&path);
+
+    // Local files have no headers; the extension stands in for Content-Type
+    let mut headers = std::collections::BTreeMap::new();
+    let content_type = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("html") | Some("htm") => Some("text/html"),
+        Some("txt") => Some("text/plain"),
+        _ => None,
+    };
+    if let Some(content_type) = content_type {
+        headers.insert("content-type".to_string(), content_type.to_string());
+    }
+
+    Ok((
+        url.clone(),
+        ehttp::Response {
+            url,
+            ok: true,
+            status: 200,
+            status_text: "OK".to_string(),
+            headers,
+            bytes,
+        },
+    ))
+}
+
+// Linked stylesheets are loaded before rendering; ones that fail are skipped
+fn load_stylesheets(page: &mut HtmlPage, client: &HttpClient) {
+    for url in page.pending_stylesheets() {
+        let css = if is_fetchable(&url) {
+            fetch_page(&url, client)
+                .ok()
+                .filter(|response| response.ok)
+                .map(|response| {
+                    let info = ResponseInfo::from_response(&response);
+                    decode_body(&response.bytes, info.content_type()).text
+                })
+        } else {
+            file_url_to_path(&url).and_then(|path| std::fs::read_to_string(path).ok())
+        };
+        if let Some(css) = css {
+            page.set_linked_stylesheet(&url, parse_stylesheet(&css));
+        }
+    }
+}
+
+// Images are loaded before rendering too, as there's no browser to fetch them
+// while drawing: local files, data: URLs and, given a client, http(s) ones.
+// Ones that fail keep their placeholder.
+pub fn load_images(ctx: &Context, html_renderer: &mut HtmlRenderer, page: &HtmlPage, client: Option<&HttpClient>) {
+    let mut sources = Vec::new();
+    collect_image_sources(&page.body, &mut sources);
+    for src in sources {
+        let url = resolve_url(&page.base_url, &src);
+        if html_renderer.images.contains_key(&url) {
+            continue;
+        }
+        let bytes = if let Some(bytes) = decode_data_url(&url) {
+            Some(bytes)
+        } else if let Some(path) = file_url_to_path(&url) {
+            std::fs::read(path).ok()
+        } else if is_fetchable(&url) {
+            client.and_then(|client| fetch_page(&url, client).ok()).filter(|response| response.ok).map(|response| response.bytes)
+        } else {
+            None
+        };
+        let Some(image) = bytes.and_then(|bytes| image::load_from_memory(&bytes).ok()) else {
+            continue;
+        };
+        let image = image.to_rgba8();
+        let size = [image.width() as usize, image.height() as usize];
+        let texture = ctx.load_texture(&url, ColorImage::from_rgba_unmultiplied(size, &image.into_raw()), Default::default());
+        html_renderer.images.insert(url, (texture, Vec2::new(size[0] as f32, size[1] as f32)));
+    }
+}
+
+fn collect_image_sources(nodes: &[html_parser::Node], sources: &mut Vec<String>) {
+    for node in nodes {
+        if let html_parser::Node::Element(element) = node {
+            if element.name.eq_ignore_ascii_case("img") {
+                if let Some(Some(src)) = element.attributes.get("src") {
+                    sources.push(src.trim().to_string());
+                }
+            }
+            collect_image_sources(&element.children, sources);
+        }
+    }
+}
+
+// Run one egui frame of the given size, returning its output and the height the
+// page content actually used
+pub fn run_frame(
+    ctx: &Context,
+    rasterizer: &mut Rasterizer,
+    size: Vec2,
+    add_contents: impl FnOnce(&mut egui::Ui),
+) -> (FullOutput, f32) {
+    let raw_input = egui::RawInput {
+        screen_rect: Some(Rect::from_min_size(Pos2::ZERO, size)),
+        max_texture_side: Some(8192),
+        ..Default::default()
+    };
+
+    let mut content_height = 0.0;
+    let output = ctx.run(raw_input, |ctx| {
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none())
+            .show(ctx, |ui| {
+                // Same white page frame the browser window uses
+                let html_frame = egui::Frame::default()
+                    .fill(Color32::from_rgb(255, 255, 255))
+                    .inner_margin(egui::style::Margin::same(10.0));
+                let response = html_frame.show(ui, |ui| {
+                    ui.set_min_size(ui.available_size());
+                    add_contents(ui);
+                    ui.min_rect().height()
+                });
+                content_height = response.inner + 20.0;
+            });
+    });
+
+    rasterizer.update_textures(&output.textures_delta);
+    (output, content_height)
+}
+
+// Lay out a page at the given width. Without an explicit height, a first pass
+// measures the content and a second pass renders at exactly that height.
+pub fn render_page(
+    ctx: &Context,
+    rasterizer: &mut Rasterizer,
+    html_renderer: &HtmlRenderer,
+    page: &HtmlPage,
+    width: f32,
+    height: Option<f32>,
+) -> (FullOutput, Vec2) {
+    let height = match height {
+        Some(height) => height,
+        None => {
+            // The first frame only loads fonts, so measure on the second
+            let measure_size = Vec2::new(width, 1.0);
+            run_frame(ctx, rasterizer, measure_size, |ui| html_renderer.render_document(ui, page));
+            let (_, content_height) =
+                run_frame(ctx, rasterizer, measure_size, |ui| html_renderer.render_document(ui, page));
+            content_height.clamp(1.0, MAX_HEIGHT).ceil()
+        }
+    };
+
+    let size = Vec2::new(width, height);
+    let (output, _) = run_frame(ctx, rasterizer, size, |ui| html_renderer.render_document(ui, page));
+    (output, size)
+}
+
+// An RGBA texture with premultiplied alpha
+struct Texture {
+    size: [usize; 2],
+    pixels: Vec<Color32>,
+}
+
+impl Texture {
+    fn sample(&self, uv: Pos2) -> Color32 {
+        // Nearest-neighbour: glyphs are rasterized at their final pixel size
+        let x = ((uv.x * self.size[0] as f32) as usize).min(self.size[0].saturating_sub(1));
+        let y = ((uv.y * self.size[1] as f32) as usize).min(self.size[1].saturating_sub(1));
+        self.pixels.get(y * self.size[0] + x).copied().unwrap_or(Color32::TRANSPARENT)
+    }
+}
+
+// CPU rasterizer for tessellated egui meshes
+#[derive(Default)]
+pub struct Rasterizer {
+    textures: HashMap<TextureId, Texture>,
+}
+
+impl Rasterizer {
+    pub fn update_textures(&mut self, delta: &egui::TexturesDelta) {
+        for (id, image_delta) in &delta.set {
+            let (size, pixels): ([usize; 2], Vec<Color32>) = match &image_delta.image {
+                ImageData::Color(image) => (image.size, image.pixels.clone()),
+                ImageData::Font(image) => (image.size, image.srgba_pixels(None).collect()),
+            };
+
+            match image_delta.pos {
+                None => {
+                    self.textures.insert(*id, Texture { size, pixels });
+                }
+                Some([x0, y0]) => {
+                    // Partial update of an existing texture (the font atlas grows this way)
+                    if let Some(texture) = self.textures.get_mut(id) {
+                        for y in 0..size[1] {
+                            for x in 0..size[0] {
+                                let (tx, ty) = (x0 + x, y0 + y);
+                                if tx < texture.size[0] && ty < texture.size[1] {
+                                    texture.pixels[ty * texture.size[0] + tx] = pixels[y * size[0] + x];
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        for id in &delta.free {
+            self.textures.remove(id);
+        }
+    }
+
+    pub fn paint(&self, primitives: &[ClippedPrimitive], size: Vec2) -> image::RgbaImage {
+        let (width, height) = (size.x as usize, size.y as usize);
+        let mut pixels = vec![Color32::BLACK; width * height];
+
+        for primitive in primitives {
+            let Primitive::Mesh(mesh) = &primitive.primitive else {
+                continue;
+            };
+            let texture = self.textures.get(&mesh.texture_id);
+            let clip = primitive.clip_rect.intersect(Rect::from_min_size(Pos2::ZERO, size));
+
+            for triangle in mesh.indices.chunks_exact(3) {
+                let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| &mesh.vertices[i as usize]);
+                let area = edge(a.pos, b.pos, c.pos);
+                if area.abs() < f32::EPSILON {
+                    continue;
+                }
+
+                let bounds = Rect::from_points(&[a.pos, b.pos, c.pos]).intersect(clip);
+                if bounds.is_negative() {
+                    continue;
+                }
+
+                for y in bounds.min.y.floor() as usize..(bounds.max.y.ceil() as usize).min(height) {
+                    for x in bounds.min.x.floor() as usize..(bounds.max.x.ceil() as usize).min(width) {
+                        let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                        if !clip.contains(p) {
+                            continue;
+                        }
+
+                        // Barycentric weights, normalised so the triangle's winding doesn't matter
+                        let wa = edge(b.pos, c.pos, p) / area;
+                        let wb = edge(c.pos, a.pos, p) / area;
+                        let wc = edge(a.pos, b.pos, p) / area;
+                        if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                            continue;
+                        }
+
+                        let color = interpolate_color([a.color, b.color, c.color], [wa, wb, wc]);
+                        let color = match texture {
+                            Some(texture) => {
+                                let uv = Pos2::new(
+                                    a.uv.x * wa + b.uv.x * wb + c.uv.x * wc,
+                                    a.uv.y * wa + b.uv.y * wb + c.uv.y * wc,
+                                );
+                                multiply(color, texture.sample(uv))
+                            }
+                            None => color,
+                        };
+
+                        let dst = &mut pixels[y * width + x];
+                        *dst = blend(*dst, color);
+                    }
+                }
+            }
+        }
+
+        let mut image = image::RgbaImage::new(width as u32, height as u32);
+        for (pixel, color) in image.pixels_mut().zip(pixels) {
+            *pixel = image::Rgba([color.r(), color.g(), color.b(), 255]);
+        }
+        image
+    }
+}
+
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+fn interpolate_color(colors: [Color32; 3], weights: [f32; 3]) -> Color32 {
+    let channel = |i: usize| {
+        let value: f32 = colors.iter().zip(weights).map(|(c, w)| c.to_array()[i] as f32 * w).sum();
+        value.round().clamp(0.0, 255.0) as u8
+    };
+    Color32::from_rgba_premultiplied(channel(0), channel(1), channel(2), channel(3))
+}
+
+fn multiply(a: Color32, b: Color32) -> Color32 {
+    let channel = |x: u8, y: u8| ((x as u16 * y as u16 + 127) / 255) as u8;
+    Color32::from_rgba_premultiplied(
+        channel(a.r(), b.r()),
+        channel(a.g(), b.g()),
+        channel(a.b(), b.b()),
+        channel(a.a(), b.a()),
+    )
+}
+
+// Premultiplied "source over" blending, as egui's renderers do
+fn blend(dst: Color32, src: Color32) -> Color32 {
+    let inverse = 255 - src.a() as u16;
+    let channel = |d: u8, s: u8| (s as u16 + (d as u16 * inverse + 127) / 255).min(255) as u8;
+    Color32::from_rgba_premultiplied(
+        channel(dst.r(), src.r()),
+        channel(dst.g(), src.g()),
+        channel(dst.b(), src.b()),
+        channel(dst.a(), src.a()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_screenshot_arguments() {
+        let parsed = ScreenshotArgs::parse(&args(&["--screenshot", "in.html", "out.png", "--width", "640"]));
+        assert_eq!(
+            parsed,
+            Some(Ok(ScreenshotArgs {
+                input: "in.html".to_string(),
+                output: PathBuf::from("out.png"),
+                width: 640.0,
+                height: None,
+            }))
+        );
+        assert_eq!(ScreenshotArgs::parse(&args(&[])), None);
+        assert!(matches!(ScreenshotArgs::parse(&args(&["--screenshot", "in.html"])), Some(Err(_))));
+        assert!(matches!(
+            ScreenshotArgs::parse(&args(&["--screenshot", "a", "b", "--width", "wide"])),
+            Some(Err(_))
+        ));
+        for value in ["inf", "NaN", "0", "100000"] {
+            assert!(matches!(
+                ScreenshotArgs::parse(&args(&["--screenshot", "a", "b", "--height", value])),
+                Some(Err(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn renders_page_without_a_window() {
+        let html_renderer = HtmlRenderer::new(create_default_styles(), LinkHandler::new());
+        let page = HtmlPage::new(
+            "file:///test.html",
+            "<html><body><h1>Heading</h1><p>Some text</p></body></html>".to_string(),
+            &html_renderer,
+        );
+
+        let ctx = Context::default();
+        let mut rasterizer = Rasterizer::default();
+        let (output, size) = render_page(&ctx, &mut rasterizer, &html_renderer, &page, 300.0, None);
+        let primitives = ctx.tessellate(output.shapes, output.pixels_per_point);
+        let image = rasterizer.paint(&primitives, size);
+
+        assert_eq!(image.width(), 300);
+        assert!(image.height() > 40);
+        // White page background with some darker text pixels on it
+        assert_eq!(image.get_pixel(5, 5).0, [255, 255, 255, 255]);
+        assert!(image.pixels().any(|p| p.0[0] < 200));
+    }
+}
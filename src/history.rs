@@ -0,0 +1,232 @@
+// src/history.rs
+// The global history: every page visited in any tab, kept on disk between
+// runs and shown on about:history. Each tab's back/forward list is separate
+// (see NavigationHistory in tab.rs) and lasts only as long as the tab.
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+const HEADER: &str = "# egui_browser history: url, title, visit times\n";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub url: String,
+    pub title: Option<String>,
+    // Unix times of every visit, oldest first
+    pub visits: Vec<i64>,
+}
+
+impl HistoryEntry {
+    pub fn last_visit(&self) -> i64 {
+        self.visits.last().copied().unwrap_or(0)
+    }
+
+    pub fn visit_count(&self) -> usize {
+        self.visits.len()
+    }
+
+    // Case-insensitive match on the URL or title
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.url.to_lowercase().contains(&query)
+            || self.title.as_ref().is_some_and(|title| title.to_lowercase().contains(&query))
+    }
+}
+
+#[derive(Default)]
+pub struct GlobalHistory {
+    entries: Vec<HistoryEntry>,
+    // Where history is saved; None keeps it in memory only
+    file: Option<PathBuf>,
+}
+
+impl GlobalHistory {
+    // History kept in `file`, starting with what's saved there. Visits are
+    // appended to the file as lines of their own, which are merged here.
+    pub fn load(file: PathBuf) -> Self {
+        let mut history = Self { entries: Vec::new(), file: Some(file) };
+        let text = history.file.as_ref().and_then(|file| std::fs::read_to_string(file).ok()).unwrap_or_default();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut lines = 0;
+        for saved in text.lines().filter_map(parse_saved_entry) {
+            lines += 1;
+            match index.get(&saved.url) {
+                Some(&position) => {
+                    let entry = &mut history.entries[position];
+                    entry.visits.extend(saved.visits);
+                    if saved.title.is_some() {
+                        entry.title = saved.title;
+                    }
+                }
+                None => {
+                    index.insert(saved.url.clone(), history.entries.len());
+                    history.entries.push(saved);
+                }
+            }
+        }
+        for entry in &mut history.entries {
+            entry.visits.sort_unstable();
+        }
+        // Fold appended visits back into one line per page once they pile up
+        if lines > 2 * history.entries.len() + 100 {
+            history.save();
+        }
+        history
+    }
+
+    // Rewrite the whole file, one line per page
+    fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let mut text = String::from(HEADER);
+        for entry in &self.entries {
+            let visits: Vec<String> = entry.visits.iter().map(|visit| visit.to_string()).collect();
+            text.push_str(&format!("{}\t{}\t{}\n", entry.url, one_line(entry.title.as_deref().unwrap_or_default()), visits.join(",")));
+        }
+        // Write a temporary file and rename it, so a crash can't lose history
+        let temporary = file.with_extension("tmp");
+        if std::fs::write(&temporary, text).is_ok() {
+            let _ = std::fs::rename(&temporary, file);
+        }
+    }
+
+    // Add a line for one visit to the end of the file
+    fn append(&self, url: &str, title: Option<&str>, now: i64) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let new_file = !file.exists();
+        let Ok(mut out) = std::fs::OpenOptions::new().create(true).append(true).open(file) else {
+            return;
+        };
+        let mut line = String::new();
+        if new_file {
+            line.push_str(HEADER);
+        }
+        line.push_str(&format!("{}\t{}\t{}\n", url, one_line(title.unwrap_or_default()), now));
+        let _ = out.write_all(line.as_bytes());
+    }
+
+    // Record a visit to a loaded page
+    pub fn record_visit(&mut self, url: &str, title: Option<&str>, now: i64) {
+        let title = title.map(str::trim).filter(|title| !title.is_empty()).map(str::to_string);
+        self.append(url, title.as_deref(), now);
+        match self.entries.iter_mut().find(|entry| entry.url == url) {
+            Some(entry) => {
+                entry.visits.push(now);
+                if title.is_some() {
+                    entry.title = title;
+                }
+            }
+            None => self.entries.push(HistoryEntry {
+                url: url.to_string(),
+                title,
+                visits: vec![now],
+            }),
+        }
+    }
+
+    // Entries matching the query, most recently visited first
+    pub fn search(&self, query: &str) -> Vec<&HistoryEntry> {
+        let query = query.trim();
+        let mut entries: Vec<&HistoryEntry> = self.entries.iter().filter(|entry| query.is_empty() || entry.matches(query)).collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_visit()));
+        entries
+    }
+
+    pub fn remove(&mut self, url: &str) {
+        self.entries.retain(|entry| entry.url != url);
+        self.save();
+    }
+
+    // Forget visits from `from` up to but not including `to`; pages with no
+    // visits left go entirely
+    pub fn remove_range(&mut self, from: i64, to: i64) {
+        for entry in &mut self.entries {
+            entry.visits.retain(|visit| !(from..to).contains(visit));
+        }
+        self.entries.retain(|entry| !entry.visits.is_empty());
+        self.save();
+    }
+}
+
+// Titles are saved on one tab-separated line
+fn one_line(text: &str) -> String {
+    text.replace(['\t', '\n', '\r'], " ")
+}
+
+// A line of the saved history
+fn parse_saved_entry(line: &str) -> Option<HistoryEntry> {
+    if line.starts_with('#') {
+        return None;
+    }
+    let mut fields = line.splitn(3, '\t');
+    let (url, title, visits) = (fields.next()?, fields.next()?, fields.next()?);
+    let visits: Vec<i64> = visits.split(',').filter_map(|visit| visit.parse().ok()).collect();
+    (!url.is_empty() && !visits.is_empty()).then(|| HistoryEntry {
+        url: url.to_string(),
+        title: (!title.is_empty()).then(|| title.to_string()),
+        visits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_and_searches_visits() {
+        let mut history = GlobalHistory::default();
+        history.record_visit("http://a.test/", Some("Rust Home"), 100);
+        history.record_visit("http://b.test/rust", None, 200);
+        history.record_visit("http://c.test/", Some("Other"), 300);
+        history.record_visit("http://a.test/", None, 400);
+
+        let urls = |entries: Vec<&HistoryEntry>| entries.iter().map(|entry| entry.url.clone()).collect::<Vec<_>>();
+        assert_eq!(urls(history.search("")), ["http://a.test/", "http://c.test/", "http://b.test/rust"]);
+        assert_eq!(urls(history.search("RUST")), ["http://a.test/", "http://b.test/rust"]);
+        let entry = history.search("a.test")[0];
+        assert_eq!((entry.title.as_deref(), entry.visit_count(), entry.last_visit()), (Some("Rust Home"), 2, 400));
+    }
+
+    #[test]
+    fn appends_visits_to_the_file() {
+        let file = std::env::temp_dir().join(format!("history_append_{}.txt", std::process::id()));
+        let mut history = GlobalHistory::load(file.clone());
+        history.record_visit("http://a.test/", Some("First"), 100);
+        history.record_visit("http://b.test/", None, 150);
+        history.record_visit("http://a.test/", Some("Renamed"), 200);
+        let text = std::fs::read_to_string(&file).unwrap();
+
+        let loaded = GlobalHistory::load(file.clone());
+        std::fs::remove_file(&file).unwrap();
+        // Each visit is a line of its own, merged again on load
+        assert_eq!(text.lines().count(), 4);
+        assert_eq!(loaded.entries, history.entries);
+        assert_eq!(loaded.entries[0].title.as_deref(), Some("Renamed"));
+    }
+
+    #[test]
+    fn deletes_entries_and_ranges() {
+        let file = std::env::temp_dir().join(format!("history_{}.txt", std::process::id()));
+        let mut history = GlobalHistory::load(file.clone());
+        history.record_visit("http://a.test/", Some("A\ttabbed"), 100);
+        history.record_visit("http://b.test/", None, 150);
+        history.record_visit("http://a.test/", None, 250);
+        history.record_visit("http://c.test/", None, 300);
+        history.remove("http://c.test/");
+        history.remove_range(100, 200);
+
+        let loaded = GlobalHistory::load(file.clone());
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(
+            loaded.entries,
+            [HistoryEntry {
+                url: "http://a.test/".to_string(),
+                title: Some("A tabbed".to_string()),
+                visits: vec![250],
+            }]
+        );
+    }
+}
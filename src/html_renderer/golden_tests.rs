@@ -0,0 +1,262 @@
+// src/html_renderer/golden_tests.rs
+// Golden-file tests for HtmlRenderer. Each fixture in tests/render/*.html is laid
+// out in a headless egui Context and the painted shapes are written out as text
+// and compared with the matching .golden file. data: and file images are decoded
+// first; remote ones are never fetched and stay placeholders.
+//
+// After an intended rendering change, regenerate the golden files with:
+//     UPDATE_GOLDEN=1 cargo test golden
+use crate::app::LinkHandler;
+use crate::css::parse_stylesheet;
+use crate::document::HtmlPage;
+use crate::headless::{load_images, render_page, Rasterizer};
+use crate::html_renderer::HtmlRenderer;
+use crate::style::create_default_styles;
+use egui::epaint::{ClippedShape, Shape, TextShape};
+use egui::{Color32, Context, Pos2, Rect, Stroke, TextureId};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+const PAGE_WIDTH: f32 = 600.0;
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("render")
+}
+
+// Render a document and describe everything it painted
+fn render_to_text(html: &str) -> String {
+    let mut html_renderer = HtmlRenderer::new(create_default_styles(), LinkHandler::new());
+    let page = HtmlPage::new("http://golden.test/page.html", html.to_string(), &html_renderer);
+    html_renderer.set_page(&page);
+    let ctx = Context::default();
+    // data: images show up; http ones aren't fetched and keep their placeholder
+    load_images(&ctx, &mut html_renderer, &page, None);
+    describe_render(&ctx, &html_renderer, &page)
+}
+
+fn describe_render(ctx: &Context, html_renderer: &HtmlRenderer, page: &HtmlPage) -> String {
+    let mut rasterizer = Rasterizer::default();
+    let (output, size) = render_page(ctx, &mut rasterizer, html_renderer, page, PAGE_WIDTH, None);
+
+    let mut text = format!("page {}\n", format_vec(size.x, size.y));
+    for ClippedShape { shape, .. } in &output.shapes {
+        write_shape(&mut text, shape);
+    }
+    text
+}
+
+fn write_shape(out: &mut String, shape: &Shape) {
+    match shape {
+        Shape::Noop => {}
+        Shape::Vec(shapes) => {
+            for shape in shapes {
+                write_shape(out, shape);
+            }
+        }
+        Shape::Text(text_shape) => write_text(out, text_shape),
+        Shape::Rect(rect) => {
+            // Images are rects filled with their texture
+            let textured = if rect.fill_texture_id == TextureId::default() { "" } else { " textured" };
+            let _ = writeln!(
+                out,
+                "rect {} fill {} stroke {}{}",
+                format_rect(rect.rect),
+                format_color(rect.fill),
+                format_stroke(rect.stroke),
+                textured
+            );
+        }
+        Shape::LineSegment { points, stroke } => {
+            let _ = writeln!(
+                out,
+                "line {} -> {} stroke {}",
+                format_pos(points[0]),
+                format_pos(points[1]),
+                format_stroke(*stroke)
+            );
+        }
+        Shape::Circle(circle) => {
+            let _ = writeln!(
+                out,
+                "circle {} r {:.1} fill {}",
+                format_pos(circle.center),
+                circle.radius,
+                format_color(circle.fill)
+            );
+        }
+        Shape::Path(path) => {
+            let _ = writeln!(
+                out,
+                "path {} points fill {} stroke {}",
+                path.points.len(),
+                format_color(path.fill),
+                format_stroke(path.stroke)
+            );
+        }
+        // Images and other textured meshes: texture ids aren't stable, so only geometry
+        Shape::Mesh(mesh) => {
+            let _ = writeln!(out, "mesh {}", format_rect(mesh.calc_bounds()));
+        }
+        Shape::QuadraticBezier(_) | Shape::CubicBezier(_) => {
+            let _ = writeln!(out, "bezier");
+        }
+        Shape::Callback(_) => {
+            let _ = writeln!(out, "callback");
+        }
+    }
+}
+
+fn write_text(out: &mut String, text_shape: &TextShape) {
+    let galley = &text_shape.galley;
+    let _ = writeln!(
+        out,
+        "text {} {}",
+        format_pos(text_shape.pos),
+        format_vec(galley.rect.width(), galley.rect.height())
+    );
+
+    // Styled runs as requested by the layout job
+    for section in &galley.job.sections {
+        let format = &section.format;
+        let mut flags = String::new();
+        if format.font_id.family == egui::FontFamily::Monospace {
+            flags.push_str(" monospace");
+        }
+        if format.valign == egui::Align::TOP {
+            flags.push_str(" raised");
+        }
+        if format.italics {
+            flags.push_str(" italic");
+        }
+        if format.underline != Stroke::NONE {
+            flags.push_str(" underline");
+        }
+        if format.strikethrough != Stroke::NONE {
+            flags.push_str(" strikethrough");
+        }
+        if format.background != Color32::TRANSPARENT {
+            flags.push_str(&format!(" background {}", format_color(format.background)));
+        }
+        let color = text_shape.override_text_color.unwrap_or(format.color);
+        let _ = writeln!(
+            out,
+            "  run {:?} {:.1}px {}{}",
+            &galley.job.text[section.byte_range.clone()],
+            format.font_id.size,
+            format_color(color),
+            flags
+        );
+    }
+
+    // Where wrapping put each line
+    for row in &galley.rows {
+        let row_text: String = row.glyphs.iter().map(|glyph| glyph.chr).collect();
+        let _ = writeln!(out, "  row {} {:?}", format_pos(row.rect.min), row_text);
+    }
+}
+
+fn format_pos(pos: Pos2) -> String {
+    format!("({:.1}, {:.1})", pos.x, pos.y)
+}
+
+fn format_vec(x: f32, y: f32) -> String {
+    format!("{:.1}x{:.1}", x, y)
+}
+
+fn format_rect(rect: Rect) -> String {
+    format!("{} {}", format_pos(rect.min), format_vec(rect.width(), rect.height()))
+}
+
+fn format_color(color: Color32) -> String {
+    if color.a() == 255 {
+        format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+    } else {
+        let [r, g, b, a] = color.to_array();
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+fn format_stroke(stroke: Stroke) -> String {
+    if stroke.width == 0.0 || stroke.color == Color32::TRANSPARENT {
+        "none".to_string()
+    } else {
+        format!("{:.1} {}", stroke.width, format_color(stroke.color))
+    }
+}
+
+// Styles are cached between frames, but recomputed once a stylesheet arrives
+#[test]
+fn restyles_when_a_linked_stylesheet_arrives() {
+    let mut html_renderer = HtmlRenderer::new(create_default_styles(), LinkHandler::new());
+    let html = r#"<link rel="stylesheet" href="late.css"><p>Late styles</p>"#;
+    let mut page = HtmlPage::new("http://golden.test/page.html", html.to_string(), &html_renderer);
+    html_renderer.set_page(&page);
+    let ctx = Context::default();
+    let before = describe_render(&ctx, &html_renderer, &page);
+    assert_eq!(describe_render(&ctx, &html_renderer, &page), before);
+
+    page.set_linked_stylesheet("http://golden.test/late.css", parse_stylesheet("p { color: #ff0000 }"));
+    html_renderer.set_page(&page);
+    let after = describe_render(&ctx, &html_renderer, &page);
+    assert!(!before.contains("#ff0000") && after.contains("#ff0000"));
+}
+
+#[test]
+fn golden_render_fixtures() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut fixtures: Vec<PathBuf> = std::fs::read_dir(fixtures_dir())
+        .expect("tests/render directory")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no fixtures in tests/render");
+
+    let mut failures = Vec::new();
+    for fixture in &fixtures {
+        let html = std::fs::read_to_string(fixture).unwrap();
+        let actual = render_to_text(&html);
+        let golden_path = fixture.with_extension("golden");
+
+        if update {
+            std::fs::write(&golden_path, &actual).unwrap();
+            continue;
+        }
+
+        match std::fs::read_to_string(&golden_path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{}: rendering differs from golden file\n{}",
+                fixture.display(),
+                first_difference(&expected, &actual)
+            )),
+            Err(_) => failures.push(format!("{}: missing golden file", fixture.display())),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{}\n\nRun `UPDATE_GOLDEN=1 cargo test golden` if the change is intended.",
+        failures.join("\n\n")
+    );
+}
+
+// Show the first line that changed, with its line number
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(e), Some(a)) if e == a => line += 1,
+            (e, a) => {
+                return format!(
+                    "line {}:\n  expected: {}\n  actual:   {}",
+                    line,
+                    e.unwrap_or("<end of file>"),
+                    a.unwrap_or("<end of file>")
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,308 @@
+// src/html_renderer/layout.rs
+// Box model for block-level elements. A block's geometry is worked out from
+// its computed style and the containing block's width before anything inside
+// it is painted: margins, borders, padding and the content width follow the
+// CSS 2.1 rules for blocks in normal flow (section 10.3.3), so auto margins
+// center a block with a set width. The content height is only known once the
+// contents have been laid out, unless the style fixes it.
+use crate::style::{ComputedStyle, Length, Sides};
+use egui::{Pos2, Rect, Vec2};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockBox {
+    pub margin: Sides<f32>,
+    pub border: Sides<f32>,
+    pub padding: Sides<f32>,
+    pub content_width: f32,
+    // Content height set by the style; otherwise the contents decide
+    pub height: Option<f32>,
+}
+
+impl BlockBox {
+    pub fn layout(style: &ComputedStyle, containing_width: f32) -> Self {
+        // Percentages, vertical ones included, refer to the containing block's width
+        let resolve = |length: Length| length.resolve(containing_width).unwrap_or(0.0);
+        let border = Sides {
+            top: style.border.top.width,
+            right: style.border.right.width,
+            bottom: style.border.bottom.width,
+            left: style.border.left.width,
+        };
+        let padding = Sides {
+            top: resolve(style.padding.top),
+            right: resolve(style.padding.right),
+            bottom: resolve(style.padding.bottom),
+            left: resolve(style.padding.left),
+        };
+        let edges = border.left + padding.left + padding.right + border.right;
+
+        let mut width = style.width.resolve(containing_width);
+        let max_width = style.max_width.resolve(containing_width);
+        let margin_left = style.margin.left.resolve(containing_width);
+        let margin_right = style.margin.right.resolve(containing_width);
+        if let (Some(max), Some(current)) = (max_width, width) {
+            width = Some(current.min(max));
+        }
+        // An auto width fills the line; max-width then turns it into a set
+        // width, so auto margins center it
+        let filled = containing_width - edges - margin_left.unwrap_or(0.0) - margin_right.unwrap_or(0.0);
+        let width = match (width, max_width) {
+            (Some(width), _) => Some(width),
+            (None, Some(max)) if filled > max => Some(max),
+            (None, _) => None,
+        };
+
+        let (content_width, margin_left, margin_right) = match width {
+            None => (filled.max(0.0), margin_left.unwrap_or(0.0), margin_right.unwrap_or(0.0)),
+            Some(width) => {
+                let remaining = containing_width - edges - width;
+                let (left, right) = match (margin_left, margin_right) {
+                    // Auto margins share what's left, but never go negative
+                    (None, None) => (remaining.max(0.0) / 2.0, remaining.max(0.0) / 2.0),
+                    (None, Some(right)) => ((remaining - right).max(0.0), right),
+                    // Over-constrained: the right margin gives way
+                    (Some(left), _) => (left, remaining - left),
+                };
+                (width.max(0.0), left, right)
+            }
+        };
+
+        let margin = Sides {
+            top: resolve(style.margin.top),
+            right: margin_right,
+            bottom: resolve(style.margin.bottom),
+            left: margin_left,
+        };
+        let height = match style.height {
+            Length::Px(height) => Some(height),
+            // Percentages of an auto-height containing block behave as auto
+            Length::Percent(_) | Length::Auto => None,
+        };
+        Self { margin, border, padding, content_width, height }
+    }
+
+    // Where the content area starts, relative to the border box
+    pub fn content_offset(&self) -> Vec2 {
+        Vec2::new(self.border.left + self.padding.left, self.border.top + self.padding.top)
+    }
+
+    // The border box placed at `min`, given how tall the contents turned out
+    pub fn border_rect(&self, min: Pos2, content_height: f32) -> Rect {
+        let height = self.height.unwrap_or(content_height);
+        let size = Vec2::new(
+            self.border.left + self.padding.left + self.content_width + self.padding.right + self.border.right,
+            self.border.top + self.padding.top + height + self.padding.bottom + self.border.bottom,
+        );
+        Rect::from_min_size(min, size)
+    }
+}
+
+// Adjoining vertical margins collapse into one: the largest positive margin
+// plus the most negative one
+pub fn collapse_margins(a: f32, b: f32) -> f32 {
+    a.max(b).max(0.0) + a.min(b).min(0.0)
+}
+
+// Narrowest and widest some content can be laid out: its widest unbreakable
+// piece, and all of it on one line
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Intrinsic {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Intrinsic {
+    pub fn fixed(width: f32) -> Self {
+        Self { min: width, max: width }
+    }
+
+    // Side by side on one line
+    pub fn add(&mut self, other: Intrinsic) {
+        self.min = self.min.max(other.min);
+        self.max += other.max;
+    }
+
+    // Stacked, like blocks
+    pub fn stack(&mut self, other: Intrinsic) {
+        self.min = self.min.max(other.min);
+        self.max = self.max.max(other.max);
+    }
+}
+
+// A table cell's intrinsic widths and the columns it covers. A cell with a
+// set width fixes its column to it.
+#[derive(Clone, Copy, Debug)]
+pub struct CellWidths {
+    pub column: usize,
+    pub span: usize,
+    pub widths: Intrinsic,
+    pub fixed: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Column {
+    pub widths: Intrinsic,
+    // Only widened when every column is fixed
+    pub fixed: bool,
+}
+
+// Each column's intrinsic widths. Single-column cells decide first; a cell
+// spanning several columns then widens them by whatever it still lacks,
+// evenly for the minimum and in proportion to the columns' widths for the maximum.
+pub fn table_columns(columns: usize, cells: &[CellWidths]) -> Vec<Column> {
+    let mut fixed = vec![false; columns];
+    let mut result = vec![Intrinsic::default(); columns];
+    for cell in cells.iter().filter(|cell| cell.span == 1) {
+        result[cell.column].stack(cell.widths);
+        fixed[cell.column] |= cell.fixed;
+    }
+
+    let mut spanning: Vec<&CellWidths> = cells.iter().filter(|cell| cell.span > 1).collect();
+    spanning.sort_by_key(|cell| cell.span);
+    for cell in spanning {
+        let spanned = &mut result[cell.column..cell.column + cell.span];
+        let span = spanned.len() as f32;
+        let min: f32 = spanned.iter().map(|column| column.min).sum();
+        if cell.widths.min > min {
+            let extra = (cell.widths.min - min) / span;
+            spanned.iter_mut().for_each(|column| column.min += extra);
+        }
+        let max: f32 = spanned.iter().map(|column| column.max).sum();
+        if cell.widths.max > max {
+            for column in spanned.iter_mut() {
+                let share = if max > 0.0 { column.max / max } else { 1.0 / span };
+                column.max += (cell.widths.max - max) * share;
+            }
+        }
+        spanned.iter_mut().for_each(|column| column.max = column.max.max(column.min));
+    }
+    result.into_iter().zip(fixed).map(|(widths, fixed)| Column { widths, fixed }).collect()
+}
+
+// Column widths for the automatic table layout. A table without a set width
+// uses every column's maximum when that fits in `available`, its minimum when
+// even that doesn't, and otherwise gives each column the same share of its
+// range. A set width is filled exactly, but never below the minimums.
+pub fn column_widths(columns: &[Column], available: f32, set_width: Option<f32>) -> Vec<f32> {
+    let min: f32 = columns.iter().map(|column| column.widths.min).sum();
+    let max: f32 = columns.iter().map(|column| column.widths.max).sum();
+    let target = match set_width {
+        Some(width) => width.max(min),
+        None => available.clamp(min, max.max(min)),
+    };
+
+    if target >= max {
+        // Widening past the maximums goes to the columns without a set width,
+        // in proportion to them
+        let all_fixed = columns.iter().all(|column| column.fixed);
+        let widened = |column: &Column| all_fixed || !column.fixed;
+        let widened_max: f32 = columns.iter().filter(|column| widened(column)).map(|column| column.widths.max).sum();
+        let widened_count = columns.iter().filter(|column| widened(column)).count() as f32;
+        let extra = target - max;
+        return columns
+            .iter()
+            .map(|column| {
+                let share = match (widened(column), widened_max > 0.0) {
+                    (false, _) => 0.0,
+                    (true, true) => column.widths.max / widened_max,
+                    (true, false) => 1.0 / widened_count,
+                };
+                column.widths.max + extra * share
+            })
+            .collect();
+    }
+    let ratio = (target - min) / (max - min);
+    columns
+        .iter()
+        .map(|column| column.widths.min + (column.widths.max - column.widths.min) * ratio)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parse_declarations;
+    use crate::style::cascade;
+
+    fn layout(css: &str, containing_width: f32) -> BlockBox {
+        let style = cascade(&ComputedStyle::default(), None, &[], &parse_declarations(css));
+        BlockBox::layout(&style, containing_width)
+    }
+
+    #[test]
+    fn auto_width_fills_the_containing_block() {
+        let block = layout("margin: 10px; padding: 5px 10%; border: 2px solid", 500.0);
+        assert_eq!(block.content_width, 500.0 - 20.0 - 100.0 - 4.0);
+        assert_eq!(block.padding.top, 5.0);
+        assert_eq!(block.content_offset(), Vec2::new(52.0, 7.0));
+        let rect = block.border_rect(Pos2::new(10.0, 10.0), 30.0);
+        assert_eq!(rect.size(), Vec2::new(480.0, 30.0 + 10.0 + 4.0));
+    }
+
+    #[test]
+    fn auto_margins_center_a_block() {
+        let block = layout("width: 200px; margin: 0 auto", 500.0);
+        assert_eq!((block.margin.left, block.margin.right), (150.0, 150.0));
+        assert_eq!(block.content_width, 200.0);
+
+        // max-width caps an auto width, which then centers the same way
+        let block = layout("max-width: 300px; margin-left: auto; margin-right: auto", 500.0);
+        assert_eq!((block.margin.left, block.content_width), (100.0, 300.0));
+        let block = layout("max-width: 300px; margin: 0 auto", 200.0);
+        assert_eq!((block.margin.left, block.content_width), (0.0, 200.0));
+    }
+
+    #[test]
+    fn over_constrained_widths_give_way_on_the_right() {
+        let block = layout("width: 50%; margin-left: 20px; margin-right: 20px", 400.0);
+        assert_eq!((block.margin.left, block.content_width, block.margin.right), (20.0, 200.0, 180.0));
+        let block = layout("width: 100px; margin-right: 50px; margin-left: auto", 400.0);
+        assert_eq!(block.margin.left, 250.0);
+    }
+
+    #[test]
+    fn set_heights_override_the_contents() {
+        let block = layout("height: 40px; padding: 5px", 100.0);
+        assert_eq!(block.border_rect(Pos2::ZERO, 200.0).height(), 50.0);
+        assert_eq!(layout("height: 50%", 100.0).height, None);
+    }
+
+    #[test]
+    fn collapses_margins() {
+        assert_eq!(collapse_margins(10.0, 4.0), 10.0);
+        assert_eq!(collapse_margins(10.0, -4.0), 6.0);
+        assert_eq!(collapse_margins(-10.0, -4.0), -10.0);
+    }
+
+    fn cell(column: usize, span: usize, min: f32, max: f32) -> CellWidths {
+        CellWidths { column, span, widths: Intrinsic { min, max }, fixed: false }
+    }
+
+    fn column(min: f32, max: f32, fixed: bool) -> Column {
+        Column { widths: Intrinsic { min, max }, fixed }
+    }
+
+    #[test]
+    fn spanning_cells_widen_their_columns() {
+        let columns = table_columns(2, &[cell(0, 1, 10.0, 30.0), cell(1, 1, 10.0, 10.0), cell(0, 2, 40.0, 80.0)]);
+        assert_eq!(columns[0].widths, Intrinsic { min: 20.0, max: 60.0 });
+        assert_eq!(columns[1].widths, Intrinsic { min: 20.0, max: 20.0 });
+    }
+
+    #[test]
+    fn distributes_the_available_width() {
+        let columns = [column(10.0, 100.0, false), column(30.0, 40.0, false)];
+        // Everything fits on one line
+        assert_eq!(column_widths(&columns, 500.0, None), vec![100.0, 40.0]);
+        // Not even the minimums fit
+        assert_eq!(column_widths(&columns, 20.0, None), vec![10.0, 30.0]);
+        // Half way between
+        assert_eq!(column_widths(&columns, 90.0, None), vec![55.0, 35.0]);
+        // A set width is filled
+        assert_eq!(column_widths(&columns, 500.0, Some(280.0)), vec![200.0, 80.0]);
+
+        // Columns with a set width keep it
+        let columns = [column(80.0, 80.0, true), column(30.0, 40.0, false)];
+        assert_eq!(column_widths(&columns, 500.0, Some(300.0)), vec![80.0, 220.0]);
+    }
+}
@@ -1,8 +1,11 @@
 // src/html_renderer/mod.rs
+mod layout;
 mod renderer;
 mod style_handler;
-mod text_processor;
+pub mod text_processor;
 mod renderers;
+#[cfg(test)]
+mod golden_tests;
 // Removing unused module: mod element_renderers;
 
 pub use renderer::HtmlRenderer;
@@ -1,22 +1,60 @@
 // src/html_renderer/renderer.rs
-use crate::style::ElementStyle;
+use crate::style::{cascade, ComputedStyle, ElementStyle, USER_AGENT_CSS};
 use crate::app::{LinkHandler, EguiBrowser};
+use crate::css::{matching_declarations, parse_declarations, parse_stylesheet, Declaration, ElementData, Stylesheet};
+use crate::document::HtmlPage;
+use crate::forms::{control_key, first_legend, is_control, label_target, FormRef, FormState, LabelTarget, Submission};
 use egui::Ui;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
 
 // Import specific render functions from their modules
-use crate::html_renderer::renderers::text::{render_heading, render_paragraph, render_link, render_text_formatting};
-use crate::html_renderer::renderers::lists::render_list;
+use crate::html_renderer::renderers::inline::{is_inline, is_inline_tag, render_inline};
+use crate::html_renderer::renderers::lists::{render_list, render_list_item};
 use crate::html_renderer::renderers::media::render_image;
-use crate::html_renderer::renderers::block::{render_block_element, render_code};
+use crate::html_renderer::layout::collapse_margins;
+use crate::html_renderer::renderers::block::{render_block_box, render_code};
 use crate::html_renderer::renderers::forms::render_form_element;
 use crate::html_renderer::renderers::tables::render_table;
-use super::text_processor::get_text_content;
+use crate::url_resolver::resolve_url;
 
 pub struct HtmlRenderer {
     pub style_map: HashMap<String, ElementStyle>,
     pub link_handler: LinkHandler,
     pub browser: Option<*const EguiBrowser>,
+    // Base URL of the current document, used to resolve href/src attributes
+    pub base_url: String,
+    // Images loaded before rendering, by resolved URL, for when there's no
+    // browser to fetch them while drawing (headless rendering)
+    pub images: HashMap<String, (egui::TextureHandle, egui::Vec2)>,
+    // Author stylesheets of the current document, in cascade order
+    pub stylesheets: Vec<Rc<Stylesheet>>,
+    // Form control values of the current document
+    pub form_state: Rc<RefCell<FormState>>,
+    // Forms enclosing the element being rendered; controls belong to the innermost
+    forms: RefCell<Vec<FormRef>>,
+    // Set inside a disabled fieldset, whose controls are all disabled
+    fieldset_disabled: Cell<bool>,
+    // The label wrapping the controls being rendered, if any
+    label: RefCell<Option<LabelTarget>>,
+    // Built-in rules applied under every page's own
+    user_agent_sheet: Vec<Rc<Stylesheet>>,
+    // Elements enclosing the one being rendered, for descendant selectors
+    ancestors: RefCell<Vec<ElementData>>,
+    // Computed styles of those elements; the last one is inherited from
+    styles: RefCell<Vec<ComputedStyle>>,
+    // Keys of those elements, each hashed from its parent's key and the node
+    // itself, so an element has the same key every frame
+    path_keys: RefCell<Vec<u64>>,
+    // Computed styles by node path, kept until the page or its stylesheets change
+    style_cache: RefCell<HashMap<u64, ComputedStyle>>,
+    // Id and stylesheet version of the page the cached styles belong to
+    page_key: Option<(u64, u64)>,
+    // Bottom margin of the last block box, held back so it can collapse with
+    // the next block's top margin
+    pending_margin: Cell<f32>,
 }
 
 impl HtmlRenderer {
@@ -25,21 +63,180 @@ impl HtmlRenderer {
             style_map, 
             link_handler,
             browser: None,
+            base_url: String::new(),
+            images: HashMap::new(),
+            stylesheets: Vec::new(),
+            form_state: Rc::default(),
+            forms: RefCell::new(Vec::new()),
+            fieldset_disabled: Cell::new(false),
+            label: RefCell::new(None),
+            user_agent_sheet: vec![Rc::new(parse_stylesheet(USER_AGENT_CSS))],
+            ancestors: RefCell::new(Vec::new()),
+            styles: RefCell::new(Vec::new()),
+            path_keys: RefCell::new(Vec::new()),
+            style_cache: RefCell::new(HashMap::new()),
+            page_key: None,
+            pending_margin: Cell::new(0.0),
         }
     }
     
-    pub fn set_browser(&mut self, browser: &EguiBrowser) {
-        self.browser = Some(browser as *const EguiBrowser);
-    }
-    
     pub fn get_browser(&self) -> Option<&EguiBrowser> {
         unsafe {
             self.browser.map(|ptr| &*ptr)
         }
     }
     
+    // Render `page` from now on: its base URL, stylesheets and form state.
+    // Cached styles are dropped when the page or its stylesheets change.
+    pub fn set_page(&mut self, page: &HtmlPage) {
+        let key = (page.id, page.stylesheet_version);
+        if self.page_key == Some(key) {
+            return;
+        }
+        self.page_key = Some(key);
+        self.base_url = page.base_url.clone();
+        self.stylesheets = page.loaded_stylesheets();
+        self.form_state = page.form_state.clone();
+        self.style_cache.borrow_mut().clear();
+    }
+    
+    // Resolve an attribute value against the current document's base URL
+    pub fn resolve_url(&self, reference: &str) -> String {
+        resolve_url(&self.base_url, reference)
+    }
+    
+    // Computed style of the innermost element being rendered
+    pub fn current_style(&self) -> ComputedStyle {
+        self.styles.borrow().last().cloned().unwrap_or_default()
+    }
+    
+    // Cascade the user-agent style, presentational attributes, matching author
+    // rules and the style attribute over what the element inherits from its parent
+    fn compute_style(&self, element: &html_parser::Element) -> ComputedStyle {
+        let parent = self.current_style();
+        let ancestors = self.ancestors.borrow();
+        let data = ElementData::from_element(element);
+        let mut user_agent = self.style_map.get(&element.name.to_lowercase()).cloned().unwrap_or_default();
+        for declaration in matching_declarations(&self.user_agent_sheet, &data, &ancestors) {
+            user_agent.apply_declaration(declaration, &parent);
+        }
+        let hints = presentational_hints(element);
+        let author: Vec<&Declaration> = hints
+            .iter()
+            .chain(matching_declarations(&self.stylesheets, &data, &ancestors))
+            .collect();
+        let inline = element
+            .attributes
+            .get("style")
+            .and_then(|style| style.as_deref())
+            .map(parse_declarations)
+            .unwrap_or_default();
+        cascade(&parent, Some(&user_agent), &author, &inline)
+    }
+    
+    // Run `render` with the element's computed style, with the element on the
+    // ancestor stack so its descendants match selectors and inherit from it.
+    // Styles are computed the first time an element is drawn and reused after.
+    pub fn with_element<R>(&self, element: &html_parser::Element, render: impl FnOnce(&ComputedStyle) -> R) -> R {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        (self.path_keys.borrow().last(), element as *const html_parser::Element).hash(&mut hasher);
+        let key = hasher.finish();
+        let cached = self.style_cache.borrow().get(&key).cloned();
+        let style = cached.unwrap_or_else(|| {
+            let style = self.compute_style(element);
+            self.style_cache.borrow_mut().insert(key, style.clone());
+            style
+        });
+        self.ancestors.borrow_mut().push(ElementData::from_element(element));
+        self.styles.borrow_mut().push(style.clone());
+        self.path_keys.borrow_mut().push(key);
+        let result = render(&style);
+        self.path_keys.borrow_mut().pop();
+        self.styles.borrow_mut().pop();
+        self.ancestors.borrow_mut().pop();
+        result
+    }
+    
+    // Space above a block box: its top margin collapsed with the bottom
+    // margin of the block before it
+    pub fn begin_block(&self, ui: &mut Ui, margin_top: f32) {
+        let space = collapse_margins(self.pending_margin.take(), margin_top);
+        if space > 0.0 {
+            ui.add_space(space);
+        }
+    }
+    
+    pub fn end_block(&self, margin_bottom: f32) {
+        self.pending_margin.set(margin_bottom);
+    }
+    
+    // Add the held-back margin before content that isn't a block box
+    pub fn flush_margin(&self, ui: &mut Ui) {
+        let space = self.pending_margin.take();
+        if space > 0.0 {
+            ui.add_space(space);
+        }
+    }
+    
+    // The form a control belongs to: the one its `form` attribute names, or
+    // else the form it is inside
+    pub fn form_owner(&self, control: &html_parser::Element) -> Option<FormRef> {
+        match control.attributes.get("form") {
+            Some(id) => Some(FormRef::Id(id.clone().unwrap_or_default())),
+            None => self.forms.borrow().last().cloned(),
+        }
+    }
+    
+    // Ask for the control's form to be submitted; the browser picks the
+    // submission up after the frame. `submitter` is false for implicit
+    // submission, e.g. pressing Enter in a text field.
+    pub fn submit_form(&self, control: &html_parser::Element, submitter: bool) {
+        if let Some(form) = self.form_owner(control) {
+            self.form_state.borrow_mut().submission = Some(Submission {
+                form,
+                submitter: submitter.then(|| control_key(control)),
+            });
+        }
+    }
+    
+    // Whether a control is disabled, by its own attribute or a fieldset's
+    pub fn is_disabled(&self, control: &html_parser::Element) -> bool {
+        control.attributes.contains_key("disabled") || self.fieldset_disabled.get()
+    }
+    
+    pub fn current_label(&self) -> Option<LabelTarget> {
+        self.label.borrow().clone()
+    }
+    
+    // Pass a click on a label on to its control, which may already have been
+    // drawn this frame
+    pub fn activate_label(&self, ctx: &egui::Context, target: LabelTarget) {
+        self.form_state.borrow_mut().activation = Some(target);
+        ctx.request_repaint();
+    }
+    
+    // Render a page's body inside its <html> and <body> elements, so rules and
+    // styles set on them apply
+    pub fn render_document(&self, ui: &mut Ui, page: &HtmlPage) {
+        self.pending_margin.set(0.0);
+        self.forms.borrow_mut().clear();
+        self.fieldset_disabled.set(false);
+        self.render_in_path(ui, &page.body_path, &page.body);
+    }
+    
+    fn render_in_path(&self, ui: &mut Ui, path: &[html_parser::Element], body: &[html_parser::Node]) {
+        match path.split_first() {
+            Some((element, rest)) => self.with_element(element, |style| {
+                if !style.display_none {
+                    render_block_box(ui, style, self, |ui| self.render_in_path(ui, rest, body));
+                }
+            }),
+            None => self.render_html_node(ui, body),
+        }
+    }
+    
     // Find the body element in the DOM and filter out script/style content
-    pub fn find_body_element<'a>(&self, nodes: &'a [html_parser::Node]) -> Option<Vec<html_parser::Node>> {
+    pub fn find_body_element(&self, nodes: &[html_parser::Node]) -> Option<Vec<html_parser::Node>> {
         for node in nodes {
             if let html_parser::Node::Element(element) = node {
                 if element.name.to_lowercase() == "body" {
@@ -73,7 +270,7 @@ impl HtmlRenderer {
                     
                     // Handle deeply nested div structures by flattening when possible
                     if tag_name == "div" && element.children.len() == 1 {
-                        if let Some(html_parser::Node::Element(child)) = element.children.get(0) {
+                        if let Some(html_parser::Node::Element(child)) = element.children.first() {
                             if child.name.to_lowercase() == "div" {
                                 // Add the child's children directly, skipping one layer
                                 let grandchildren = self.filter_nodes(&child.children);
@@ -83,19 +280,27 @@ impl HtmlRenderer {
                         }
                     }
                     
+                    // The parser drops whitespace-only text, so bring back the
+                    // space between elements that weren't adjacent in the source
+                    if let Some(html_parser::Node::Element(previous)) = filtered.last() {
+                        let gap = (previous.source_span.end_line, previous.source_span.end_column)
+                            != (element.source_span.start_line, element.source_span.start_column);
+                        if gap {
+                            filtered.push(html_parser::Node::Text(" ".to_string()));
+                        }
+                    }
+                    
                     // For other elements, filter their children
                     let mut cloned = element.clone();
                     cloned.children = self.filter_nodes(&element.children);
                     filtered.push(html_parser::Node::Element(cloned));
                 },
-                html_parser::Node::Text(text) => {
-                    // Keep only non-empty text nodes
-                    let trimmed = text.trim();
-                    if !trimmed.is_empty() {
-                        filtered.push(html_parser::Node::Text(trimmed.to_string()));
-                    }
+                // Keep only non-empty text nodes; surrounding whitespace
+                // separates words from neighbouring inline elements
+                html_parser::Node::Text(text) if !text.trim().is_empty() => {
+                    filtered.push(html_parser::Node::Text(text.clone()));
                 },
-                _ => {} // Skip comments and other node types
+                _ => {} // Skip blank text, comments and other node types
             }
         }
         
@@ -104,113 +309,213 @@ impl HtmlRenderer {
     
     // Main HTML renderer
     pub fn render_html_node(&self, ui: &mut Ui, nodes: &[html_parser::Node]) {
-        for node in nodes {
+        let mut index = 0;
+        while index < nodes.len() {
+            // Form controls sit in the line with the text around them
+            let run_count = nodes[index..].iter().take_while(|node| is_inline(node) || holds_control(node)).count();
+            if nodes[index..index + run_count].iter().any(holds_control) {
+                self.flush_margin(ui);
+                ui.horizontal_wrapped(|ui| self.render_control_run(ui, &nodes[index..index + run_count]));
+                index += run_count;
+                continue;
+            }
             
-            match node {
-                html_parser::Node::Text(text) => {
-                    // Render plain text
-                    let trimmed_text = text.trim();
-                    if !trimmed_text.is_empty() {
-                        ui.label(trimmed_text);
+            // Consecutive text and inline elements flow as one paragraph
+            let inline_count = nodes[index..].iter().take_while(|node| is_inline(node)).count();
+            if inline_count > 0 {
+                self.flush_margin(ui);
+                render_inline(ui, &nodes[index..index + inline_count], self);
+                index += inline_count;
+                continue;
+            }
+            
+            if let html_parser::Node::Element(element) = &nodes[index] {
+                // Every element renders with its computed style, and its
+                // children inherit from it
+                self.with_element(element, |style| {
+                    if !style.display_none {
+                        self.render_element(ui, element, style);
                     }
-                }
-                html_parser::Node::Element(element) => {
-                    let tag_name = element.name.to_lowercase();
-                    
-                    // Get styling for the tag
-                    let style = self.style_map.get(&tag_name);
-                    
-                    match tag_name.as_str() {
-                        // Heading elements
-                        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
-                            render_heading(ui, element, style, self);
-                        }
-                        
-                        // Paragraph
-                        "p" => {
-                            render_paragraph(ui, element, style, self);
-                        }
-                        
-                        // Links
-                        "a" => {
-                            render_link(ui, element, self);
-                        }
-                        
-                        // Text formatting
-                        "strong" | "b" | "em" | "i" => {
-                            render_text_formatting(ui, element, tag_name.as_str(), self);
-                        }
-                        
-                        // Lists
-                        "ul" | "ol" => {
-                            render_list(ui, element, tag_name.as_str(), self);
-                        }
-                        
-                        // List items are handled in the list rendering functions
-                        "li" => {
-                            // This should be handled by parent ul/ol
-                            let text = get_text_content(&element.children);
-                            ui.label(text);
-                        }
-                        
-                        // Image
-                        "img" => {
-                            render_image(ui, element, self);
-                        }
-                        
-                        // Horizontal rule
-                        "hr" => {
-                            ui.add_space(4.0);
-                            ui.separator();
-                            ui.add_space(4.0);
-                        }
-                        
-                        // Block elements
-                        "div" | "section" | "article" | "main" | "aside" | "header" | "footer" => {
-                            render_block_element(ui, element, self);
-                        }
-                        
-                        // Inline elements - just render children
-                        "span" => {
-                            self.render_html_node(ui, &element.children);
-                        }
-                        
-                        // Container elements - render their children
-                        "html" | "body" | "head" => {
-                            self.render_html_node(ui, &element.children);
-                        }
-                        
-                        // Code blocks
-                        "pre" | "code" => {
-                            render_code(ui, element, self);
-                        }
-                        
-                        // Form elements
-                        "input" | "textarea" | "button" | "select" => {
-                            render_form_element(ui, element, tag_name.as_str(), self);
-                        }
-                        
-                        // Form container
-                        "form" => {
-                            ui.horizontal(|ui| {
-                                self.render_html_node(ui, &element.children);
-                            });
-                        }
-                        
-                        // Table rendering
-                        "table" => {
-                            render_table(ui, element, self);
-                        }
-                        
-                        // Any other element - render its children
-                        _ => {
-                            self.render_html_node(ui, &element.children);
-                        }
+                });
+            }
+            index += 1;
+        }
+    }
+    
+    // A line of controls and the inline content between them
+    fn render_control_run(&self, ui: &mut Ui, nodes: &[html_parser::Node]) {
+        let mut index = 0;
+        while index < nodes.len() {
+            let inline_count = nodes[index..].iter().take_while(|node| !holds_control(node)).count();
+            if inline_count > 0 {
+                render_inline(ui, &nodes[index..index + inline_count], self);
+                index += inline_count;
+                continue;
+            }
+            if let html_parser::Node::Element(element) = &nodes[index] {
+                self.with_element(element, |style| {
+                    if style.display_none {
+                        return;
+                    }
+                    if is_control(element) {
+                        render_form_element(ui, element, &element.name.to_lowercase(), style, self);
+                    } else if element.name.eq_ignore_ascii_case("label") {
+                        let outer = self.label.replace(label_target(element));
+                        self.render_control_run(ui, &element.children);
+                        *self.label.borrow_mut() = outer;
+                    } else {
+                        self.render_control_run(ui, &element.children);
                     }
+                });
+            }
+            index += 1;
+        }
+    }
+    
+    // A disabled fieldset disables everything in it but its first legend
+    fn render_fieldset(&self, ui: &mut Ui, element: &html_parser::Element) {
+        let outer = self.fieldset_disabled.get();
+        let disabled = outer || element.attributes.contains_key("disabled");
+        let children = &element.children;
+        let (before, legend, after) = match first_legend(children) {
+            Some(legend) => (&children[..legend], &children[legend..legend + 1], &children[legend + 1..]),
+            None => (&children[..], &children[..0], &children[..0]),
+        };
+        self.fieldset_disabled.set(disabled);
+        self.render_html_node(ui, before);
+        self.fieldset_disabled.set(outer);
+        self.render_html_node(ui, legend);
+        self.fieldset_disabled.set(disabled);
+        self.render_html_node(ui, after);
+        self.fieldset_disabled.set(outer);
+    }
+    
+    fn render_element(&self, ui: &mut Ui, element: &html_parser::Element, style: &ComputedStyle) {
+        let tag_name = element.name.to_lowercase();
+        
+        // Everything but replaced elements, forms and tables is a block box:
+        // headings, paragraphs, sections, lists and their items, and inline
+        // elements with blocks inside them
+        if !matches!(
+            tag_name.as_str(),
+            "img" | "hr" | "pre" | "input" | "textarea" | "button" | "select" | "table"
+        ) {
+            render_block_box(ui, style, self, |ui| match tag_name.as_str() {
+                "ul" | "ol" => render_list(ui, element, &tag_name, self),
+                "form" => {
+                    self.forms.borrow_mut().push(FormRef::Key(control_key(element)));
+                    self.render_html_node(ui, &element.children);
+                    self.forms.borrow_mut().pop();
                 }
-                // Ignore comments and other node types
-                _ => {}
+                "fieldset" => self.render_fieldset(ui, element),
+                // A list item outside a list still gets its marker
+                "li" => render_list_item(ui, element, style, 1, self),
+                _ => self.render_html_node(ui, &element.children),
+            });
+            return;
+        }
+        
+        self.flush_margin(ui);
+        match tag_name.as_str() {
+            // Image
+            "img" => {
+                render_image(ui, element, style, self);
+            }
+            
+            // Horizontal rule
+            "hr" => {
+                ui.add_space(4.0);
+                ui.separator();
+                ui.add_space(4.0);
+            }
+            
+            // Preformatted blocks
+            "pre" => {
+                render_code(ui, element, style, self);
+            }
+            
+            // Form elements
+            "input" | "textarea" | "button" | "select" => {
+                render_form_element(ui, element, tag_name.as_str(), style, self);
+            }
+            
+            // Table rendering
+            _ => {
+                render_table(ui, element, style, self);
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// A form control, or an inline element such as <label> with controls in it
+fn holds_control(node: &html_parser::Node) -> bool {
+    let html_parser::Node::Element(element) = node else {
+        return false;
+    };
+    is_control(element)
+        || (is_inline_tag(&element.name)
+            && element.children.iter().any(holds_control)
+            && element.children.iter().all(|child| is_inline(child) || holds_control(child)))
+}
+
+// Attributes that stand in for CSS, like bgcolor and cellspacing. They take
+// part in the cascade as author rules that any stylesheet rule overrides.
+fn presentational_hints(element: &html_parser::Element) -> Vec<Declaration> {
+    let tag = element.name.to_lowercase();
+    let attribute = |name: &str| element.attributes.get(name).map(|value| value.clone().unwrap_or_default());
+    // Plain numbers are pixels
+    let length = |value: String| {
+        let value = value.trim().to_string();
+        if value.parse::<f32>().is_ok() { format!("{}px", value) } else { value }
+    };
+    let mut hints = Vec::new();
+    let mut hint = |property: &str, value: String| {
+        hints.push(Declaration { property: property.to_string(), value, important: false });
+    };
+
+    if attribute("hidden").is_some() {
+        hint("display", "none".to_string());
+    }
+    if let Some(color) = attribute("bgcolor") {
+        hint("background-color", color);
+    }
+    if matches!(tag.as_str(), "table" | "td" | "th") {
+        if let Some(width) = attribute("width") {
+            hint("width", length(width));
+        }
+        if let Some(height) = attribute("height") {
+            hint("height", length(height));
+        }
+    }
+    // HTML list types; a and A, i and I differ only in case
+    if matches!(tag.as_str(), "ol" | "ul" | "li") {
+        let list_style = match attribute("type").unwrap_or_default().trim() {
+            "1" => Some("decimal"),
+            "a" => Some("lower-alpha"),
+            "A" => Some("upper-alpha"),
+            "i" => Some("lower-roman"),
+            "I" => Some("upper-roman"),
+            list_type => ["disc", "circle", "square"].into_iter().find(|name| list_type.eq_ignore_ascii_case(name)),
+        };
+        if let Some(list_style) = list_style {
+            hint("list-style-type", list_style.to_string());
+        }
+    }
+    if tag == "table" {
+        // A bare `border` attribute means a one pixel border
+        if let Some(border) = attribute("border") {
+            let width = border.trim().parse::<f32>().unwrap_or(1.0);
+            if width > 0.0 {
+                hint("border", format!("{}px outset #808080", width));
+            }
+        }
+        if let Some(spacing) = attribute("cellspacing") {
+            hint("border-spacing", length(spacing));
+        }
+        if attribute("align").is_some_and(|align| align.eq_ignore_ascii_case("center")) {
+            hint("margin-left", "auto".to_string());
+            hint("margin-right", "auto".to_string());
+        }
+    }
+    hints
+}
@@ -1,37 +1,65 @@
 // src/html_renderer/renderers/block.rs
-use egui::{TextEdit, Ui};
+use egui::{Align, Layout, Pos2, Rect, Sense, Shape, TextEdit, Ui, Vec2};
+use crate::html_renderer::layout::BlockBox;
 use crate::html_renderer::renderer::HtmlRenderer;
 use crate::html_renderer::text_processor::get_text_content;
+use crate::style::ComputedStyle;
 
-// Render block element
-pub fn render_block_element(
-    ui: &mut Ui, 
-    element: &html_parser::Element,
-    renderer: &HtmlRenderer
+// Render a block box: lay out its geometry, place the contents in its content
+// area, then paint its background and borders behind them
+pub fn render_block_box(
+    ui: &mut Ui,
+    style: &ComputedStyle,
+    renderer: &HtmlRenderer,
+    add_contents: impl FnOnce(&mut Ui),
 ) {
-    // Check if we need to render as a group (reduce nesting)
-    // Only create a group for elements with certain attributes or multiple children
-    let has_id = element.attributes.contains_key("id");
-    let has_class = element.attributes.contains_key("class");
-    let multiple_children = element.children.len() > 1;
-    
-    if has_id || has_class || multiple_children {
-        // Add minimal spacing and group
-        ui.add_space(1.0);
-        ui.group(|ui| {
-            renderer.render_html_node(ui, &element.children);
-        });
-        ui.add_space(1.0);
-    } else {
-        // Skip the group and render children directly to avoid nesting
-        renderer.render_html_node(ui, &element.children);
+    let block = BlockBox::layout(style, ui.available_width());
+    renderer.begin_block(ui, block.margin.top);
+
+    // The background is painted first but sized last, once the contents are laid out
+    let background = ui.painter().add(Shape::Noop);
+    let min = ui.cursor().min + Vec2::new(block.margin.left, 0.0);
+    let content_min = min + block.content_offset();
+    let mut content_ui = ui.child_ui(
+        Rect::from_min_size(content_min, Vec2::new(block.content_width, f32::INFINITY)),
+        Layout::top_down(Align::Min),
+    );
+    add_contents(&mut content_ui);
+    // Margins don't collapse through the box's own edges
+    renderer.flush_margin(&mut content_ui);
+
+    let content_height = content_ui.min_rect().bottom() - content_min.y;
+    let rect = block.border_rect(min, content_height.max(0.0));
+    ui.painter().set(background, box_shape(rect, style, &block));
+    ui.allocate_rect(rect, Sense::hover());
+    renderer.end_block(block.margin.bottom);
+}
+
+// Background over the whole border box, with each border drawn along its edge
+pub fn box_shape(rect: Rect, style: &ComputedStyle, block: &BlockBox) -> Shape {
+    let mut shapes = Vec::new();
+    if let Some(color) = style.background_color {
+        shapes.push(Shape::rect_filled(rect, 0.0, color));
+    }
+    let edges = [
+        (block.border.top, style.border.top.color, Rect::from_min_max(rect.min, Pos2::new(rect.max.x, rect.min.y + block.border.top))),
+        (block.border.right, style.border.right.color, Rect::from_min_max(Pos2::new(rect.max.x - block.border.right, rect.min.y), rect.max)),
+        (block.border.bottom, style.border.bottom.color, Rect::from_min_max(Pos2::new(rect.min.x, rect.max.y - block.border.bottom), rect.max)),
+        (block.border.left, style.border.left.color, Rect::from_min_max(rect.min, Pos2::new(rect.min.x + block.border.left, rect.max.y))),
+    ];
+    for (width, color, edge) in edges {
+        if width > 0.0 {
+            shapes.push(Shape::rect_filled(edge, 0.0, color));
+        }
     }
+    Shape::Vec(shapes)
 }
 
 // Render code block
 pub fn render_code(
     ui: &mut Ui, 
     element: &html_parser::Element, 
+    style: &ComputedStyle,
     _renderer: &HtmlRenderer
 ) {
     let text = get_text_content(&element.children);
@@ -48,7 +76,8 @@ pub fn render_code(
     
     ui.add(
         TextEdit::multiline(&mut display_text.clone())
-            .font(egui::TextStyle::Monospace)
+            .font(egui::FontId::monospace(style.font_size))
+            .text_color(style.color)
             .desired_width(f32::INFINITY)
             .interactive(false)
     );
@@ -1,63 +1,397 @@
 // src/html_renderer/renderers/forms.rs
-use egui::Ui;
+// Form controls. What the user types, ticks or picks lives in the document's
+// form state, so it survives repaints; submit buttons and Enter in a text
+// field queue a submission of the control's form.
+use egui::{Align, Color32, RichText, Stroke, Ui};
+use crate::dates::{days_in_month, today, weekday, MAX_YEAR};
+use crate::forms::{
+    control_key, format_color, format_date, format_number, input_type, is_multiple, number_bounds, option_label,
+    parse_color, parse_date, range_value, select_options,
+};
 use crate::html_renderer::renderer::HtmlRenderer;
 use crate::html_renderer::text_processor::{get_text_content, get_attribute};
+use crate::style::ComputedStyle;
+
+// Rough width of a character in a text field, for `size` and `cols`
+const CHAR_WIDTH: f32 = 7.0;
+
+// Outline of a control whose value breaks its constraints
+const INVALID_COLOR: Color32 = Color32::from_rgb(220, 40, 40);
 
 // Render form element
 pub fn render_form_element(
-    ui: &mut Ui, 
+    ui: &mut Ui,
     element: &html_parser::Element,
     tag: &str,
-    _renderer: &HtmlRenderer
+    _style: &ComputedStyle,
+    renderer: &HtmlRenderer
 ) {
-    match tag {
-        "input" => render_input(ui, element),
+    let disabled = renderer.is_disabled(element);
+    // A click on one of the control's labels counts as a click on the control
+    let activated = !disabled && renderer.form_state.borrow_mut().take_activation(element);
+    let response = ui.add_enabled_ui(!disabled, |ui| match tag {
+        "input" => render_input(ui, element, activated, renderer),
         "textarea" => {
-            let mut text = get_text_content(&element.children);
-            ui.text_edit_multiline(&mut text);
+            let mut text = renderer.form_state.borrow().text(element);
+            let columns = get_attribute(element, "cols", "").parse::<f32>().unwrap_or(20.0);
+            let rows = get_attribute(element, "rows", "").parse::<usize>().unwrap_or(2);
+            let response = ui.add(
+                egui::TextEdit::multiline(&mut text)
+                    .desired_width(columns * CHAR_WIDTH)
+                    .desired_rows(rows)
+                    .char_limit(max_length(element))
+                    .interactive(!element.attributes.contains_key("readonly")),
+            );
+            if response.changed() {
+                renderer.form_state.borrow_mut().set_text(element, text);
+            }
+            if activated {
+                response.request_focus();
+            }
         }
         "button" => {
             let text = get_text_content(&element.children);
-            let _ = ui.button(text);
-        }
-        "select" => {
-            ui.label("[Dropdown menu]");
+            let clicked = ui.button(text).clicked() || activated;
+            // Buttons submit their form unless they're type=button or reset
+            let kind = get_attribute(element, "type", "submit").to_lowercase();
+            if clicked && kind != "button" && kind != "reset" {
+                renderer.submit_form(element, true);
+            }
         }
+        "select" => render_select(ui, element, renderer),
         _ => {}
+    })
+    .response;
+    show_validity(ui, element, &response, renderer);
+}
+
+// Outline an invalid control and explain what's wrong when it's hovered. The
+// control that stopped a submission shows its message until the user edits
+// something.
+fn show_validity(ui: &mut Ui, element: &html_parser::Element, response: &egui::Response, renderer: &HtmlRenderer) {
+    let mut state = renderer.form_state.borrow_mut();
+    let Some(message) = state.validation_message(element).map(str::to_string) else {
+        return;
+    };
+    ui.painter().rect_stroke(response.rect.expand(1.0), 2.0, Stroke::new(1.5, INVALID_COLOR));
+    if state.reported == Some(control_key(element)) {
+        if std::mem::take(&mut state.scroll_to_reported) {
+            response.scroll_to_me(Some(Align::Center));
+        }
+        egui::show_tooltip_for(ui.ctx(), response.id.with("validation"), &response.rect, |ui| {
+            ui.label(message);
+        });
+    } else {
+        response.clone().on_hover_text(message);
     }
 }
 
 // Render input element
-fn render_input(ui: &mut Ui, element: &html_parser::Element) {
-    let input_type = get_attribute(element, "type", "text");
-    let name = get_attribute(element, "name", "");
-    
+fn render_input(ui: &mut Ui, element: &html_parser::Element, activated: bool, renderer: &HtmlRenderer) {
+    let input_type = input_type(element);
+    let state = &renderer.form_state;
+
     match input_type.as_str() {
-        "button" | "submit" => {
-            let value = get_attribute(element, "value", "Button");
-            if value.is_empty() {
-                let _ = ui.button("Submit");
+        "button" | "submit" | "reset" | "image" => {
+            let default = match input_type.as_str() {
+                "submit" => "Submit",
+                "reset" => "Reset",
+                _ => "Button",
+            };
+            let value = if input_type == "image" {
+                get_attribute(element, "alt", "Submit")
             } else {
-                let _ = ui.button(&value);
+                get_attribute(element, "value", default)
+            };
+            let label = if value.is_empty() { default } else { &value };
+            let clicked = ui.button(label).clicked() || activated;
+            if clicked && (input_type == "submit" || input_type == "image") {
+                renderer.submit_form(element, true);
             }
         }
         "checkbox" => {
-            let mut checked = element.attributes.contains_key("checked");
-            ui.checkbox(&mut checked, "");
+            let mut checked = state.borrow().checked(element);
+            if ui.checkbox(&mut checked, "").changed() || activated {
+                let checked = if activated { !checked } else { checked };
+                state.borrow_mut().set_checked(element, checked);
+            }
+        }
+        "radio" => {
+            let form = renderer.form_owner(element);
+            let checked = state.borrow().radio_checked(element, form.as_ref());
+            if ui.radio(checked, "").clicked() || activated {
+                state.borrow_mut().check_radio(element, form.as_ref());
+            }
+        }
+        "number" => render_number(ui, element, activated, renderer),
+        "range" => {
+            let bounds = number_bounds(element);
+            let mut value = range_value(element, &state.borrow().text(element));
+            let (min, max) = (bounds.min.unwrap_or(0.0), bounds.max.unwrap_or(100.0).max(bounds.min.unwrap_or(0.0)));
+            let mut slider = egui::Slider::new(&mut value, min..=max).show_value(false);
+            if let Some(step) = bounds.step {
+                slider = slider.step_by(step);
+            }
+            if ui.add(slider).changed() {
+                state.borrow_mut().set_text(element, format_number(range_value(element, &value.to_string())));
+            }
+        }
+        "color" => {
+            let mut color = parse_color(&state.borrow().text(element)).unwrap_or_default();
+            if ui.color_edit_button_srgb(&mut color).changed() {
+                state.borrow_mut().set_text(element, format_color(color));
+            }
         }
+        "date" => render_date(ui, element, activated, renderer),
+        "file" => render_file(ui, element, activated, renderer),
         "hidden" => {
             // Don't render hidden inputs
         }
-        "search" | "text" | "password" | "email" | _ => {
-            let mut value = get_attribute(element, "value", "");
+        // text, search, password, email and anything unknown
+        _ => {
+            let mut value = state.borrow().text(element);
+            let name = get_attribute(element, "name", "");
             let placeholder = get_attribute(element, "placeholder", "");
-            
+            let width = get_attribute(element, "size", "").parse::<f32>().map_or(200.0, |size| size * CHAR_WIDTH);
+
             // Create more visible text field
             let field = egui::TextEdit::singleline(&mut value)
-                .desired_width(200.0)
+                .desired_width(width)
+                .password(input_type == "password")
+                .char_limit(max_length(element))
+                .interactive(!element.attributes.contains_key("readonly"))
                 .hint_text(if placeholder.is_empty() { &name } else { &placeholder });
-                
-            ui.add(field);
+
+            let response = ui.add(field);
+            if response.changed() {
+                state.borrow_mut().set_text(element, value);
+            }
+            if activated {
+                response.request_focus();
+            }
+            // Enter in a text field submits its form
+            if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                renderer.submit_form(element, false);
+            }
+        }
+    }
+}
+
+// Typing stops at maxlength, though a longer value from the markup is kept
+fn max_length(element: &html_parser::Element) -> usize {
+    get_attribute(element, "maxlength", "").trim().parse().unwrap_or(usize::MAX)
+}
+
+// A text field with buttons that step the number up and down
+fn render_number(ui: &mut Ui, element: &html_parser::Element, activated: bool, renderer: &HtmlRenderer) {
+    let bounds = number_bounds(element);
+    let readonly = element.attributes.contains_key("readonly");
+    let mut text = renderer.form_state.borrow().text(element);
+    let response = ui.add(egui::TextEdit::singleline(&mut text).desired_width(80.0).interactive(!readonly));
+    if activated {
+        response.request_focus();
+    }
+    if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+        renderer.submit_form(element, false);
+    }
+
+    // Stepping starts from the current number, or from min or 0
+    let current = text.trim().parse::<f64>().ok().filter(|value| value.is_finite());
+    let step = bounds.step.unwrap_or(1.0);
+    let mut stepped = None;
+    ui.add_enabled_ui(!readonly, |ui| {
+        if ui.small_button("-").clicked() {
+            stepped = Some(current.map_or(bounds.min.unwrap_or(0.0), |value| value - step));
+        }
+        if ui.small_button("+").clicked() {
+            stepped = Some(current.map_or(bounds.min.unwrap_or(0.0), |value| value + step));
+        }
+    });
+    if let Some(value) = stepped {
+        text = format_number(bounds.constrain(value));
+    }
+    if response.changed() || stepped.is_some() {
+        renderer.form_state.borrow_mut().set_text(element, text);
+    }
+}
+
+// A date field with a calendar that drops down from the button beside it
+fn render_date(ui: &mut Ui, element: &html_parser::Element, activated: bool, renderer: &HtmlRenderer) {
+    let readonly = element.attributes.contains_key("readonly");
+    let mut text = renderer.form_state.borrow().text(element);
+    let response = ui.add(
+        egui::TextEdit::singleline(&mut text)
+            .desired_width(90.0)
+            .hint_text("yyyy-mm-dd")
+            .interactive(!readonly),
+    );
+    if response.changed() {
+        renderer.form_state.borrow_mut().set_text(element, text.clone());
+    }
+    if activated {
+        response.request_focus();
+    }
+
+    let popup_id = ui.make_persistent_id(("date_picker", control_key(element)));
+    let button = ui.add_enabled(!readonly, egui::Button::new("...").small());
+    if button.clicked() {
+        ui.memory_mut(|memory| memory.toggle_popup(popup_id));
+    }
+    let chosen = egui::popup_below_widget(ui, popup_id, &button, |ui| {
+        ui.set_min_width(200.0);
+        calendar(ui, popup_id, parse_date(&text), element)
+    });
+    if let Some(date) = chosen.flatten() {
+        renderer.form_state.borrow_mut().set_text(element, format_date(date));
+        ui.memory_mut(|memory| memory.close_popup());
+    }
+}
+
+// A month of days to pick from, starting at the chosen date's month. Days
+// outside min and max can't be picked.
+fn calendar(ui: &mut Ui, id: egui::Id, chosen: Option<(i32, u32, u32)>, element: &html_parser::Element) -> Option<(i32, u32, u32)> {
+    let min = parse_date(&get_attribute(element, "min", ""));
+    let max = parse_date(&get_attribute(element, "max", ""));
+    let (year, month) = ui.data(|data| data.get_temp::<(i32, u32)>(id)).unwrap_or_else(|| {
+        let (year, month, _) = chosen.unwrap_or_else(today);
+        (year, month)
+    });
+
+    // Paging stops at the first and last years a date can have
+    let mut shown = (year, month);
+    let valid_year = |year: &i32| (1..=MAX_YEAR).contains(year);
+    ui.horizontal(|ui| {
+        if ui.small_button("<").clicked() {
+            shown = match month {
+                1 => year.checked_sub(1).filter(valid_year).map_or(shown, |year| (year, 12)),
+                _ => (year, month - 1),
+            };
+        }
+        ui.label(format!("{:04}-{:02}", year, month));
+        if ui.small_button(">").clicked() {
+            shown = match month {
+                12 => year.checked_add(1).filter(valid_year).map_or(shown, |year| (year, 1)),
+                _ => (year, month + 1),
+            };
+        }
+    });
+    ui.data_mut(|data| data.insert_temp(id, shown));
+
+    let mut picked = None;
+    egui::Grid::new(id.with("days")).show(ui, |ui| {
+        for name in ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"] {
+            ui.label(RichText::new(name).weak());
+        }
+        ui.end_row();
+        let first = weekday(year, month, 1);
+        for _ in 0..first {
+            ui.label("");
+        }
+        for day in 1..=days_in_month(year, month) {
+            let date = (year, month, day);
+            let allowed = min.is_none_or(|min| date >= min) && max.is_none_or(|max| date <= max);
+            let label = egui::SelectableLabel::new(chosen == Some(date), day.to_string());
+            if ui.add_enabled(allowed, label).clicked() {
+                picked = Some(date);
+            }
+            if (first + day).is_multiple_of(7) {
+                ui.end_row();
+            }
+        }
+    });
+    picked
+}
+
+// A button that opens the native file picker, and the names of the files picked
+fn render_file(ui: &mut Ui, element: &html_parser::Element, activated: bool, renderer: &HtmlRenderer) {
+    let multiple = element.attributes.contains_key("multiple");
+    if ui.button("Browse...").clicked() || activated {
+        let mut dialog = rfd::FileDialog::new();
+        // Only file extensions in `accept` can be filtered on
+        let extensions: Vec<String> = get_attribute(element, "accept", "")
+            .split(',')
+            .filter_map(|accept| accept.trim().strip_prefix('.').map(str::to_string))
+            .collect();
+        if !extensions.is_empty() {
+            dialog = dialog.add_filter("Accepted files", &extensions);
         }
+        let files = if multiple {
+            dialog.pick_files()
+        } else {
+            dialog.pick_file().map(|file| vec![file])
+        };
+        // Cancelling keeps what was picked before
+        if let Some(files) = files {
+            renderer.form_state.borrow_mut().set_files(element, files);
+        }
+    }
+
+    let files = renderer.form_state.borrow().files(element);
+    let names: Vec<String> = files
+        .iter()
+        .filter_map(|file| file.file_name().map(|name| name.to_string_lossy().into_owned()))
+        .collect();
+    ui.label(match names.len() {
+        0 => "No file selected.".to_string(),
+        1 => names[0].clone(),
+        count => format!("{} files", count),
+    });
+}
+
+// A drop-down list, or a list box for multiple choice and `size` above one
+fn render_select(ui: &mut Ui, element: &html_parser::Element, renderer: &HtmlRenderer) {
+    let options = select_options(element);
+    let selected = renderer.form_state.borrow().selected(element);
+    let multiple = is_multiple(element);
+    let size = get_attribute(element, "size", "").parse::<usize>().unwrap_or(if multiple { 4 } else { 1 });
+    let mut choice = None;
+
+    // Options under a heading for each optgroup
+    let mut list = |ui: &mut Ui| {
+        let mut group = None;
+        for (index, option) in options.iter().enumerate() {
+            if option.group.map(control_key) != group.map(control_key) {
+                group = option.group;
+                if let Some(group) = group {
+                    let label = get_attribute(group, "label", "");
+                    ui.label(RichText::new(label).strong().color(Color32::GRAY));
+                }
+            }
+            let indent = if option.group.is_some() { "    " } else { "" };
+            let label = egui::SelectableLabel::new(selected.contains(&index), format!("{}{}", indent, option_label(option.element)));
+            if ui.add_enabled(!option.disabled, label).clicked() {
+                choice = Some(index);
+            }
+        }
+    };
+
+    if size <= 1 && !multiple {
+        let text = selected.first().map(|&index| option_label(options[index].element)).unwrap_or_default();
+        egui::ComboBox::from_id_source(("select", control_key(element)))
+            .selected_text(text)
+            .show_ui(ui, |ui| list(ui));
+    } else {
+        let row_height = ui.spacing().interact_size.y;
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .id_source(("select", control_key(element)))
+                .max_height(size as f32 * row_height)
+                .show(ui, |ui| ui.vertical(|ui| list(ui)));
+        });
+    }
+
+    // Clicking toggles an option of a multiple select, and replaces the choice otherwise
+    if let Some(index) = choice {
+        let selected = if !multiple {
+            vec![index]
+        } else if selected.contains(&index) {
+            selected.into_iter().filter(|&chosen| chosen != index).collect()
+        } else {
+            let mut selected = selected;
+            selected.push(index);
+            selected.sort_unstable();
+            selected
+        };
+        renderer.form_state.borrow_mut().set_selected(element, selected);
     }
 }
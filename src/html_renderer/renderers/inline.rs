@@ -0,0 +1,356 @@
+// src/html_renderer/renderers/inline.rs
+// Inline formatting context: a run of text and inline elements is laid out as
+// one paragraph. Every styled run becomes a section of a single LayoutJob, so
+// egui wraps the whole paragraph to the available width, and link runs are
+// found again by hit-testing the laid-out glyphs.
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Align, Color32, FontFamily, FontId, Galley, Pos2, Sense, Stroke, Ui, Vec2};
+use crate::forms::{label_target, LabelTarget};
+use crate::html_renderer::renderer::HtmlRenderer;
+use crate::html_renderer::text_processor::get_attribute;
+use crate::style::{ComputedStyle, VerticalAlign};
+use crate::url_resolver::is_fetchable;
+
+// Elements that flow within a line of text
+const INLINE_TAGS: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "big", "br", "cite", "code", "data", "del", "dfn", "em", "font", "i",
+    "ins", "kbd", "label", "mark", "q", "s", "samp", "small", "span", "strike", "strong", "sub", "sup",
+    "time", "tt", "u", "var",
+];
+
+// How far the bold overlay is shifted to thicken the strokes, per point of
+// font size
+const BOLD_OFFSET: f32 = 0.045;
+
+pub fn is_inline_tag(name: &str) -> bool {
+    INLINE_TAGS.contains(&name.to_lowercase().as_str())
+}
+
+// Whether a node can be part of an inline run: text, or an inline element with
+// only inline content. Comments are skipped without breaking the run.
+pub fn is_inline(node: &html_parser::Node) -> bool {
+    match node {
+        html_parser::Node::Text(_) | html_parser::Node::Comment(_) => true,
+        html_parser::Node::Element(element) => {
+            is_inline_tag(&element.name) && element.children.iter().all(is_inline)
+        }
+    }
+}
+
+// Lay out and paint a run of inline nodes as one wrapped paragraph
+pub fn render_inline(ui: &mut Ui, nodes: &[html_parser::Node], renderer: &HtmlRenderer) {
+    // Runs directly in a block don't repeat the block's own background
+    let style = ComputedStyle {
+        background_color: None,
+        ..renderer.current_style()
+    };
+    let mut builder = InlineBuilder::default();
+    // Text inside a label that wraps a control is part of that label
+    let label = renderer.current_label().map(ClickTarget::Label);
+    builder.collect(nodes, renderer, &style, label.as_ref());
+    if builder.job.text.is_empty() {
+        return;
+    }
+
+    builder.job.wrap.max_width = ui.available_width();
+    let overlay = builder.bold_overlay();
+    let galley = ui.fonts(|fonts| fonts.layout_job(builder.job));
+    let (rect, response) = ui.allocate_exact_size(galley.size(), Sense::click());
+
+    if ui.is_rect_visible(rect) {
+        ui.painter().galley(rect.min, galley.clone());
+        if let Some((overlay, offset)) = overlay {
+            let overlay = ui.fonts(|fonts| fonts.layout_job(overlay));
+            ui.painter().galley(rect.min + Vec2::new(offset, 0.0), overlay);
+        }
+    }
+
+    let hovered = response
+        .hover_pos()
+        .and_then(|pos| section_at(&galley, (pos - rect.min).to_pos2()))
+        .and_then(|section| builder.targets[section].clone());
+    match hovered {
+        Some(ClickTarget::Link(href)) => {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+            renderer.link_handler.set_hovered(renderer.resolve_url(&href));
+            if response.clicked() || response.middle_clicked() {
+                follow_link(ui, &href, response.middle_clicked(), renderer);
+            }
+        }
+        // Clicking a label clicks its control
+        Some(ClickTarget::Label(target)) if response.clicked() => renderer.activate_label(ui.ctx(), target),
+        _ => {}
+    }
+}
+
+// Navigate to a clicked link
+fn follow_link(ui: &Ui, href: &str, middle_clicked: bool, renderer: &HtmlRenderer) {
+    // Resolve relative and root-relative URLs against the document base
+    let url = renderer.resolve_url(href);
+    if !is_fetchable(&url) {
+        // Shown in the status bar rather than followed
+        renderer.link_handler.set_unsupported(url);
+        return;
+    }
+
+    // Middle-click and Ctrl+click open the link in a new tab
+    let new_tab = middle_clicked || ui.input(|i| i.modifiers.command);
+    renderer.link_handler.set_link(url, new_tab);
+}
+
+// The section of the glyph under a point relative to the galley, if any
+fn section_at(galley: &Galley, pos: Pos2) -> Option<usize> {
+    galley
+        .rows
+        .iter()
+        .filter(|row| row.rect.y_range().contains(pos.y))
+        .flat_map(|row| &row.glyphs)
+        .find(|glyph| glyph.logical_rect().x_range().contains(pos.x))
+        .map(|glyph| glyph.section_index as usize)
+}
+
+// What clicking a run of text does
+#[derive(Clone, Debug, PartialEq)]
+enum ClickTarget {
+    Link(String),
+    Label(LabelTarget),
+}
+
+#[derive(Default)]
+struct InlineBuilder {
+    job: LayoutJob,
+    // Per section of the job: the link or label it belongs to, and whether it's bold
+    targets: Vec<Option<ClickTarget>>,
+    bold: Vec<bool>,
+    // Collapsed whitespace waiting for the next word, with the formatting of
+    // the text it came from
+    pending_space: Option<(TextFormat, Option<ClickTarget>, bool)>,
+}
+
+impl InlineBuilder {
+    fn collect(&mut self, nodes: &[html_parser::Node], renderer: &HtmlRenderer, style: &ComputedStyle, target: Option<&ClickTarget>) {
+        for node in nodes {
+            match node {
+                html_parser::Node::Text(text) => self.push_text(text, style, target),
+                html_parser::Node::Element(element) => {
+                    let tag = element.name.to_lowercase();
+                    if tag == "br" {
+                        self.line_break(style);
+                        continue;
+                    }
+
+                    let href = get_attribute(element, "href", "");
+                    let element_target = match tag.as_str() {
+                        "a" if !href.is_empty() => Some(ClickTarget::Link(href)),
+                        "label" => label_target(element).map(ClickTarget::Label),
+                        _ => None,
+                    };
+                    let target = element_target.as_ref().or(target);
+                    renderer.with_element(element, |element_style| {
+                        if element_style.display_none {
+                            return;
+                        }
+                        // Text inside a highlighted element keeps the highlight
+                        let element_style = ComputedStyle {
+                            background_color: element_style.background_color.or(style.background_color),
+                            ..element_style.clone()
+                        };
+                        if tag == "q" {
+                            self.push_text("\u{201c}", &element_style, target);
+                            self.collect(&element.children, renderer, &element_style, target);
+                            self.push_text("\u{201d}", &element_style, target);
+                        } else {
+                            self.collect(&element.children, renderer, &element_style, target);
+                        }
+                    });
+                }
+                html_parser::Node::Comment(_) => {}
+            }
+        }
+    }
+
+    // Add text with its whitespace collapsed, as `white-space: normal` does
+    fn push_text(&mut self, text: &str, style: &ComputedStyle, target: Option<&ClickTarget>) {
+        let format = text_format(style);
+        let target = target.cloned();
+        let space = (format.clone(), target.clone(), style.is_bold());
+
+        let words = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if words.is_empty() {
+            if !text.is_empty() && !self.job.text.is_empty() && self.pending_space.is_none() {
+                self.pending_space = Some(space);
+            }
+            return;
+        }
+
+        if text.starts_with(char::is_whitespace) && !self.job.text.is_empty() && self.pending_space.is_none() {
+            self.pending_space = Some(space.clone());
+        }
+        if let Some((format, target, bold)) = self.pending_space.take() {
+            self.append(" ", format, target, bold);
+        }
+        self.append(&words, format, target, style.is_bold());
+        if text.ends_with(char::is_whitespace) {
+            self.pending_space = Some(space);
+        }
+    }
+
+    fn line_break(&mut self, style: &ComputedStyle) {
+        self.pending_space = None;
+        self.append("\n", text_format(style), None, false);
+    }
+
+    fn append(&mut self, text: &str, format: TextFormat, target: Option<ClickTarget>, bold: bool) {
+        self.job.append(text, 0.0, format);
+        self.targets.push(target);
+        self.bold.push(bold);
+    }
+
+    // egui's default fonts have no bold face, so bold runs are drawn a second
+    // time slightly offset. The overlay is the same job with everything but
+    // the bold glyphs made invisible, so both lay out identically. Returns the
+    // overlay and how far to shift it.
+    fn bold_overlay(&self) -> Option<(LayoutJob, f32)> {
+        let largest_bold = self
+            .job
+            .sections
+            .iter()
+            .zip(&self.bold)
+            .filter(|(_, bold)| **bold)
+            .map(|(section, _)| section.format.font_id.size)
+            .reduce(f32::max)?;
+        let mut overlay = self.job.clone();
+        for (section, bold) in overlay.sections.iter_mut().zip(&self.bold) {
+            if !bold {
+                section.format.color = Color32::TRANSPARENT;
+            }
+            section.format.background = Color32::TRANSPARENT;
+            section.format.underline = Stroke::NONE;
+            section.format.strikethrough = Stroke::NONE;
+        }
+        Some((overlay, largest_bold * BOLD_OFFSET))
+    }
+}
+
+pub fn text_format(style: &ComputedStyle) -> TextFormat {
+    let family = if style.monospace { FontFamily::Monospace } else { FontFamily::Proportional };
+    // Sub- and superscripts are drawn smaller, like the `font-size: smaller`
+    // browsers give them; egui can raise text but not lower it below the line
+    let (size, valign) = match style.vertical_align {
+        VerticalAlign::Baseline => (style.font_size, Align::BOTTOM),
+        VerticalAlign::Sub => (style.font_size * 0.8, Align::BOTTOM),
+        VerticalAlign::Super => (style.font_size * 0.8, Align::TOP),
+    };
+    let line = |on: bool| if on { Stroke::new(1.0, style.color) } else { Stroke::NONE };
+    TextFormat {
+        font_id: FontId::new(size, family),
+        color: style.color,
+        background: style.background_color.unwrap_or(Color32::TRANSPARENT),
+        italics: style.italic,
+        underline: line(style.underline),
+        strikethrough: line(style.strikethrough),
+        valign,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::LinkHandler;
+    use crate::style::create_default_styles;
+    use egui::{Context, Event, PointerButton, RawInput, Rect};
+
+    fn parse(html: &str) -> Vec<html_parser::Node> {
+        html_parser::Dom::parse(html).unwrap().children
+    }
+
+    // Run a frame with the nodes laid out from the top-left corner
+    fn run(ctx: &Context, renderer: &HtmlRenderer, nodes: &[html_parser::Node], events: Vec<Event>) {
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::new(400.0, 200.0))),
+            events,
+            ..Default::default()
+        };
+        let _ = ctx.run(input, |ctx| {
+            egui::CentralPanel::default().frame(egui::Frame::none()).show(ctx, |ui| {
+                render_inline(ui, nodes, renderer);
+            });
+        });
+    }
+
+    fn click_at(ctx: &Context, renderer: &HtmlRenderer, nodes: &[html_parser::Node], pos: Pos2) {
+        let press = |pressed| Event::PointerButton {
+            pos,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Default::default(),
+        };
+        run(ctx, renderer, nodes, vec![Event::PointerMoved(pos), press(true)]);
+        run(ctx, renderer, nodes, vec![press(false)]);
+    }
+
+    #[test]
+    fn inline_nodes_are_detected() {
+        assert!(parse("<p>x</p>").iter().all(|node| !is_inline(node)));
+        assert!(parse("text <b>bold <a href=x>link</a></b>").iter().all(is_inline));
+        // An inline element wrapping a block is laid out as a block
+        assert!(!is_inline(&parse("<span><div>block</div></span>")[0]));
+    }
+
+    #[test]
+    fn whitespace_collapses_between_runs() {
+        let renderer = HtmlRenderer::new(create_default_styles(), LinkHandler::new());
+        let mut builder = InlineBuilder::default();
+        builder.collect(&parse("  Hello   <b>bold</b>\n and<i> it </i>."), &renderer, &ComputedStyle::default(), None);
+        assert_eq!(builder.job.text, "Hello bold and it .");
+        assert_eq!(builder.bold, vec![false, false, true, false, false, false, false, false, false]);
+    }
+
+    #[test]
+    fn clicking_a_link_run_follows_it() {
+        let mut renderer = HtmlRenderer::new(create_default_styles(), LinkHandler::new());
+        renderer.base_url = "http://example.com/dir/page.html".to_string();
+        let nodes = parse("Plain words then <a href=\"next.html\">the link</a>");
+        let ctx = Context::default();
+        run(&ctx, &renderer, &nodes, Vec::new());
+
+        // The start of the line is plain text
+        click_at(&ctx, &renderer, &nodes, Pos2::new(3.0, 8.0));
+        assert!(renderer.link_handler.take_link().is_none());
+
+        // The end of the line is the link
+        let text_width = ctx.fonts(|fonts| {
+            fonts.layout_no_wrap("Plain words then the lin".to_string(), FontId::proportional(14.0), Color32::BLACK).size().x
+        });
+        click_at(&ctx, &renderer, &nodes, Pos2::new(text_width, 8.0));
+        let link = renderer.link_handler.take_link().expect("link was clicked");
+        assert_eq!(link.url, "http://example.com/dir/next.html");
+        assert!(!link.new_tab);
+    }
+
+    #[test]
+    fn unsupported_links_are_reported_not_followed() {
+        let renderer = HtmlRenderer::new(create_default_styles(), LinkHandler::new());
+        let nodes = parse("<a href=\"mailto:someone@example.com\">Mail</a>");
+        let ctx = Context::default();
+        run(&ctx, &renderer, &nodes, Vec::new());
+
+        click_at(&ctx, &renderer, &nodes, Pos2::new(5.0, 8.0));
+        assert!(renderer.link_handler.take_link().is_none());
+        assert_eq!(renderer.link_handler.take_unsupported().as_deref(), Some("mailto:someone@example.com"));
+        // Hovering it shows where it goes
+        assert_eq!(renderer.link_handler.take_hovered().as_deref(), Some("mailto:someone@example.com"));
+    }
+
+    #[test]
+    fn clicking_a_label_activates_its_control() {
+        let renderer = HtmlRenderer::new(create_default_styles(), LinkHandler::new());
+        let nodes = parse("<label for=\"agree\">I agree</label>");
+        let ctx = Context::default();
+        run(&ctx, &renderer, &nodes, Vec::new());
+
+        click_at(&ctx, &renderer, &nodes, Pos2::new(3.0, 8.0));
+        assert_eq!(renderer.form_state.borrow().activation, Some(LabelTarget::Id("agree".to_string())));
+    }
+}
@@ -1,69 +1,152 @@
 // src/html_renderer/renderers/lists.rs
-use egui::Ui;
+use egui::{Pos2, Rect, Stroke, Ui, Vec2};
 use crate::html_renderer::renderer::HtmlRenderer;
+use crate::html_renderer::renderers::block::render_block_box;
+use crate::html_renderer::renderers::inline::text_format;
+use crate::html_renderer::text_processor::get_attribute;
+use crate::style::{ComputedStyle, ListStyleType};
 
-// Render list (ordered or unordered)
+// Gap between a marker and its item's content
+const MARKER_GAP: f32 = 6.0;
+
+// Render the items of a list (ordered or unordered). Items are numbered from
+// `start`, counting down when `reversed`; an item's `value` renumbers it and
+// the items after it.
 pub fn render_list(
-    ui: &mut Ui, 
+    ui: &mut Ui,
     element: &html_parser::Element,
     list_type: &str,
     renderer: &HtmlRenderer
 ) {
-    ui.add_space(4.0);
-    
-    match list_type {
-        "ul" => render_unordered_list(ui, &element.children, renderer),
-        "ol" => render_ordered_list(ui, &element.children, renderer),
-        _ => {}
-    }
-    
-    ui.add_space(4.0);
-}
+    let is_item = |node: &&html_parser::Node| {
+        matches!(node, html_parser::Node::Element(child) if child.name.eq_ignore_ascii_case("li"))
+    };
+    let reversed = list_type == "ol" && element.attributes.contains_key("reversed");
+    let default_start = if reversed { element.children.iter().filter(is_item).count() as i64 } else { 1 };
+    let mut ordinal = parse_ordinal(&get_attribute(element, "start", "")).unwrap_or(default_start);
+    let step = if reversed { -1 } else { 1 };
 
-// Render unordered list
-fn render_unordered_list(
-    ui: &mut Ui, 
-    nodes: &[html_parser::Node],
-    renderer: &HtmlRenderer
-) {
-    for node in nodes {
-        if let html_parser::Node::Element(element) = node {
-            if element.name.to_lowercase() == "li" {
-                ui.horizontal(|ui| {
-                    ui.label("• ");
-                    ui.vertical(|ui| {
-                        renderer.render_html_node(ui, &element.children);
-                    });
+    for node in &element.children {
+        match node {
+            html_parser::Node::Element(item) if is_item(&node) => {
+                if let Some(value) = parse_ordinal(&get_attribute(item, "value", "")) {
+                    ordinal = value;
+                }
+                renderer.with_element(item, |style| {
+                    if !style.display_none {
+                        render_block_box(ui, style, renderer, |ui| render_list_item(ui, item, style, ordinal, renderer));
+                    }
                 });
-            } else {
-                // For nested lists or other elements
-                renderer.render_html_node(ui, &[node.clone()]);
+                ordinal = ordinal.saturating_add(step);
             }
+            // Nested lists and stray text outside an item
+            _ => renderer.render_html_node(ui, std::slice::from_ref(node)),
         }
     }
 }
 
-// Render ordered list
-fn render_ordered_list(
-    ui: &mut Ui, 
-    nodes: &[html_parser::Node],
+// Render a list item's contents, with its marker hanging to the left of the
+// first line
+pub fn render_list_item(
+    ui: &mut Ui,
+    element: &html_parser::Element,
+    style: &ComputedStyle,
+    ordinal: i64,
     renderer: &HtmlRenderer
 ) {
-    let mut counter = 1;
-    for node in nodes {
-        if let html_parser::Node::Element(element) = node {
-            if element.name.to_lowercase() == "li" {
-                ui.horizontal(|ui| {
-                    ui.label(format!("{}. ", counter));
-                    counter += 1;
-                    ui.vertical(|ui| {
-                        renderer.render_html_node(ui, &element.children);
-                    });
-                });
-            } else {
-                // For nested lists or other elements
-                renderer.render_html_node(ui, &[node.clone()]);
-            }
+    let top_left = ui.cursor().min;
+    paint_marker(ui, top_left, style, ordinal);
+    renderer.render_html_node(ui, &element.children);
+}
+
+fn paint_marker(ui: &Ui, top_left: Pos2, style: &ComputedStyle, ordinal: i64) {
+    let font = text_format(style).font_id;
+    let row_height = ui.fonts(|fonts| fonts.row_height(&font));
+    // Bullets are drawn rather than typed, so they don't depend on the font
+    // having the glyphs
+    let radius = style.font_size * 0.18;
+    let center = Pos2::new(top_left.x - MARKER_GAP - radius, top_left.y + row_height / 2.0);
+    let painter = ui.painter();
+    match style.list_style_type {
+        ListStyleType::None => {}
+        ListStyleType::Disc => painter.circle_filled(center, radius, style.color),
+        ListStyleType::Circle => painter.circle_stroke(center, radius - 0.5, Stroke::new(1.0, style.color)),
+        ListStyleType::Square => painter.rect_filled(Rect::from_center_size(center, Vec2::splat(radius * 2.0)), 0.0, style.color),
+        list_style => {
+            let text = ordinal_marker(list_style, ordinal);
+            let galley = ui.fonts(|fonts| fonts.layout_no_wrap(text, font, style.color));
+            let pos = Pos2::new(top_left.x - MARKER_GAP - galley.size().x, top_left.y);
+            painter.galley(pos, galley);
         }
     }
 }
+
+// A `start` or `value` attribute, clamped to the 32-bit range browsers keep
+fn parse_ordinal(text: &str) -> Option<i64> {
+    let number: i64 = text.trim().parse().ok()?;
+    Some(number.clamp(i32::MIN.into(), i32::MAX.into()))
+}
+
+// Text of a numbered marker, like "3.", "c." or "iii.". Numbers the style
+// can't write, such as zero in letters, fall back to decimal.
+fn ordinal_marker(list_style: ListStyleType, ordinal: i64) -> String {
+    let number = match list_style {
+        ListStyleType::LowerAlpha if ordinal > 0 => alphabetic(ordinal),
+        ListStyleType::UpperAlpha if ordinal > 0 => alphabetic(ordinal).to_uppercase(),
+        ListStyleType::LowerRoman if (1..4000).contains(&ordinal) => roman(ordinal),
+        ListStyleType::UpperRoman if (1..4000).contains(&ordinal) => roman(ordinal).to_uppercase(),
+        _ => ordinal.to_string(),
+    };
+    format!("{}.", number)
+}
+
+// a, b, ... z, aa, ab, ...
+fn alphabetic(mut ordinal: i64) -> String {
+    let mut letters = Vec::new();
+    while ordinal > 0 {
+        ordinal -= 1;
+        letters.push((b'a' + (ordinal % 26) as u8) as char);
+        ordinal /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+fn roman(mut ordinal: i64) -> String {
+    const NUMERALS: [(i64, &str); 13] = [
+        (1000, "m"), (900, "cm"), (500, "d"), (400, "cd"), (100, "c"), (90, "xc"),
+        (50, "l"), (40, "xl"), (10, "x"), (9, "ix"), (5, "v"), (4, "iv"), (1, "i"),
+    ];
+    let mut text = String::new();
+    for (value, numeral) in NUMERALS {
+        while ordinal >= value {
+            text.push_str(numeral);
+            ordinal -= value;
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_ordinal_markers() {
+        assert_eq!(ordinal_marker(ListStyleType::Decimal, -2), "-2.");
+        assert_eq!(ordinal_marker(ListStyleType::LowerAlpha, 1), "a.");
+        assert_eq!(ordinal_marker(ListStyleType::UpperAlpha, 28), "AB.");
+        assert_eq!(ordinal_marker(ListStyleType::LowerRoman, 1994), "mcmxciv.");
+        assert_eq!(ordinal_marker(ListStyleType::UpperRoman, 4), "IV.");
+        // Out of the style's range
+        assert_eq!(ordinal_marker(ListStyleType::LowerAlpha, 0), "0.");
+        assert_eq!(ordinal_marker(ListStyleType::UpperRoman, 4000), "4000.");
+    }
+
+    #[test]
+    fn clamps_ordinal_attributes() {
+        assert_eq!(parse_ordinal(" 7 "), Some(7));
+        assert_eq!(parse_ordinal("9223372036854775807"), Some(i32::MAX.into()));
+        assert_eq!(parse_ordinal("-9223372036854775808"), Some(i32::MIN.into()));
+        assert_eq!(parse_ordinal("seven"), None);
+    }
+}
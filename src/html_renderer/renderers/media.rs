@@ -3,11 +3,14 @@ use egui::Ui;
 use crate::html_renderer::text_processor::get_attribute;
 use crate::html_renderer::renderer::HtmlRenderer;
 use crate::app::EguiBrowser;
+use crate::html_renderer::style_handler::apply_style;
+use crate::style::ComputedStyle;
 
 // Render image
 pub fn render_image(
     ui: &mut Ui, 
     element: &html_parser::Element,
+    style: &ComputedStyle,
     html_renderer: &HtmlRenderer
 ) {
     let src = get_attribute(element, "src", "");
@@ -15,68 +18,74 @@ pub fn render_image(
     
     if src.is_empty() {
         // No source attribute, show placeholder
-        ui.label("[No image source]");
+        ui.label(apply_style("[No image source]", style));
         return;
     }
     
-    // Check if we have the image in cache
-    if let Some(browser) = html_renderer.get_browser() {
-        if let Some((texture_id, size)) = browser.get_image(&src) {
-            // Calculate a reasonable display size, respecting width/height if specified
-            let width_attr = get_attribute(element, "width", "");
-            let height_attr = get_attribute(element, "height", "");
-            
-            let mut display_size = size;
-            
-            // Apply width constraint if specified
-            if !width_attr.is_empty() {
-                if let Ok(width) = width_attr.parse::<f32>() {
-                    let scale = width / size.x;
-                    display_size = egui::Vec2::new(width, size.y * scale);
-                }
-            }
-            
-            // Apply height constraint if specified
-            if !height_attr.is_empty() {
-                if let Ok(height) = height_attr.parse::<f32>() {
-                    let scale = height / size.y;
-                    display_size = egui::Vec2::new(size.x * scale, height);
-                }
+    // Images are cached under their resolved URL
+    let src = html_renderer.resolve_url(&src);
+    
+    // Check if we have the image in cache, or loaded up front without a browser
+    let browser = html_renderer.get_browser();
+    let loaded = match browser {
+        Some(browser) => browser.get_image(&src),
+        None => html_renderer.images.get(&src).map(|(texture, size)| (texture.id(), *size)),
+    };
+    if let Some((texture_id, size)) = loaded {
+        // Calculate a reasonable display size, respecting width/height if specified
+        let width_attr = get_attribute(element, "width", "");
+        let height_attr = get_attribute(element, "height", "");
+        
+        let mut display_size = size;
+        
+        // Apply width constraint if specified
+        if !width_attr.is_empty() {
+            if let Ok(width) = width_attr.parse::<f32>() {
+                let scale = width / size.x;
+                display_size = egui::Vec2::new(width, size.y * scale);
             }
-            
-            // Limit maximum size to available width
-            let available_width = ui.available_width();
-            if display_size.x > available_width {
-                let scale = available_width / display_size.x;
-                display_size = egui::Vec2::new(available_width, display_size.y * scale);
+        }
+        
+        // Apply height constraint if specified
+        if !height_attr.is_empty() {
+            if let Ok(height) = height_attr.parse::<f32>() {
+                let scale = height / size.y;
+                display_size = egui::Vec2::new(size.x * scale, height);
             }
-            
-            // Display the image
-            ui.add(egui::Image::new((texture_id, display_size)));
+        }
+        
+        // Limit maximum size to available width
+        let available_width = ui.available_width();
+        if display_size.x > available_width {
+            let scale = available_width / display_size.x;
+            display_size = egui::Vec2::new(available_width, display_size.y * scale);
+        }
+        
+        // Display the image
+        ui.add(egui::Image::new((texture_id, display_size)));
+    } else if browser.is_some() {
+        // Automatically request the image to be fetched
+        let browser_ptr = html_renderer.browser.unwrap();
+        unsafe {
+            let browser = &mut *(browser_ptr as *mut EguiBrowser);
+            browser.fetch_image(ui.ctx(), src.clone());
+        }
+        
+        // Show placeholder while loading
+        if !alt.is_empty() {
+            ui.label(apply_style(&format!("[Loading image: {}]", alt), style));
         } else {
-            // Automatically request the image to be fetched
-            let browser_ptr = html_renderer.browser.unwrap();
-            unsafe {
-                let browser = &mut *(browser_ptr as *mut EguiBrowser);
-                browser.fetch_image(ui.ctx(), src.clone());
-            }
-            
-            // Show placeholder while loading
-            if !alt.is_empty() {
-                ui.label(format!("[Loading image: {}]", alt));
-            } else {
-                ui.label("[Loading image...]");
-            }
-            
-            // Request a repaint to check for image load completion
-            ui.ctx().request_repaint();
+            ui.label(apply_style("[Loading image...]", style));
         }
+        
+        // Request a repaint to check for image load completion
+        ui.ctx().request_repaint();
     } else {
         // No browser reference, show placeholder
         if !alt.is_empty() {
-            ui.label(format!("[Image: {}]", alt));
+            ui.label(apply_style(&format!("[Image: {}]", alt), style));
         } else {
-            ui.label("[Image]");
+            ui.label(apply_style("[Image]", style));
         }
     }
 }
@@ -1,5 +1,5 @@
 // Revert to original structure for now to get a successful build
-pub mod text;
+pub mod inline;
 pub mod lists;
 pub mod tables;
 pub mod forms;
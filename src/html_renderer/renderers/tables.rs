@@ -1,103 +1,456 @@
 // src/html_renderer/renderers/tables.rs
-use egui::{RichText, Ui};
+// Table layout. Rows are collected from the table's sections, header rows
+// first and footer rows last, and every cell is placed on a grid of slots
+// honoring colspan and rowspan. Column widths come from the cells' intrinsic
+// widths, then each row's cells are rendered in their columns with the full
+// renderer, and the row is as tall as its tallest cell.
+use egui::{Align, FontId, Layout, Rect, Sense, Shape, Ui, Vec2};
+use crate::html_renderer::layout::{column_widths, table_columns, BlockBox, CellWidths, Column, Intrinsic};
 use crate::html_renderer::renderer::HtmlRenderer;
-use crate::html_renderer::text_processor::get_text_content;
+use crate::html_renderer::renderers::block::{box_shape, render_block_box};
+use crate::html_renderer::renderers::inline::{is_inline, text_format};
+use crate::html_renderer::text_processor::{get_attribute, get_text_content};
+use crate::style::{Border, ComputedStyle, Length, Sides};
 
 // Render table
 pub fn render_table(
-    ui: &mut Ui, 
+    ui: &mut Ui,
     table_element: &html_parser::Element,
-    _renderer: &HtmlRenderer
+    style: &ComputedStyle,
+    renderer: &HtmlRenderer
 ) {
-    ui.add_space(4.0);
-    ui.group(|ui| {
-        // Extract and render table components
-        let (headers, rows) = extract_table_data(table_element);
-        
-        // Render table header
-        if !headers.is_empty() {
-            ui.horizontal(|ui| {
-                for header in headers {
-                    ui.label(RichText::new(header).strong());
+    let grid = TableGrid::new(table_element);
+    let hints = CellHints::new(table_element);
+    let spacing = spacing(style);
+
+    // The table is a block whose width is set by its columns, unless the
+    // style gives it one
+    let outer = BlockBox::layout(style, ui.available_width());
+    let set_width = (style.width != Length::Auto).then_some(outer.content_width);
+    let columns = measure_columns(ui, &grid, &hints, spacing, renderer);
+    let gaps = spacing * (grid.columns + 1) as f32;
+    let widths = column_widths(&columns, outer.content_width - gaps, set_width.map(|width| width - gaps));
+    let table_width = widths.iter().sum::<f32>() + gaps;
+    let table_style = ComputedStyle {
+        width: Length::Px(table_width),
+        ..style.clone()
+    };
+
+    // The caption sits above the table's border box, as wide and as far in
+    if let Some(caption) = grid.caption {
+        let table_box = BlockBox::layout(&table_style, ui.available_width());
+        let border_width = table_box.border_rect(egui::Pos2::ZERO, 0.0).width();
+        renderer.with_element(caption, |caption_style| {
+            let caption_style = ComputedStyle {
+                width: Length::Px(border_width),
+                margin: Sides { left: Length::Px(table_box.margin.left), ..caption_style.margin },
+                ..caption_style.clone()
+            };
+            render_block_box(ui, &caption_style, renderer, |ui| renderer.render_html_node(ui, &caption.children));
+        });
+    }
+    render_block_box(ui, &table_style, renderer, |ui| {
+        render_rows(ui, &grid, &hints, &widths, spacing, renderer);
+    });
+}
+
+// Gap between cells; collapsed borders have none
+fn spacing(style: &ComputedStyle) -> f32 {
+    if style.border_collapse { 0.0 } else { style.border_spacing }
+}
+
+struct TableRow<'a> {
+    element: &'a html_parser::Element,
+    // The thead, tbody or tfoot the row is in, if any
+    section: Option<&'a html_parser::Element>,
+}
+
+struct TableCell<'a> {
+    element: &'a html_parser::Element,
+    row: usize,
+    column: usize,
+    colspan: usize,
+    rowspan: usize,
+}
+
+struct TableGrid<'a> {
+    caption: Option<&'a html_parser::Element>,
+    rows: Vec<TableRow<'a>>,
+    // In row order, then column order
+    cells: Vec<TableCell<'a>>,
+    columns: usize,
+}
+
+impl<'a> TableGrid<'a> {
+    fn new(table: &'a html_parser::Element) -> Self {
+        let mut caption = None;
+        let (mut head, mut body, mut foot) = (Vec::new(), Vec::new(), Vec::new());
+        for child in child_elements(table) {
+            match child.name.to_lowercase().as_str() {
+                "caption" => {
+                    caption.get_or_insert(child);
                 }
-            });
-            ui.separator();
-        }
-        
-        // Render table rows
-        for row in rows {
-            ui.horizontal(|ui| {
-                for cell in row {
-                    ui.label(cell);
+                "tr" => body.push(TableRow { element: child, section: None }),
+                section @ ("thead" | "tbody" | "tfoot") => {
+                    let rows = child_elements(child)
+                        .filter(|row| row.name.eq_ignore_ascii_case("tr"))
+                        .map(|row| TableRow { element: row, section: Some(child) });
+                    match section {
+                        "thead" => head.extend(rows),
+                        "tbody" => body.extend(rows),
+                        _ => foot.extend(rows),
+                    }
                 }
-            });
+                _ => {}
+            }
         }
-    });
-    ui.add_space(4.0);
-}
-
-// Extract table data
-fn extract_table_data(
-    table_element: &html_parser::Element
-) -> (Vec<String>, Vec<Vec<String>>) {
-    let mut headers = Vec::new();
-    let mut rows = Vec::new();
-    
-    for node in &table_element.children {
-        if let html_parser::Node::Element(element) = node {
-            match element.name.to_lowercase().as_str() {
-                "thead" => {
-                    for thead_child in &element.children {
-                        if let html_parser::Node::Element(tr_element) = thead_child {
-                            if tr_element.name.to_lowercase() == "tr" {
-                                for th_node in &tr_element.children {
-                                    if let html_parser::Node::Element(th_element) = th_node {
-                                        if th_element.name.to_lowercase() == "th" {
-                                            headers.push(get_text_content(&th_element.children));
-                                        }
-                                    }
-                                }
-                            }
-                        }
+        let rows: Vec<TableRow> = head.into_iter().chain(body).chain(foot).collect();
+
+        // Slots already taken by cells spanning down from earlier rows
+        let mut occupied: Vec<Vec<bool>> = vec![Vec::new(); rows.len()];
+        let mut cells = Vec::new();
+        for (row_index, row) in rows.iter().enumerate() {
+            let mut column = 0;
+            for cell in child_elements(row.element).filter(|cell| is_cell(cell)) {
+                while occupied[row_index].get(column).copied().unwrap_or(false) {
+                    column += 1;
+                }
+                let colspan = span_attribute(cell, "colspan", 1).clamp(1, 1000);
+                // rowspan="0" reaches the last row
+                let remaining = rows.len() - row_index;
+                let rowspan = match span_attribute(cell, "rowspan", 1) {
+                    0 => remaining,
+                    rowspan => rowspan.min(remaining),
+                };
+                for slots in &mut occupied[row_index..row_index + rowspan] {
+                    if slots.len() < column + colspan {
+                        slots.resize(column + colspan, false);
                     }
+                    slots[column..column + colspan].fill(true);
                 }
-                "tbody" => {
-                    for tbody_child in &element.children {
-                        if let html_parser::Node::Element(tr_element) = tbody_child {
-                            if tr_element.name.to_lowercase() == "tr" {
-                                let mut row = Vec::new();
-                                for td_node in &tr_element.children {
-                                    if let html_parser::Node::Element(td_element) = td_node {
-                                        if td_element.name.to_lowercase() == "td" {
-                                            row.push(get_text_content(&td_element.children));
-                                        }
-                                    }
-                                }
-                                if !row.is_empty() {
-                                    rows.push(row);
-                                }
-                            }
-                        }
+                cells.push(TableCell { element: cell, row: row_index, column, colspan, rowspan });
+                column += colspan;
+            }
+        }
+        let columns = occupied.iter().map(Vec::len).max().unwrap_or(0);
+
+        Self { caption, rows, cells, columns }
+    }
+}
+
+fn child_elements(element: &html_parser::Element) -> impl Iterator<Item = &html_parser::Element> {
+    element.children.iter().filter_map(|node| match node {
+        html_parser::Node::Element(child) => Some(child),
+        _ => None,
+    })
+}
+
+fn is_cell(element: &html_parser::Element) -> bool {
+    element.name.eq_ignore_ascii_case("td") || element.name.eq_ignore_ascii_case("th")
+}
+
+fn span_attribute(element: &html_parser::Element, name: &str, default: usize) -> usize {
+    get_attribute(element, name, "").trim().parse().unwrap_or(default)
+}
+
+// What the table's border and cellpadding attributes ask of its cells
+struct CellHints {
+    border: bool,
+    padding: Option<f32>,
+}
+
+impl CellHints {
+    fn new(table: &html_parser::Element) -> Self {
+        // A bare `border` attribute counts
+        let border = table
+            .attributes
+            .get("border")
+            .is_some_and(|border| !matches!(border.as_deref().unwrap_or("").trim().parse::<f32>(), Ok(width) if width <= 0.0));
+        let padding = get_attribute(table, "cellpadding", "").trim().parse::<f32>().ok();
+        Self { border, padding }
+    }
+
+    // The attributes only fill in what the cell's style leaves at its defaults
+    fn apply(&self, style: &ComputedStyle) -> ComputedStyle {
+        let mut style = style.clone();
+        if let Some(padding) = self.padding {
+            if style.padding == Sides::all(Length::Px(1.0)) {
+                style.padding = Sides::all(Length::Px(padding));
+            }
+        }
+        let borders = [style.border.top, style.border.right, style.border.bottom, style.border.left];
+        if self.border && borders.iter().all(|border| border.width == 0.0) {
+            style.border = Sides::all(Border { width: 1.0, color: egui::Color32::from_gray(128) });
+        }
+        // Cells fill their columns
+        style.width = Length::Auto;
+        style.max_width = Length::Auto;
+        style.margin = Sides::all(Length::Px(0.0));
+        style
+    }
+}
+
+// Run `render` inside a row's section and row elements, so the cells inherit
+// from them and selectors like `thead th` match
+fn with_row<R>(renderer: &HtmlRenderer, row: &TableRow, render: impl FnOnce(&ComputedStyle) -> R) -> R {
+    match row.section {
+        Some(section) => renderer.with_element(section, |_| renderer.with_element(row.element, render)),
+        None => renderer.with_element(row.element, render),
+    }
+}
+
+// Intrinsic widths of every column
+fn measure_columns(ui: &Ui, grid: &TableGrid, hints: &CellHints, spacing: f32, renderer: &HtmlRenderer) -> Vec<Column> {
+    let mut cells = Vec::new();
+    for (row_index, row) in grid.rows.iter().enumerate() {
+        with_row(renderer, row, |_| {
+            for cell in grid.cells.iter().filter(|cell| cell.row == row_index) {
+                let (widths, fixed) = renderer.with_element(cell.element, |style| {
+                    let block = BlockBox::layout(&hints.apply(style), 0.0);
+                    let edges = block.border.left + block.padding.left + block.padding.right + block.border.right;
+                    let mut widths = measure_nodes(ui, &cell.element.children, renderer);
+                    // A set width fixes the column, though never below its content
+                    let fixed = matches!(style.width, Length::Px(_));
+                    if let Length::Px(width) = style.width {
+                        widths = Intrinsic::fixed(widths.min.max(width));
                     }
+                    // Spanning cells also cover the spacing between their columns
+                    let spanned_spacing = spacing * (cell.colspan - 1) as f32;
+                    let widths = Intrinsic {
+                        min: (widths.min + edges - spanned_spacing).max(0.0),
+                        max: (widths.max + edges - spanned_spacing).max(0.0),
+                    };
+                    (widths, fixed)
+                });
+                cells.push(CellWidths { column: cell.column, span: cell.colspan, widths, fixed });
+            }
+        });
+    }
+    table_columns(grid.columns, &cells)
+}
+
+// Estimate the intrinsic widths of content without laying it out: text is
+// measured word by word in its font, images and controls count as their
+// size, and nested tables as the sum of their columns
+fn measure_nodes(ui: &Ui, nodes: &[html_parser::Node], renderer: &HtmlRenderer) -> Intrinsic {
+    let mut measure = Intrinsic::default();
+    let mut line = Intrinsic::default();
+    for node in nodes {
+        match node {
+            html_parser::Node::Text(text) => {
+                let font = text_format(&renderer.current_style()).font_id;
+                let words: Vec<&str> = text.split_whitespace().collect();
+                for word in &words {
+                    line.min = line.min.max(text_width(ui, word, &font));
+                }
+                let mut collapsed = words.join(" ");
+                if text.starts_with(char::is_whitespace) && line.max > 0.0 {
+                    collapsed.insert(0, ' ');
                 }
-                "tr" => {
-                    // Handle direct tr children (when no thead/tbody)
-                    let mut row = Vec::new();
-                    for td_node in &element.children {
-                        if let html_parser::Node::Element(td_element) = td_node {
-                            if td_element.name.to_lowercase() == "td" || td_element.name.to_lowercase() == "th" {
-                                row.push(get_text_content(&td_element.children));
-                            }
+                line.max += text_width(ui, &collapsed, &font);
+            }
+            html_parser::Node::Element(element) => {
+                let tag = element.name.to_lowercase();
+                renderer.with_element(element, |style| {
+                    if style.display_none {
+                        return;
+                    }
+                    match tag.as_str() {
+                        "br" => {
+                            measure.stack(line);
+                            line = Intrinsic::default();
+                        }
+                        "img" => line.add(Intrinsic::fixed(image_width(element, renderer))),
+                        "input" | "textarea" | "select" | "button" => {
+                            line.add(Intrinsic::fixed(control_width(ui, element, &tag, style)));
+                        }
+                        _ if is_inline(node) => line.add(measure_nodes(ui, &element.children, renderer)),
+                        _ => {
+                            measure.stack(line);
+                            line = Intrinsic::default();
+                            let inner = if tag == "table" {
+                                measure_table(ui, element, style, renderer)
+                            } else {
+                                measure_nodes(ui, &element.children, renderer)
+                            };
+                            let block = BlockBox::layout(style, 0.0);
+                            let edges = block.margin.left.max(0.0)
+                                + block.border.left
+                                + block.padding.left
+                                + block.padding.right
+                                + block.border.right
+                                + block.margin.right.max(0.0);
+                            measure.stack(Intrinsic { min: inner.min + edges, max: inner.max + edges });
                         }
                     }
-                    if !row.is_empty() {
-                        rows.push(row);
+                });
+            }
+            html_parser::Node::Comment(_) => {}
+        }
+    }
+    measure.stack(line);
+    measure
+}
+
+fn measure_table(ui: &Ui, table: &html_parser::Element, style: &ComputedStyle, renderer: &HtmlRenderer) -> Intrinsic {
+    let grid = TableGrid::new(table);
+    let spacing = spacing(style);
+    let columns = measure_columns(ui, &grid, &CellHints::new(table), spacing, renderer);
+    let gaps = spacing * (grid.columns + 1) as f32;
+    Intrinsic {
+        min: columns.iter().map(|column| column.widths.min).sum::<f32>() + gaps,
+        max: columns.iter().map(|column| column.widths.max).sum::<f32>() + gaps,
+    }
+}
+
+fn text_width(ui: &Ui, text: &str, font: &FontId) -> f32 {
+    ui.fonts(|fonts| fonts.layout_no_wrap(text.to_string(), font.clone(), egui::Color32::BLACK).size().x)
+}
+
+// The width attribute, or the loaded image's own width
+fn image_width(element: &html_parser::Element, renderer: &HtmlRenderer) -> f32 {
+    if let Ok(width) = get_attribute(element, "width", "").trim().parse::<f32>() {
+        return width;
+    }
+    let src = get_attribute(element, "src", "");
+    renderer
+        .get_browser()
+        .filter(|_| !src.is_empty())
+        .and_then(|browser| browser.get_image(&renderer.resolve_url(&src)))
+        .map_or(0.0, |(_, size)| size.x)
+}
+
+// Rough widths of form controls as the form renderer draws them
+fn control_width(ui: &Ui, element: &html_parser::Element, tag: &str, style: &ComputedStyle) -> f32 {
+    let font = text_format(style).font_id;
+    let button = |label: &str| text_width(ui, label, &font) + 2.0 * ui.spacing().button_padding.x;
+    let input_type = get_attribute(element, "type", "text").to_lowercase();
+    match (tag, input_type.as_str()) {
+        ("button", _) => button(&get_text_content(&element.children)),
+        ("input", "button" | "submit") => button(&get_attribute(element, "value", "Submit")),
+        ("input", "checkbox" | "radio") => ui.spacing().interact_size.y,
+        ("input", "hidden") => 0.0,
+        ("textarea", _) => ui.spacing().text_edit_width,
+        _ => 200.0,
+    }
+}
+
+// A cell being laid out: where its background goes and how tall it needs to be
+struct PlacedCell {
+    background: egui::layers::ShapeIdx,
+    style: ComputedStyle,
+    block: BlockBox,
+    height: f32,
+}
+
+fn render_rows(
+    ui: &mut Ui,
+    grid: &TableGrid,
+    hints: &CellHints,
+    widths: &[f32],
+    spacing: f32,
+    renderer: &HtmlRenderer,
+) {
+    let origin = ui.cursor().min;
+    let mut column_x = Vec::with_capacity(widths.len() + 1);
+    let mut x = spacing;
+    for width in widths {
+        column_x.push(x);
+        x += width + spacing;
+    }
+    column_x.push(x);
+    let cell_width = |cell: &TableCell| column_x[cell.column + cell.colspan] - spacing - column_x[cell.column];
+
+    let mut row_top = Vec::with_capacity(grid.rows.len() + 1);
+    let mut row_height: Vec<f32> = vec![0.0; grid.rows.len()];
+    let mut placed = Vec::with_capacity(grid.cells.len());
+    let mut y = spacing;
+    for (row_index, row) in grid.rows.iter().enumerate() {
+        row_top.push(y);
+        with_row(renderer, row, |row_style| {
+            for cell in grid.cells.iter().filter(|cell| cell.row == row_index) {
+                renderer.with_element(cell.element, |style| {
+                    // Cells show the row's background where they have none
+                    let mut style = hints.apply(style);
+                    style.background_color = style.background_color.or(row_style.background_color);
+                    let block = BlockBox::layout(&style, cell_width(cell));
+                    let background = ui.painter().add(Shape::Noop);
+                    let content_min = origin + Vec2::new(column_x[cell.column], y) + block.content_offset();
+                    let mut content_ui = ui.child_ui(
+                        Rect::from_min_size(content_min, Vec2::new(block.content_width, f32::INFINITY)),
+                        Layout::top_down(Align::Min),
+                    );
+                    renderer.render_html_node(&mut content_ui, &cell.element.children);
+                    renderer.flush_margin(&mut content_ui);
+                    let content_height = (content_ui.min_rect().bottom() - content_min.y).max(0.0);
+                    let height = block.border_rect(content_min, content_height).height();
+                    if cell.rowspan == 1 {
+                        row_height[row_index] = row_height[row_index].max(height);
                     }
-                }
-                _ => {}
+                    placed.push(PlacedCell { background, style, block, height });
+                });
+            }
+        });
+
+        // Cells spanning down to this row make it taller if they need to be
+        for (cell, placed) in grid.cells.iter().zip(&placed) {
+            if cell.rowspan > 1 && cell.row + cell.rowspan - 1 == row_index {
+                let spanned: f32 = row_height[cell.row..row_index].iter().map(|height| height + spacing).sum();
+                row_height[row_index] = row_height[row_index].max(placed.height - spanned);
             }
         }
+        y += row_height[row_index] + spacing;
+    }
+    row_top.push(y);
+
+    // Every cell fills the rows it spans
+    for (cell, placed) in grid.cells.iter().zip(placed) {
+        let min = origin + Vec2::new(column_x[cell.column], row_top[cell.row]);
+        let bottom = row_top[cell.row + cell.rowspan] - spacing;
+        let rect = Rect::from_min_size(min, Vec2::new(cell_width(cell), bottom - row_top[cell.row]));
+        ui.painter().set(placed.background, box_shape(rect, &placed.style, &placed.block));
+    }
+
+    let size = Vec2::new(column_x[widths.len()], if grid.rows.is_empty() { 0.0 } else { y });
+    ui.allocate_rect(Rect::from_min_size(origin, size), Sense::hover());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_table(html: &str) -> html_parser::Element {
+        match html_parser::Dom::parse(html).unwrap().children.remove(0) {
+            html_parser::Node::Element(table) => table,
+            _ => panic!("expected a table"),
+        }
+    }
+
+    #[test]
+    fn places_cells_around_spans() {
+        let table = parse_table(
+            "<table><tr><td rowspan=2>a</td><td colspan=2>b</td></tr>\
+             <tr><td>c</td><td>d</td><td>e</td></tr></table>",
+        );
+        let grid = TableGrid::new(&table);
+        let slots: Vec<(usize, usize, usize, usize)> =
+            grid.cells.iter().map(|cell| (cell.row, cell.column, cell.colspan, cell.rowspan)).collect();
+        assert_eq!(slots, vec![(0, 0, 1, 2), (0, 1, 2, 1), (1, 1, 1, 1), (1, 2, 1, 1), (1, 3, 1, 1)]);
+        assert_eq!(grid.columns, 4);
+    }
+
+    #[test]
+    fn orders_sections_with_the_footer_last() {
+        let table = parse_table(
+            "<table><caption>Totals</caption><tfoot><tr><th>sum</th></tr></tfoot>\
+             <tbody><tr><td>1</td></tr></tbody><thead><tr><th>n</th></tr></thead></table>",
+        );
+        let grid = TableGrid::new(&table);
+        assert!(grid.caption.is_some());
+        let sections: Vec<String> = grid.rows.iter().map(|row| row.section.unwrap().name.clone()).collect();
+        assert_eq!(sections, vec!["thead", "tbody", "tfoot"]);
+        // rowspan=0 runs to the end of the table
+        let table = parse_table("<table><tr><td rowspan=0>a</td></tr><tr></tr><tr></tr></table>");
+        assert_eq!(TableGrid::new(&table).cells[0].rowspan, 3);
     }
-    
-    (headers, rows)
 }
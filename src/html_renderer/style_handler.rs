@@ -1,29 +1,29 @@
 // src/html_renderer/style_handler.rs
-use crate::style::ElementStyle;
-use egui::{Color32, RichText};
+use crate::style::ComputedStyle;
+use egui::RichText;
 
-// Apply styling to RichText based on ElementStyle
-pub fn apply_style(text: &str, style: Option<&ElementStyle>) -> RichText {
-    let mut rich_text = RichText::new(text);
+// Apply a computed style to a run of text
+pub fn apply_style(text: &str, style: &ComputedStyle) -> RichText {
+    let mut rich_text = RichText::new(text)
+        .color(style.color)
+        .size(style.font_size);
     
-    if let Some(style) = style {
-        if let Some(color) = style.color {
-            rich_text = rich_text.color(color);
-        }
-        if let Some(size) = style.font_size {
-            rich_text = rich_text.size(size);
-        }
-        if let Some(_weight) = style.font_weight {
-            rich_text = rich_text.strong();
-        }
+    // egui has no bold faces, so bold weights use the strong text style
+    if style.is_bold() {
+        rich_text = rich_text.strong();
+    }
+    if style.italic {
+        rich_text = rich_text.italics();
+    }
+    if style.underline {
+        rich_text = rich_text.underline();
+    }
+    if style.strikethrough {
+        rich_text = rich_text.strikethrough();
+    }
+    if let Some(background) = style.background_color {
+        rich_text = rich_text.background_color(background);
     }
     
     rich_text
 }
-
-// Get link styling
-pub fn get_link_style(text: &str) -> RichText {
-    RichText::new(text)
-        .color(Color32::from_rgb(0, 102, 204))
-        .underline()
-}
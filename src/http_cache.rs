@@ -0,0 +1,523 @@
+// src/http_cache.rs
+// The on-disk HTTP cache, a private cache as described in RFC 9111. Each
+// response is one file in the cache directory: a text head with the URL,
+// status, timing, Vary values and headers, a blank line, then the body. The
+// file's modification time records when the entry was last used, so the least
+// recently used entries go first when the cache outgrows its size cap.
+use crate::cookies::parse_cookie_date;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+// Size cap of the profile's cache
+pub const DEFAULT_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+// Statuses that may be cached without explicit freshness (RFC 9110 section 15.1)
+const HEURISTIC_STATUSES: [u16; 11] = [200, 203, 204, 300, 301, 308, 404, 405, 410, 414, 501];
+
+// Headers a 304 must not change in the stored response (RFC 9111 section 3.2)
+const KEPT_ON_UPDATE: [&str; 3] = ["content-length", "content-encoding", "transfer-encoding"];
+
+// Headers never written to disk: cookies belong to the jar, which may drop
+// them long before the entry goes, and hop-by-hop headers describe only the
+// connection they came on (RFC 9111 section 3.1)
+const NOT_STORED: [&str; 10] = [
+    "set-cookie",
+    "set-cookie2",
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authentication-info",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+pub type SharedHttpCache = Arc<Mutex<HttpCache>>;
+
+// Head of a stored response
+#[derive(Clone, Debug, PartialEq)]
+struct Entry {
+    url: String,
+    status: u16,
+    status_text: String,
+    // Unix times the request was sent and the response received, for the age
+    request_time: i64,
+    response_time: i64,
+    // Request headers named by Vary, as sent with the stored response
+    vary: Vec<(String, String)>,
+    headers: BTreeMap<String, String>,
+    // Bytes on disk, head included
+    size: u64,
+    last_used: i64,
+}
+
+// A stored response that matches a request
+pub struct CacheHit {
+    pub response: ehttp::Response,
+    // Still fresh, so usable without asking the server
+    pub fresh: bool,
+}
+
+pub struct HttpCache {
+    dir: PathBuf,
+    max_size: u64,
+    // By cache key: the URL without its fragment
+    entries: HashMap<String, Entry>,
+    total_size: u64,
+}
+
+impl HttpCache {
+    // The cache in `dir`, with whatever an earlier run left there
+    pub fn open(dir: PathBuf, max_size: u64) -> Self {
+        let _ = std::fs::create_dir_all(&dir);
+        let mut cache = Self {
+            dir,
+            max_size,
+            entries: HashMap::new(),
+            total_size: 0,
+        };
+        let files = std::fs::read_dir(&cache.dir).into_iter().flatten().flatten();
+        for file in files.filter(|file| file.path().extension().is_some_and(|ext| ext == "entry")) {
+            let last_used = file.metadata().ok().and_then(|metadata| metadata.modified().ok());
+            match read_head(&file.path()) {
+                Some(mut entry) => {
+                    entry.last_used = last_used.map_or(0, unix_time);
+                    cache.total_size += entry.size;
+                    cache.entries.insert(cache_key(&entry.url), entry);
+                }
+                // Unreadable or from another format: not worth keeping
+                None => {
+                    let _ = std::fs::remove_file(file.path());
+                }
+            }
+        }
+        cache
+    }
+
+    pub fn shared(self) -> SharedHttpCache {
+        Arc::new(Mutex::new(self))
+    }
+
+    // The stored response for a GET of `url` sent with `request_headers`, if any
+    pub fn lookup(&mut self, url: &str, request_headers: &[(&str, String)], now: i64) -> Option<CacheHit> {
+        let key = cache_key(url);
+        let entry = self.entries.get(&key)?;
+        let varies = entry
+            .vary
+            .iter()
+            .any(|(name, value)| header_value(request_headers, name) != *value);
+        if varies {
+            return None;
+        }
+        let Some(body) = read_body(&self.path(&key)) else {
+            self.remove(&key);
+            return None;
+        };
+
+        let fresh = is_fresh(entry, now);
+        let response = ehttp::Response {
+            url: entry.url.clone(),
+            ok: (200..300).contains(&entry.status),
+            status: entry.status,
+            status_text: entry.status_text.clone(),
+            headers: entry.headers.clone(),
+            bytes: body,
+        };
+        self.touch(&key, now);
+        Some(CacheHit { response, fresh })
+    }
+
+    // Keep a response to a GET, if RFC 9111 section 3 allows storing it
+    pub fn store(&mut self, request_headers: &[(&str, String)], response: &ehttp::Response, request_time: i64, response_time: i64) {
+        let key = cache_key(&response.url);
+        let directives = cache_control(&response.headers);
+        let vary_names: Vec<String> = response
+            .headers
+            .get("vary")
+            .map(|vary| vary.split(',').map(|name| name.trim().to_ascii_lowercase()).collect())
+            .unwrap_or_default();
+        let explicit = directives.contains_key("max-age") || directives.contains_key("public") || response.headers.contains_key("expires");
+        let storable = !directives.contains_key("no-store")
+            && !vary_names.iter().any(|name| name == "*")
+            && (explicit || HEURISTIC_STATUSES.contains(&response.status))
+            && !(100..200).contains(&response.status)
+            && !matches!(response.status, 206 | 304);
+        if !storable {
+            self.remove(&key);
+            return;
+        }
+
+        let mut entry = Entry {
+            url: response.url.clone(),
+            status: response.status,
+            status_text: response.status_text.clone(),
+            request_time,
+            response_time,
+            vary: vary_names
+                .into_iter()
+                .filter(|name| !name.is_empty())
+                .map(|name| {
+                    let value = header_value(request_headers, &name);
+                    (name, value)
+                })
+                .collect(),
+            headers: response
+                .headers
+                .iter()
+                .filter(|(name, _)| !NOT_STORED.contains(&name.as_str()))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            size: 0,
+            last_used: response_time,
+        };
+        self.write(&key, &mut entry, &response.bytes);
+    }
+
+    // Refresh a stored response with a 304 Not Modified, returning it as it
+    // now stands (RFC 9111 section 4.3.4)
+    pub fn revalidated(&mut self, url: &str, not_modified: &ehttp::Response, request_time: i64, response_time: i64) -> Option<ehttp::Response> {
+        let key = cache_key(url);
+        let mut entry = self.entries.get(&key)?.clone();
+        let body = read_body(&self.path(&key))?;
+        for (name, value) in &not_modified.headers {
+            if !KEPT_ON_UPDATE.contains(&name.as_str()) && !NOT_STORED.contains(&name.as_str()) {
+                entry.headers.insert(name.clone(), value.clone());
+            }
+        }
+        entry.request_time = request_time;
+        entry.response_time = response_time;
+        entry.last_used = response_time;
+        self.write(&key, &mut entry, &body);
+        Some(ehttp::Response {
+            url: entry.url,
+            ok: (200..300).contains(&entry.status),
+            status: entry.status,
+            status_text: entry.status_text,
+            headers: entry.headers,
+            bytes: body,
+        })
+    }
+
+    // Drop the stored response for a URL, e.g. after a POST to it changed it
+    pub fn invalidate(&mut self, url: &str) {
+        self.remove(&cache_key(url));
+    }
+
+    fn write(&mut self, key: &str, entry: &mut Entry, body: &[u8]) {
+        let mut bytes = format!(
+            "url {}\nstatus {} {}\ntime {} {}\n",
+            entry.url, entry.status, entry.status_text, entry.request_time, entry.response_time
+        );
+        for (name, value) in &entry.vary {
+            bytes.push_str(&format!("vary {} {}\n", name, value));
+        }
+        for (name, value) in &entry.headers {
+            bytes.push_str(&format!("header {} {}\n", name, value));
+        }
+        bytes.push('\n');
+        let mut bytes = bytes.into_bytes();
+        bytes.extend_from_slice(body);
+        entry.size = bytes.len() as u64;
+
+        // Anything taking more than an eighth of the cache would push out too much
+        self.remove(key);
+        if entry.size > self.max_size / 8 {
+            return;
+        }
+        let path = self.path(key);
+        let temporary = path.with_extension("tmp");
+        if std::fs::write(&temporary, &bytes).is_err() || std::fs::rename(&temporary, &path).is_err() {
+            return;
+        }
+        self.total_size += entry.size;
+        self.entries.insert(key.to_string(), entry.clone());
+        self.touch(key, entry.last_used);
+        self.evict();
+    }
+
+    // Remove least recently used entries until the cache fits its cap
+    fn evict(&mut self) {
+        if self.total_size <= self.max_size {
+            return;
+        }
+        let mut by_use: Vec<(i64, String)> = self.entries.iter().map(|(key, entry)| (entry.last_used, key.clone())).collect();
+        by_use.sort();
+        for (_, key) in by_use {
+            if self.total_size <= self.max_size {
+                break;
+            }
+            self.remove(&key);
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.total_size -= entry.size;
+            let _ = std::fs::remove_file(self.path(key));
+        }
+    }
+
+    // Record a use, in memory and as the file's modification time
+    fn touch(&mut self, key: &str, now: i64) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.last_used = now;
+            if let Ok(file) = std::fs::File::options().append(true).open(self.path(key)) {
+                let _ = file.set_modified(UNIX_EPOCH + Duration::from_secs(now.max(0) as u64));
+            }
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.entry", fnv1a(key.as_bytes())))
+    }
+}
+
+// Validators for a conditional request that revalidates a stored response
+pub fn validators(response: &ehttp::Response) -> Vec<(&'static str, String)> {
+    let mut validators = Vec::new();
+    if let Some(etag) = response.headers.get("etag") {
+        validators.push(("If-None-Match", etag.clone()));
+    }
+    if let Some(last_modified) = response.headers.get("last-modified") {
+        validators.push(("If-Modified-Since", last_modified.clone()));
+    }
+    validators
+}
+
+// Cache-Control directives by lower-case name (RFC 9111 section 5.2)
+fn cache_control(headers: &BTreeMap<String, String>) -> HashMap<String, Option<String>> {
+    let Some(value) = headers.get("cache-control") else {
+        return HashMap::new();
+    };
+    value
+        .split(',')
+        .filter(|directive| !directive.trim().is_empty())
+        .map(|directive| match directive.split_once('=') {
+            Some((name, argument)) => (name.trim().to_ascii_lowercase(), Some(argument.trim().trim_matches('"').to_string())),
+            None => (directive.trim().to_ascii_lowercase(), None),
+        })
+        .collect()
+}
+
+// How long a response stays fresh (RFC 9111 section 4.2.1), falling back to
+// the usual 10% of the time since Last-Modified
+fn freshness_lifetime(entry: &Entry) -> i64 {
+    let directives = cache_control(&entry.headers);
+    if directives.contains_key("no-cache") {
+        return 0;
+    }
+    if let Some(Some(max_age)) = directives.get("max-age") {
+        return max_age.parse().unwrap_or(0);
+    }
+    let date = entry.headers.get("date").and_then(|date| parse_cookie_date(date)).unwrap_or(entry.response_time);
+    if let Some(expires) = entry.headers.get("expires") {
+        // An invalid Expires, like "0", means already expired
+        return parse_cookie_date(expires).map_or(0, |expires| expires.saturating_sub(date));
+    }
+    match entry.headers.get("last-modified").and_then(|modified| parse_cookie_date(modified)) {
+        Some(modified) if HEURISTIC_STATUSES.contains(&entry.status) => date.saturating_sub(modified).max(0) / 10,
+        _ => 0,
+    }
+}
+
+// Age of the response now (RFC 9111 section 4.2.3). Header values come from
+// the server, so the sums saturate rather than overflow
+fn current_age(entry: &Entry, now: i64) -> i64 {
+    let date = entry.headers.get("date").and_then(|date| parse_cookie_date(date)).unwrap_or(entry.response_time);
+    let age = entry.headers.get("age").and_then(|age| age.trim().parse::<i64>().ok()).unwrap_or(0).max(0);
+    let apparent_age = entry.response_time.saturating_sub(date).max(0);
+    let corrected_age = age.saturating_add(entry.response_time.saturating_sub(entry.request_time));
+    apparent_age.max(corrected_age).saturating_add(now.saturating_sub(entry.response_time))
+}
+
+fn is_fresh(entry: &Entry, now: i64) -> bool {
+    freshness_lifetime(entry) > current_age(entry, now)
+}
+
+fn cache_key(url: &str) -> String {
+    url.split('#').next().unwrap_or(url).to_string()
+}
+
+fn header_value(headers: &[(&str, String)], name: &str) -> String {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.clone())
+        .unwrap_or_default()
+}
+
+// File names come from a hash of the key that stays the same between runs
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+fn unix_time(time: std::time::SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+// Split an entry file at the blank line that ends its head
+fn split_entry(bytes: &[u8]) -> Option<(&str, &[u8])> {
+    let end = bytes.windows(2).position(|pair| pair == b"\n\n")?;
+    Some((std::str::from_utf8(&bytes[..end]).ok()?, &bytes[end + 2..]))
+}
+
+fn read_head(path: &std::path::Path) -> Option<Entry> {
+    let bytes = std::fs::read(path).ok()?;
+    let (head, _) = split_entry(&bytes)?;
+    let mut entry = Entry {
+        url: String::new(),
+        status: 0,
+        status_text: String::new(),
+        request_time: 0,
+        response_time: 0,
+        vary: Vec::new(),
+        headers: BTreeMap::new(),
+        size: bytes.len() as u64,
+        last_used: 0,
+    };
+    for line in head.lines() {
+        let (field, value) = line.split_once(' ').unwrap_or((line, ""));
+        match field {
+            "url" => entry.url = value.to_string(),
+            "status" => {
+                let (status, text) = value.split_once(' ').unwrap_or((value, ""));
+                entry.status = status.parse().ok()?;
+                entry.status_text = text.to_string();
+            }
+            "time" => {
+                let (request, response) = value.split_once(' ')?;
+                entry.request_time = request.parse().ok()?;
+                entry.response_time = response.parse().ok()?;
+            }
+            "vary" | "header" => {
+                let (name, value) = value.split_once(' ').unwrap_or((value, ""));
+                if field == "vary" {
+                    entry.vary.push((name.to_string(), value.to_string()));
+                } else {
+                    entry.headers.insert(name.to_string(), value.to_string());
+                }
+            }
+            _ => return None,
+        }
+    }
+    (!entry.url.is_empty() && entry.status != 0).then_some(entry)
+}
+
+fn read_body(path: &std::path::Path) -> Option<Vec<u8>> {
+    let bytes = std::fs::read(path).ok()?;
+    split_entry(&bytes).map(|(_, body)| body.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn temporary_cache(name: &str, max_size: u64) -> HttpCache {
+        let dir = std::env::temp_dir().join(format!("http_cache_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        HttpCache::open(dir, max_size)
+    }
+
+    fn response(url: &str, headers: &[(&str, &str)], body: &str) -> ehttp::Response {
+        ehttp::Response {
+            url: url.to_string(),
+            ok: true,
+            status: 200,
+            status_text: "OK".to_string(),
+            headers: headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            bytes: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn computes_freshness() {
+        let mut cache = temporary_cache("freshness", DEFAULT_MAX_SIZE);
+        let fresh_for = |cache: &mut HttpCache, url: &str, now: i64| cache.lookup(url, &[], now).map(|hit| hit.fresh);
+
+        cache.store(&[], &response("http://a.test/max-age", &[("cache-control", "max-age=60"), ("age", "10")], "x"), NOW, NOW);
+        assert_eq!(fresh_for(&mut cache, "http://a.test/max-age#top", NOW + 49), Some(true));
+        assert_eq!(fresh_for(&mut cache, "http://a.test/max-age", NOW + 50), Some(false));
+
+        // Out-of-range ages saturate, and negative ones count as zero
+        let headers = [("cache-control", "max-age=9223372036854775807"), ("age", "9223372036854775807")];
+        cache.store(&[], &response("http://a.test/huge-age", &headers, "x"), NOW - 1, NOW);
+        assert_eq!(fresh_for(&mut cache, "http://a.test/huge-age", NOW + 1), Some(false));
+        cache.store(&[], &response("http://a.test/negative-age", &[("cache-control", "max-age=60"), ("age", "-100")], "x"), NOW, NOW);
+        assert_eq!(fresh_for(&mut cache, "http://a.test/negative-age", NOW + 59), Some(true));
+        assert_eq!(fresh_for(&mut cache, "http://a.test/negative-age", NOW + 60), Some(false));
+
+        let headers = [("date", "Tue, 14 Nov 2023 22:13:20 GMT"), ("expires", "Tue, 14 Nov 2023 23:13:20 GMT")];
+        cache.store(&[], &response("http://a.test/expires", &headers, "x"), NOW, NOW);
+        assert_eq!(fresh_for(&mut cache, "http://a.test/expires", NOW + 3599), Some(true));
+        assert_eq!(fresh_for(&mut cache, "http://a.test/expires", NOW + 3600), Some(false));
+
+        // Modified 10 days before: fresh for a day
+        let headers = [("date", "Tue, 14 Nov 2023 22:13:20 GMT"), ("last-modified", "Sat, 04 Nov 2023 22:13:20 GMT")];
+        cache.store(&[], &response("http://a.test/heuristic", &headers, "x"), NOW, NOW);
+        assert_eq!(fresh_for(&mut cache, "http://a.test/heuristic", NOW + 86_399), Some(true));
+
+        cache.store(&[], &response("http://a.test/no-cache", &[("cache-control", "no-cache")], "x"), NOW, NOW);
+        assert_eq!(fresh_for(&mut cache, "http://a.test/no-cache", NOW), Some(false));
+        cache.store(&[], &response("http://a.test/no-store", &[("cache-control", "no-store")], "x"), NOW, NOW);
+        assert_eq!(fresh_for(&mut cache, "http://a.test/no-store", NOW), None);
+        std::fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn matches_vary_and_revalidates() {
+        let mut cache = temporary_cache("vary", DEFAULT_MAX_SIZE);
+        let stored = response("http://a.test/", &[("vary", "Cookie"), ("etag", "\"v1\""), ("x-version", "1")], "body");
+        cache.store(&[("cookie", "a=1".to_string())], &stored, NOW, NOW);
+
+        assert!(cache.lookup("http://a.test/", &[("cookie", "a=2".to_string())], NOW).is_none());
+        let hit = cache.lookup("http://a.test/", &[("cookie", "a=1".to_string())], NOW).unwrap();
+        assert_eq!(validators(&hit.response), vec![("If-None-Match", "\"v1\"".to_string())]);
+
+        let mut not_modified = response("http://a.test/", &[("x-version", "2"), ("cache-control", "max-age=60")], "");
+        not_modified.status = 304;
+        let updated = cache.revalidated("http://a.test/", &not_modified, NOW + 5, NOW + 5).unwrap();
+        assert_eq!((updated.status, updated.bytes.as_slice()), (200, b"body".as_slice()));
+        assert_eq!(updated.headers.get("x-version").map(String::as_str), Some("2"));
+
+        // Cookies stay with the jar
+        let headers = [("cache-control", "max-age=60"), ("set-cookie", "session=secret; HttpOnly"), ("connection", "close")];
+        cache.store(&[], &response("http://a.test/login", &headers, "x"), NOW, NOW);
+        let stored = cache.lookup("http://a.test/login", &[], NOW).unwrap().response;
+        assert!(!stored.headers.contains_key("set-cookie") && !stored.headers.contains_key("connection"));
+
+        // Everything survives a restart
+        let reopened = HttpCache::open(cache.dir.clone(), DEFAULT_MAX_SIZE).lookup("http://a.test/", &[("cookie", "a=1".to_string())], NOW + 10);
+        assert!(reopened.unwrap().fresh);
+        let on_disk: String = std::fs::read_dir(&cache.dir)
+            .unwrap()
+            .flatten()
+            .map(|file| String::from_utf8_lossy(&std::fs::read(file.path()).unwrap()).into_owned())
+            .collect();
+        assert!(!on_disk.contains("secret"));
+        std::fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = temporary_cache("evict", 8 * 200);
+        for (index, name) in ["a", "b", "c"].iter().enumerate() {
+            let body = name.repeat(120);
+            cache.store(&[], &response(&format!("http://a.test/{}", name), &[], &body), NOW, NOW + index as i64);
+        }
+        // Using "a" makes "b" the oldest
+        cache.lookup("http://a.test/a", &[], NOW + 10).unwrap();
+        for name in ["d", "e", "f", "g", "h", "i"] {
+            cache.store(&[], &response(&format!("http://a.test/{}", name), &[], &name.repeat(120)), NOW, NOW + 20);
+        }
+
+        assert!(cache.total_size <= 8 * 200);
+        assert!(cache.lookup("http://a.test/a", &[], NOW + 30).is_some());
+        assert!(cache.lookup("http://a.test/b", &[], NOW + 30).is_none());
+        std::fs::remove_dir_all(&cache.dir).unwrap();
+    }
+}
@@ -1,12 +1,40 @@
 // src/main.rs
+mod address_bar;
 mod app;
+mod bookmarks;
+mod cookies;
+mod css;
+mod dates;
+mod document;
+mod encoding;
+mod fetch;
+mod forms;
+mod headless;
+mod history;
 mod html_renderer;
+mod http_cache;
+mod mime;
+mod profile;
 mod style;
+mod tab;
+#[cfg(test)]
+mod test_server;
 mod ui_components;
+mod url_resolver;
 
 use app::EguiBrowser;
 
 fn main() -> Result<(), eframe::Error> {
+    // `--screenshot` renders a page to PNG without opening a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(screenshot) = headless::ScreenshotArgs::parse(&args) {
+        if let Err(err) = screenshot.and_then(|args| headless::run_screenshot(&args)) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([900.0, 700.0]),
         ..Default::default()
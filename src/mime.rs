@@ -0,0 +1,155 @@
+// src/mime.rs
+// Decide which viewer handles a response, from its Content-Type with a content
+// sniffing fallback loosely following the WHATWG MIME Sniffing standard.
+
+// How many bytes to look at when sniffing
+const SNIFF_LIMIT: usize = 512;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContentKind {
+    Html,
+    Image,
+    PlainText,
+    // Anything we can't display, with the MIME type we settled on
+    Unsupported(String),
+}
+
+// The "essence" of a Content-Type value: type/subtype, lowercased, without parameters
+pub fn mime_essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase()
+}
+
+pub fn classify(content_type: Option<&str>, bytes: &[u8]) -> ContentKind {
+    let essence = content_type.map(mime_essence).unwrap_or_default();
+
+    match essence.as_str() {
+        // Missing or meaningless types are sniffed
+        "" | "unknown/unknown" | "application/unknown" | "*/*" | "application/octet-stream" => {
+            sniff(bytes, &essence)
+        }
+        "text/html" | "application/xhtml+xml" => ContentKind::Html,
+        _ if essence.starts_with("image/") => {
+            // Only formats the image crate can decode go to the image viewer
+            if image::guess_format(bytes).is_ok() {
+                ContentKind::Image
+            } else {
+                ContentKind::Unsupported(essence)
+            }
+        }
+        _ if is_textual(&essence) => ContentKind::PlainText,
+        _ => ContentKind::Unsupported(essence),
+    }
+}
+
+// Types shown as source in the plain-text viewer
+fn is_textual(essence: &str) -> bool {
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence,
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/ecmascript"
+                | "application/x-javascript"
+                | "application/x-sh"
+        )
+}
+
+// Determine the content kind from the bytes alone
+fn sniff(bytes: &[u8], essence: &str) -> ContentKind {
+    let head = &bytes[..bytes.len().min(SNIFF_LIMIT)];
+
+    if looks_like_html(head) {
+        return ContentKind::Html;
+    }
+
+    if image::guess_format(head).is_ok() {
+        return ContentKind::Image;
+    }
+
+    if head.iter().any(|&b| is_binary_byte(b)) {
+        let essence = if essence.is_empty() { "application/octet-stream" } else { essence };
+        return ContentKind::Unsupported(essence.to_string());
+    }
+
+    ContentKind::PlainText
+}
+
+// The tag patterns from the "identify an unknown MIME type" algorithm
+fn looks_like_html(head: &[u8]) -> bool {
+    const PATTERNS: [&[u8]; 17] = [
+        b"<!doctype html", b"<html", b"<head", b"<script", b"<iframe", b"<h1", b"<div",
+        b"<font", b"<table", b"<a", b"<style", b"<title", b"<b", b"<body", b"<br", b"<p",
+        b"<!--",
+    ];
+
+    let start = head.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(head.len());
+    let head = &head[start..];
+
+    PATTERNS.iter().any(|pattern| {
+        if head.len() <= pattern.len() || !head[..pattern.len()].eq_ignore_ascii_case(pattern) {
+            return false;
+        }
+        // The pattern must end at a tag-terminating byte (comments need no terminator)
+        *pattern == b"<!--" || matches!(head[pattern.len()], b' ' | b'>')
+    })
+}
+
+// Control bytes that never appear in text (the "binary data byte" set)
+fn is_binary_byte(byte: u8) -> bool {
+    matches!(byte, 0x00..=0x08 | 0x0B | 0x0E..=0x1A | 0x1C..=0x1F)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn dispatches_on_declared_type() {
+        assert_eq!(classify(Some("text/html; charset=utf-8"), b""), ContentKind::Html);
+        assert_eq!(classify(Some("text/plain"), b"hello"), ContentKind::PlainText);
+        assert_eq!(classify(Some("application/json"), b"{}"), ContentKind::PlainText);
+        assert_eq!(classify(Some("application/ld+json"), b"{}"), ContentKind::PlainText);
+        assert_eq!(classify(Some("IMAGE/PNG"), PNG_HEADER), ContentKind::Image);
+    }
+
+    #[test]
+    fn reports_unsupported_types() {
+        assert_eq!(
+            classify(Some("application/pdf"), b"%PDF-1.7"),
+            ContentKind::Unsupported("application/pdf".to_string())
+        );
+        // Declared as an image, but not one we can decode
+        assert_eq!(
+            classify(Some("image/svg+xml"), b"<svg></svg>"),
+            ContentKind::Unsupported("image/svg+xml".to_string())
+        );
+    }
+
+    #[test]
+    fn sniffs_missing_types() {
+        assert_eq!(classify(None, b"  <!DOCTYPE html><html></html>"), ContentKind::Html);
+        assert_eq!(classify(None, b"<p>paragraph</p>"), ContentKind::Html);
+        assert_eq!(classify(Some("application/octet-stream"), PNG_HEADER), ContentKind::Image);
+        assert_eq!(classify(None, b"just some words"), ContentKind::PlainText);
+        assert_eq!(
+            classify(None, b"\x00\x01\x02binary"),
+            ContentKind::Unsupported("application/octet-stream".to_string())
+        );
+    }
+
+    #[test]
+    fn html_patterns_need_a_terminator() {
+        // "<bogus" starts like "<b" but isn't a match
+        assert_eq!(classify(None, b"<bogus>"), ContentKind::PlainText);
+    }
+}
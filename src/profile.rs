@@ -0,0 +1,34 @@
+// src/profile.rs
+// Where the browser keeps data between runs: cookies and the like. The
+// location can be overridden with EGUI_BROWSER_PROFILE, e.g. to keep a
+// separate profile for testing.
+use std::path::PathBuf;
+
+const APP_DIR: &str = "egui_browser";
+
+// The profile directory, created if needed. None when there's no home to put it in.
+pub fn profile_dir() -> Option<PathBuf> {
+    let dir = match std::env::var_os("EGUI_BROWSER_PROFILE") {
+        Some(dir) => PathBuf::from(dir),
+        None => data_dir()?.join(APP_DIR),
+    };
+    std::fs::create_dir_all(&dir).ok()?;
+    Some(dir)
+}
+
+// A file in the profile directory
+pub fn profile_file(name: &str) -> Option<PathBuf> {
+    profile_dir().map(|dir| dir.join(name))
+}
+
+// The platform's directory for per-user application data
+fn data_dir() -> Option<PathBuf> {
+    let env = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    if cfg!(windows) {
+        env("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        env("XDG_DATA_HOME").or_else(|| env("HOME").map(|home| home.join(".local/share")))
+    }
+}
@@ -1,91 +1,944 @@
 // src/style.rs
-use egui::{Color32, Vec2};
+use crate::css::{parse_color, parse_length, Declaration};
+use egui::Color32;
 use std::collections::HashMap;
 
+// Size of body text, what em and rem units are relative to
+pub const DEFAULT_FONT_SIZE: f32 = 14.0;
+// Larger glyphs don't fit in egui's font atlas
+const MAX_FONT_SIZE: f32 = 1000.0;
+
 // Structure to hold CSS-like styling properties
+#[derive(Clone, Default)]
 pub struct ElementStyle {
     pub color: Option<Color32>,
     pub font_size: Option<f32>,
     pub font_weight: Option<f32>,
-    pub margin: Option<Vec2>,
-    #[allow(dead_code)]
-    pub padding: Option<Vec2>,
-    #[allow(dead_code)]
+    pub italic: Option<bool>,
+    pub underline: Option<bool>,
+    pub strikethrough: Option<bool>,
+    pub monospace: Option<bool>,
+    pub vertical_align: Option<VerticalAlign>,
+    pub border_spacing: Option<f32>,
+    pub border_collapse: Option<bool>,
+    pub list_style_type: Option<ListStyleType>,
+    pub background_color: Option<Color32>,
+    pub display_none: Option<bool>,
+    pub margin: Sides<Option<Length>>,
+    pub padding: Sides<Option<Length>>,
+    pub border_width: Sides<Option<f32>>,
+    // Whether border-style draws anything; `none` and `hidden` don't
+    pub border_visible: Sides<Option<bool>>,
+    pub border_color: Sides<Option<Color32>>,
+    pub width: Option<Length>,
+    pub height: Option<Length>,
+    pub max_width: Option<Length>,
+}
+
+// A box length as specified. Percentages refer to the containing block's
+// width, so they're only resolved at layout time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Length {
+    Px(f32),
+    Percent(f32),
+    Auto,
+}
+
+impl Length {
+    // Pixels for a containing block `containing_width` wide; None for auto
+    pub fn resolve(self, containing_width: f32) -> Option<f32> {
+        match self {
+            Length::Px(px) => Some(px),
+            Length::Percent(percent) => Some(containing_width * percent / 100.0),
+            Length::Auto => None,
+        }
+    }
+}
+
+// One value per edge of a box
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sides<T> {
+    pub top: T,
+    pub right: T,
+    pub bottom: T,
+    pub left: T,
+}
+
+impl<T: Copy> Sides<T> {
+    pub fn all(value: T) -> Self {
+        Self { top: value, right: value, bottom: value, left: value }
+    }
+
+    // The 1-4 values of a shorthand like `margin: 1px 2px`, expanded clockwise
+    // from the top
+    fn from_shorthand(values: &[T]) -> Option<Self> {
+        let (top, right, bottom, left) = match *values {
+            [all] => (all, all, all, all),
+            [vertical, horizontal] => (vertical, horizontal, vertical, horizontal),
+            [top, horizontal, bottom] => (top, horizontal, bottom, horizontal),
+            [top, right, bottom, left] => (top, right, bottom, left),
+            _ => return None,
+        };
+        Some(Self { top, right, bottom, left })
+    }
+
+    fn map<U>(self, f: impl Fn(T) -> U) -> Sides<U> {
+        Sides { top: f(self.top), right: f(self.right), bottom: f(self.bottom), left: f(self.left) }
+    }
+}
+
+impl<T> Sides<Option<T>> {
+    // Set one side by name, e.g. the `left` of `margin-left`
+    fn set(&mut self, side: &str, value: T) {
+        match side {
+            "top" => self.top = Some(value),
+            "right" => self.right = Some(value),
+            "bottom" => self.bottom = Some(value),
+            "left" => self.left = Some(value),
+            _ => {}
+        }
+    }
+}
+
+// Computed border of one edge; zero wide unless a style was given
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Border {
+    pub width: f32,
+    pub color: Color32,
+}
+
+// Marker in front of list items
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ListStyleType {
+    #[default]
+    Disc,
+    Circle,
+    Square,
+    Decimal,
+    LowerAlpha,
+    UpperAlpha,
+    LowerRoman,
+    UpperRoman,
+    None,
+}
+
+impl ListStyleType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "disc" => Some(Self::Disc),
+            "circle" => Some(Self::Circle),
+            "square" => Some(Self::Square),
+            "decimal" => Some(Self::Decimal),
+            "lower-alpha" | "lower-latin" => Some(Self::LowerAlpha),
+            "upper-alpha" | "upper-latin" => Some(Self::UpperAlpha),
+            "lower-roman" => Some(Self::LowerRoman),
+            "upper-roman" => Some(Self::UpperRoman),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum VerticalAlign {
+    #[default]
+    Baseline,
+    Sub,
+    Super,
+}
+
+// Fully resolved style of one element, as renderers see it. Inherited
+// properties come from the parent unless the element sets them; the rest start
+// from their initial values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComputedStyle {
+    // Inherited
+    pub color: Color32,
+    pub font_size: f32,
+    pub font_weight: f32,
+    pub italic: bool,
+    // Decorations aren't inherited in CSS, but they're drawn through every
+    // descendant's text, which amounts to the same thing here
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub monospace: bool,
+    // Not inherited in CSS either, but raised or lowered text carries its
+    // descendants with it
+    pub vertical_align: VerticalAlign,
+    // Gap between table cells, and whether it's dropped so borders meet
+    pub border_spacing: f32,
+    pub border_collapse: bool,
+    pub list_style_type: ListStyleType,
+    // Not inherited
     pub background_color: Option<Color32>,
+    // `display: none`; the element and its descendants generate no boxes
+    pub display_none: bool,
+    pub margin: Sides<Length>,
+    pub padding: Sides<Length>,
+    pub border: Sides<Border>,
+    pub width: Length,
+    pub height: Length,
+    // Auto here means `none`
+    pub max_width: Length,
 }
 
-impl Default for ElementStyle {
+impl Default for ComputedStyle {
+    // Initial values, the style of the document root's parent
     fn default() -> Self {
         Self {
-            color: None,
-            font_size: None,
-            font_weight: None,
-            margin: None,
-            padding: None,
+            color: Color32::BLACK,
+            font_size: DEFAULT_FONT_SIZE,
+            font_weight: 400.0,
+            italic: false,
+            underline: false,
+            strikethrough: false,
+            monospace: false,
+            vertical_align: VerticalAlign::Baseline,
+            border_spacing: 0.0,
+            border_collapse: false,
+            list_style_type: ListStyleType::Disc,
             background_color: None,
+            display_none: false,
+            margin: Sides::all(Length::Px(0.0)),
+            padding: Sides::all(Length::Px(0.0)),
+            border: Sides::all(Border { width: 0.0, color: Color32::BLACK }),
+            width: Length::Auto,
+            height: Length::Auto,
+            max_width: Length::Auto,
+        }
+    }
+}
+
+impl ComputedStyle {
+    pub fn is_bold(&self) -> bool {
+        self.font_weight >= 600.0
+    }
+}
+
+// Resolve an element's style. Declarations apply in cascade order over the
+// tag's user-agent style: author rules (already sorted by specificity and
+// source order), then the style attribute, then !important author rules, then
+// !important inline declarations.
+pub fn cascade(
+    parent: &ComputedStyle,
+    user_agent: Option<&ElementStyle>,
+    author: &[&Declaration],
+    inline: &[Declaration],
+) -> ComputedStyle {
+    let mut specified = user_agent.cloned().unwrap_or_default();
+    let normal = |declaration: &&Declaration| !declaration.important;
+    let important = |declaration: &&Declaration| declaration.important;
+    let ordered = author
+        .iter()
+        .copied()
+        .filter(normal)
+        .chain(inline.iter().filter(normal))
+        .chain(author.iter().copied().filter(important))
+        .chain(inline.iter().filter(important));
+    for declaration in ordered {
+        specified.apply_declaration(declaration, parent);
+    }
+
+    let color = specified.color.unwrap_or(parent.color);
+    let zero = Length::Px(0.0);
+    ComputedStyle {
+        color,
+        font_size: specified.font_size.unwrap_or(parent.font_size),
+        font_weight: specified.font_weight.unwrap_or(parent.font_weight),
+        italic: specified.italic.unwrap_or(parent.italic),
+        underline: specified.underline.unwrap_or(parent.underline),
+        strikethrough: specified.strikethrough.unwrap_or(parent.strikethrough),
+        monospace: specified.monospace.unwrap_or(parent.monospace),
+        vertical_align: specified.vertical_align.unwrap_or(parent.vertical_align),
+        border_spacing: specified.border_spacing.unwrap_or(parent.border_spacing),
+        border_collapse: specified.border_collapse.unwrap_or(parent.border_collapse),
+        list_style_type: specified.list_style_type.unwrap_or(parent.list_style_type),
+        background_color: specified.background_color,
+        display_none: specified.display_none.unwrap_or(false),
+        margin: specified.margin.map(|margin| margin.unwrap_or(zero)),
+        padding: specified.padding.map(|padding| padding.unwrap_or(zero)),
+        border: Sides {
+            top: border_edge(specified.border_visible.top, specified.border_width.top, specified.border_color.top, color),
+            right: border_edge(specified.border_visible.right, specified.border_width.right, specified.border_color.right, color),
+            bottom: border_edge(specified.border_visible.bottom, specified.border_width.bottom, specified.border_color.bottom, color),
+            left: border_edge(specified.border_visible.left, specified.border_width.left, specified.border_color.left, color),
+        },
+        width: specified.width.unwrap_or(Length::Auto),
+        height: specified.height.unwrap_or(Length::Auto),
+        max_width: specified.max_width.unwrap_or(Length::Auto),
+    }
+}
+
+// A border is only as wide as its width (medium by default) when its style
+// draws something; its color defaults to the text color
+fn border_edge(visible: Option<bool>, width: Option<f32>, color: Option<Color32>, text_color: Color32) -> Border {
+    Border {
+        width: if visible == Some(true) { width.unwrap_or(3.0) } else { 0.0 },
+        color: color.unwrap_or(text_color),
+    }
+}
+
+impl ElementStyle {
+    // Apply one CSS declaration; values that don't parse are ignored, as in CSS.
+    // Relative font sizes and `inherit` refer to the parent's computed style.
+    pub fn apply_declaration(&mut self, declaration: &Declaration, parent: &ComputedStyle) {
+        let value = declaration.value.as_str();
+        if value.eq_ignore_ascii_case("inherit") {
+            self.inherit(&declaration.property, parent);
+            return;
+        }
+        // em lengths other than font-size use the element's own font size
+        let font_size = self.font_size.unwrap_or(parent.font_size);
+        match declaration.property.as_str() {
+            "color" => {
+                if let Some(color) = parse_color(value) {
+                    self.color = Some(color);
+                }
+            }
+            "background-color" => {
+                if let Some(color) = parse_color(value) {
+                    self.background_color = Some(color);
+                }
+            }
+            // The shorthand may also hold images and positions; only its color is used
+            "background" => {
+                if let Some(color) = value.split_whitespace().rev().find_map(parse_color) {
+                    self.background_color = Some(color);
+                }
+            }
+            "font-size" => {
+                if let Some(size) = parse_font_size(value, parent.font_size) {
+                    self.font_size = Some(size);
+                }
+            }
+            "font-weight" => {
+                if let Some(weight) = parse_font_weight(value, parent.font_weight) {
+                    self.font_weight = Some(weight);
+                }
+            }
+            "font-style" => match value.trim().to_ascii_lowercase().as_str() {
+                "italic" | "oblique" => self.italic = Some(true),
+                "normal" => self.italic = Some(false),
+                _ => {}
+            },
+            // Colors and styles in the shorthand are ignored
+            "text-decoration" | "text-decoration-line" => {
+                let value = value.to_ascii_lowercase();
+                let lines: Vec<&str> = value.split_whitespace().collect();
+                if lines.contains(&"none") {
+                    self.underline = Some(false);
+                    self.strikethrough = Some(false);
+                } else if lines.iter().any(|line| matches!(*line, "underline" | "line-through" | "overline")) {
+                    self.underline = Some(lines.contains(&"underline"));
+                    self.strikethrough = Some(lines.contains(&"line-through"));
+                }
+            }
+            // Only the first family counts, and only as monospace or not
+            "font-family" => {
+                let family = value.split(',').next().unwrap_or("").trim().trim_matches(|c| c == '"' || c == '\'');
+                let family = family.to_ascii_lowercase();
+                self.monospace = Some(
+                    family.contains("mono")
+                        || matches!(family.as_str(), "courier" | "courier new" | "consolas" | "menlo" | "monaco"),
+                );
+            }
+            "vertical-align" => match value.trim().to_ascii_lowercase().as_str() {
+                "sub" => self.vertical_align = Some(VerticalAlign::Sub),
+                "super" => self.vertical_align = Some(VerticalAlign::Super),
+                "baseline" => self.vertical_align = Some(VerticalAlign::Baseline),
+                _ => {}
+            },
+            "display" => self.display_none = Some(value.trim().eq_ignore_ascii_case("none")),
+            "margin" | "padding" => {
+                let allow_auto = declaration.property == "margin";
+                let lengths: Option<Vec<Length>> = value
+                    .split_whitespace()
+                    .map(|part| parse_box_length(part, font_size, allow_auto))
+                    .collect();
+                if let Some(sides) = lengths.as_deref().and_then(Sides::from_shorthand) {
+                    let sides = sides.map(Some);
+                    if allow_auto {
+                        self.margin = sides;
+                    } else {
+                        self.padding = sides;
+                    }
+                }
+            }
+            "margin-top" | "margin-bottom" | "margin-left" | "margin-right" => {
+                if let Some(length) = parse_box_length(value, font_size, true) {
+                    self.margin.set(&declaration.property["margin-".len()..], length);
+                }
+            }
+            "padding-top" | "padding-bottom" | "padding-left" | "padding-right" => {
+                if let Some(length) = parse_box_length(value, font_size, false) {
+                    self.padding.set(&declaration.property["padding-".len()..], length);
+                }
+            }
+            // Only the horizontal spacing is used, for both directions
+            "border-spacing" => {
+                if let Some(spacing) = value.split_whitespace().next().and_then(|part| parse_length(part, font_size)) {
+                    self.border_spacing = Some(spacing.max(0.0));
+                }
+            }
+            "border-collapse" => match value.trim().to_ascii_lowercase().as_str() {
+                "collapse" => self.border_collapse = Some(true),
+                "separate" => self.border_collapse = Some(false),
+                _ => {}
+            },
+            "list-style-type" => {
+                if let Some(list_style) = ListStyleType::parse(value) {
+                    self.list_style_type = Some(list_style);
+                }
+            }
+            // Images and positions in the shorthand are ignored
+            "list-style" => {
+                if let Some(list_style) = value.split_whitespace().find_map(ListStyleType::parse) {
+                    self.list_style_type = Some(list_style);
+                }
+            }
+            "border" => {
+                if let Some((width, visible, color)) = parse_border(value, font_size) {
+                    self.border_width = Sides::all(Some(width));
+                    self.border_visible = Sides::all(Some(visible));
+                    self.border_color = Sides::all(color);
+                }
+            }
+            "border-top" | "border-right" | "border-bottom" | "border-left" => {
+                if let Some((width, visible, color)) = parse_border(value, font_size) {
+                    let side = &declaration.property["border-".len()..];
+                    set_side_option(&mut self.border_width, side, Some(width));
+                    set_side_option(&mut self.border_visible, side, Some(visible));
+                    set_side_option(&mut self.border_color, side, color);
+                }
+            }
+            "border-width" => {
+                let widths: Option<Vec<f32>> = value.split_whitespace().map(|part| parse_border_width(part, font_size)).collect();
+                if let Some(sides) = widths.as_deref().and_then(Sides::from_shorthand) {
+                    self.border_width = sides.map(Some);
+                }
+            }
+            "border-style" => {
+                let styles: Option<Vec<bool>> = value.split_whitespace().map(parse_border_style).collect();
+                if let Some(sides) = styles.as_deref().and_then(Sides::from_shorthand) {
+                    self.border_visible = sides.map(Some);
+                }
+            }
+            "border-color" => {
+                let colors: Option<Vec<Color32>> = split_values(value).into_iter().map(parse_color).collect();
+                if let Some(sides) = colors.as_deref().and_then(Sides::from_shorthand) {
+                    self.border_color = sides.map(Some);
+                }
+            }
+            property if property.starts_with("border-") => {
+                // border-<side>-width, -style and -color
+                let Some((side, part)) = property["border-".len()..].split_once('-') else {
+                    return;
+                };
+                match part {
+                    "width" => {
+                        if let Some(width) = parse_border_width(value, font_size) {
+                            self.border_width.set(side, width);
+                        }
+                    }
+                    "style" => {
+                        if let Some(visible) = parse_border_style(value) {
+                            self.border_visible.set(side, visible);
+                        }
+                    }
+                    "color" => {
+                        if let Some(color) = parse_color(value) {
+                            self.border_color.set(side, color);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            "width" | "height" => {
+                if let Some(length) = parse_box_length(value, font_size, true).filter(|length| !is_negative(*length)) {
+                    if declaration.property == "width" {
+                        self.width = Some(length);
+                    } else {
+                        self.height = Some(length);
+                    }
+                }
+            }
+            "max-width" => {
+                if value.trim().eq_ignore_ascii_case("none") {
+                    self.max_width = Some(Length::Auto);
+                } else if let Some(length) = parse_box_length(value, font_size, false) {
+                    self.max_width = Some(length);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // `property: inherit` takes the parent's computed value
+    fn inherit(&mut self, property: &str, parent: &ComputedStyle) {
+        match property {
+            "color" => self.color = Some(parent.color),
+            "font-size" => self.font_size = Some(parent.font_size),
+            "font-weight" => self.font_weight = Some(parent.font_weight),
+            "font-style" => self.italic = Some(parent.italic),
+            "font-family" => self.monospace = Some(parent.monospace),
+            "background-color" => self.background_color = parent.background_color,
+            "border-spacing" => self.border_spacing = Some(parent.border_spacing),
+            "border-collapse" => self.border_collapse = Some(parent.border_collapse),
+            "list-style-type" => self.list_style_type = Some(parent.list_style_type),
+            "margin" => self.margin = parent.margin.map(Some),
+            "padding" => self.padding = parent.padding.map(Some),
+            _ => {}
+        }
+    }
+}
+
+// Font size in pixels; `parent_size` is what keywords like `larger` and
+// relative units scale. Capped so nested multipliers can't compound past what
+// the font atlas can hold
+fn parse_font_size(value: &str, parent_size: f32) -> Option<f32> {
+    let size = match value.trim().to_ascii_lowercase().as_str() {
+        "xx-small" => DEFAULT_FONT_SIZE * 0.6,
+        "x-small" => DEFAULT_FONT_SIZE * 0.75,
+        "small" => DEFAULT_FONT_SIZE * 0.89,
+        "medium" => DEFAULT_FONT_SIZE,
+        "large" => DEFAULT_FONT_SIZE * 1.2,
+        "x-large" => DEFAULT_FONT_SIZE * 1.5,
+        "xx-large" => DEFAULT_FONT_SIZE * 2.0,
+        "smaller" => parent_size / 1.2,
+        "larger" => parent_size * 1.2,
+        value => match value.strip_suffix('%') {
+            Some(percent) => parent_size * percent.trim().parse::<f32>().ok()? / 100.0,
+            None => parse_length(value, parent_size)?,
+        },
+    };
+    (size > 0.0).then_some(size.min(MAX_FONT_SIZE))
+}
+
+fn parse_font_weight(value: &str, current: f32) -> Option<f32> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "normal" => Some(400.0),
+        "bold" => Some(700.0),
+        "bolder" => Some(if current < 600.0 { 700.0 } else { 900.0 }),
+        "lighter" => Some(if current > 500.0 { 400.0 } else { 100.0 }),
+        number => number.parse::<f32>().ok().filter(|w| (1.0..=1000.0).contains(w)),
+    }
+}
+
+// A margin, padding, width or height; only margins and sizes may be auto, and
+// only margins may be negative
+fn parse_box_length(value: &str, font_size: f32, allow_auto: bool) -> Option<Length> {
+    let value = value.trim();
+    let length = if value.eq_ignore_ascii_case("auto") {
+        allow_auto.then_some(Length::Auto)?
+    } else if let Some(percent) = value.strip_suffix('%') {
+        Length::Percent(percent.trim().parse().ok()?)
+    } else {
+        Length::Px(parse_length(value, font_size)?)
+    };
+    if !allow_auto && is_negative(length) {
+        return None;
+    }
+    Some(length)
+}
+
+fn is_negative(length: Length) -> bool {
+    match length {
+        Length::Px(value) | Length::Percent(value) => value < 0.0,
+        Length::Auto => false,
+    }
+}
+
+fn parse_border_width(value: &str, font_size: f32) -> Option<f32> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "thin" => Some(1.0),
+        "medium" => Some(3.0),
+        "thick" => Some(5.0),
+        value => parse_length(value, font_size).filter(|width| *width >= 0.0),
+    }
+}
+
+// Whether a border-style value draws a border; every visible style is drawn solid
+fn parse_border_style(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "none" | "hidden" => Some(false),
+        "solid" | "dashed" | "dotted" | "double" | "groove" | "ridge" | "inset" | "outset" => Some(true),
+        _ => None,
+    }
+}
+
+// The `border` shorthand: width, style and color in any order. Omitted parts
+// reset to their initial values.
+fn parse_border(value: &str, font_size: f32) -> Option<(f32, bool, Option<Color32>)> {
+    let (mut width, mut visible, mut color) = (None, None, None);
+    for part in split_values(value) {
+        if let Some(style) = parse_border_style(part).filter(|_| visible.is_none()) {
+            visible = Some(style);
+        } else if let Some(border_width) = parse_border_width(part, font_size).filter(|_| width.is_none()) {
+            width = Some(border_width);
+        } else if let Some(border_color) = parse_color(part).filter(|_| color.is_none()) {
+            color = Some(border_color);
+        } else {
+            return None;
         }
     }
+    Some((width.unwrap_or(3.0), visible.unwrap_or(false), color))
 }
 
+// A side's border color resets to the text color when the shorthand omits it
+fn set_side_option<T>(sides: &mut Sides<Option<T>>, side: &str, value: Option<T>) {
+    match side {
+        "top" => sides.top = value,
+        "right" => sides.right = value,
+        "bottom" => sides.bottom = value,
+        "left" => sides.left = value,
+        _ => {}
+    }
+}
+
+// Whitespace-separated values, keeping functions like rgb(1, 2, 3) whole
+fn split_values(value: &str) -> Vec<&str> {
+    let mut values = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+    for (index, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(begin) = start.take() {
+                    values.push(&value[begin..index]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(index);
+    }
+    if let Some(begin) = start {
+        values.push(&value[begin..]);
+    }
+    values
+}
+
+// User-agent rules that depend on where an element is, which the per-tag
+// defaults can't express: unordered lists change marker as they nest
+pub const USER_AGENT_CSS: &str = "
+    ol ul, ul ul { list-style-type: circle }
+    ol ol ul, ol ul ul, ul ol ul, ul ul ul { list-style-type: square }
+";
+
 pub fn create_default_styles() -> HashMap<String, ElementStyle> {
     let mut style_map = HashMap::new();
     
-    // Define default styles for common HTML elements
-    let mut body_style = ElementStyle::default();
-    body_style.color = Some(Color32::from_rgb(33, 33, 33)); // Dark text
-    body_style.background_color = Some(Color32::from_rgb(255, 255, 255)); // White background
+    // User-agent defaults for common HTML elements. Text color is only set on
+    // body; everything else inherits it.
+    let body_style = ElementStyle {
+        color: Some(Color32::from_rgb(33, 33, 33)), // Dark text
+        background_color: Some(Color32::from_rgb(255, 255, 255)), // White background
+        ..Default::default()
+    };
     style_map.insert("body".to_string(), body_style);
     
-    let mut h1_style = ElementStyle::default();
-    h1_style.font_size = Some(28.0);
-    h1_style.font_weight = Some(800.0);
-    h1_style.margin = Some(Vec2::new(0.0, 10.0));
-    h1_style.color = Some(Color32::from_rgb(33, 33, 33)); // Dark text
-    style_map.insert("h1".to_string(), h1_style);
-    
-    let mut h2_style = ElementStyle::default();
-    h2_style.font_size = Some(24.0);
-    h2_style.font_weight = Some(700.0);
-    h2_style.margin = Some(Vec2::new(0.0, 8.0));
-    h2_style.color = Some(Color32::from_rgb(33, 33, 33)); // Dark text
-    style_map.insert("h2".to_string(), h2_style);
-    
-    let mut h3_style = ElementStyle::default();
-    h3_style.font_size = Some(20.0);
-    h3_style.font_weight = Some(600.0);
-    h3_style.margin = Some(Vec2::new(0.0, 6.0));
-    h3_style.color = Some(Color32::from_rgb(33, 33, 33)); // Dark text
-    style_map.insert("h3".to_string(), h3_style);
-    
-    let mut p_style = ElementStyle::default();
-    p_style.margin = Some(Vec2::new(0.0, 4.0));
-    p_style.color = Some(Color32::from_rgb(33, 33, 33)); // Dark text
+    // Headings: size, weight and vertical margin per level
+    let headings = [
+        ("h1", 28.0, 800.0, 10.0),
+        ("h2", 24.0, 700.0, 8.0),
+        ("h3", 20.0, 600.0, 6.0),
+        ("h4", 16.0, 600.0, 5.0),
+        ("h5", 14.0, 600.0, 4.0),
+        ("h6", 12.0, 600.0, 4.0),
+    ];
+    for (tag, font_size, font_weight, margin) in headings {
+        let heading_style = ElementStyle {
+            font_size: Some(font_size),
+            font_weight: Some(font_weight),
+            margin: vertical_margin(margin),
+            ..Default::default()
+        };
+        style_map.insert(tag.to_string(), heading_style);
+    }
+    
+    let p_style = ElementStyle {
+        margin: vertical_margin(4.0),
+        ..Default::default()
+    };
     style_map.insert("p".to_string(), p_style);
     
-    let mut a_style = ElementStyle::default();
-    a_style.color = Some(Color32::from_rgb(0, 102, 204)); // Blue links
+    let blockquote_style = ElementStyle {
+        margin: Sides {
+            left: Some(Length::Px(40.0)),
+            right: Some(Length::Px(40.0)),
+            ..vertical_margin(8.0)
+        },
+        ..Default::default()
+    };
+    style_map.insert("blockquote".to_string(), blockquote_style);
+    
+    let table_style = ElementStyle {
+        border_spacing: Some(2.0),
+        ..Default::default()
+    };
+    style_map.insert("table".to_string(), table_style);
+    
+    let cell_style = ElementStyle {
+        padding: Sides::all(Some(Length::Px(1.0))),
+        ..Default::default()
+    };
+    style_map.insert("td".to_string(), cell_style.clone());
+    style_map.insert("th".to_string(), ElementStyle { font_weight: Some(700.0), ..cell_style });
+    
+    // Lists indent their items to make room for the markers
+    let list_indent = Sides {
+        left: Some(Length::Px(24.0)),
+        ..Default::default()
+    };
+    for (tag, list_style) in [("ul", ListStyleType::Disc), ("ol", ListStyleType::Decimal)] {
+        let list_style = ElementStyle {
+            list_style_type: Some(list_style),
+            margin: vertical_margin(4.0),
+            padding: list_indent,
+            ..Default::default()
+        };
+        style_map.insert(tag.to_string(), list_style);
+    }
+    style_map.insert("dl".to_string(), ElementStyle { margin: vertical_margin(4.0), ..Default::default() });
+    let dd_style = ElementStyle {
+        margin: Sides { left: Some(Length::Px(40.0)), ..Default::default() },
+        ..Default::default()
+    };
+    style_map.insert("dd".to_string(), dd_style);
+    
+    // A fieldset is a grooved frame around its controls
+    let fieldset_style = ElementStyle {
+        margin: Sides { left: Some(Length::Px(2.0)), right: Some(Length::Px(2.0)), ..vertical_margin(4.0) },
+        padding: Sides {
+            top: Some(Length::Px(4.0)),
+            right: Some(Length::Px(10.0)),
+            bottom: Some(Length::Px(8.0)),
+            left: Some(Length::Px(10.0)),
+        },
+        border_width: Sides::all(Some(2.0)),
+        border_visible: Sides::all(Some(true)),
+        border_color: Sides::all(Some(Color32::from_rgb(192, 192, 192))),
+        ..Default::default()
+    };
+    style_map.insert("fieldset".to_string(), fieldset_style);
+    let legend_style = ElementStyle {
+        font_weight: Some(700.0),
+        margin: Sides { bottom: Some(Length::Px(4.0)), ..Default::default() },
+        ..Default::default()
+    };
+    style_map.insert("legend".to_string(), legend_style);
+    
+    let a_style = ElementStyle {
+        color: Some(Color32::from_rgb(0, 102, 204)), // Blue links
+        underline: Some(true),
+        ..Default::default()
+    };
     style_map.insert("a".to_string(), a_style);
     
-    let mut strong_style = ElementStyle::default();
-    strong_style.font_weight = Some(700.0);
-    strong_style.color = Some(Color32::from_rgb(33, 33, 33)); // Dark text
-    style_map.insert("strong".to_string(), strong_style);
+    let bold_style = ElementStyle {
+        font_weight: Some(700.0),
+        ..Default::default()
+    };
+    for tag in ["strong", "b"] {
+        style_map.insert(tag.to_string(), bold_style.clone());
+    }
+    
+    let italic_style = ElementStyle {
+        italic: Some(true),
+        ..Default::default()
+    };
+    for tag in ["em", "i", "cite", "var"] {
+        style_map.insert(tag.to_string(), italic_style.clone());
+    }
+    
+    let u_style = ElementStyle {
+        underline: Some(true),
+        ..Default::default()
+    };
+    for tag in ["u", "ins"] {
+        style_map.insert(tag.to_string(), u_style.clone());
+    }
+    
+    let strike_style = ElementStyle {
+        strikethrough: Some(true),
+        ..Default::default()
+    };
+    for tag in ["s", "del", "strike"] {
+        style_map.insert(tag.to_string(), strike_style.clone());
+    }
+    
+    let monospace_style = ElementStyle {
+        monospace: Some(true),
+        ..Default::default()
+    };
+    for tag in ["code", "kbd", "samp", "tt", "pre"] {
+        style_map.insert(tag.to_string(), monospace_style.clone());
+    }
     
-    let mut em_style = ElementStyle::default();
-    em_style.font_weight = Some(400.0); // Normal weight but italic (handled in rendering)
-    em_style.color = Some(Color32::from_rgb(33, 33, 33)); // Dark text
-    style_map.insert("em".to_string(), em_style);
+    let sub_style = ElementStyle {
+        vertical_align: Some(VerticalAlign::Sub),
+        ..Default::default()
+    };
+    style_map.insert("sub".to_string(), sub_style);
     
-    // Add style for span elements
-    let mut span_style = ElementStyle::default();
-    span_style.color = Some(Color32::from_rgb(33, 33, 33)); // Dark text
-    style_map.insert("span".to_string(), span_style);
+    let sup_style = ElementStyle {
+        vertical_align: Some(VerticalAlign::Super),
+        ..Default::default()
+    };
+    style_map.insert("sup".to_string(), sup_style);
     
-    // Add style for div elements
-    let mut div_style = ElementStyle::default();
-    div_style.color = Some(Color32::from_rgb(33, 33, 33)); // Dark text
-    div_style.background_color = Some(Color32::from_rgb(255, 255, 255)); // White background
-    style_map.insert("div".to_string(), div_style);
+    let mark_style = ElementStyle {
+        background_color: Some(Color32::from_rgb(255, 255, 0)),
+        color: Some(Color32::BLACK),
+        ..Default::default()
+    };
+    style_map.insert("mark".to_string(), mark_style);
     
     style_map
-}
\ No newline at end of file
+}
+
+fn vertical_margin(margin: f32) -> Sides<Option<Length>> {
+    Sides {
+        top: Some(Length::Px(margin)),
+        bottom: Some(Length::Px(margin)),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parse_declarations;
+
+    fn style_from(css: &str) -> ElementStyle {
+        let mut style = ElementStyle::default();
+        for declaration in parse_declarations(css) {
+            style.apply_declaration(&declaration, &ComputedStyle::default());
+        }
+        style
+    }
+
+    #[test]
+    fn applies_font_declarations() {
+        let style = style_from("font-size: 2em; font-weight: bold; font-style: italic; color: #00f");
+        assert_eq!(style.font_size, Some(28.0));
+        assert_eq!(style.font_weight, Some(700.0));
+        assert_eq!(style.italic, Some(true));
+        assert_eq!(style.color, Some(Color32::from_rgb(0, 0, 255)));
+
+        assert_eq!(style_from("font-size: large").font_size, Some(DEFAULT_FONT_SIZE * 1.2));
+        // bolder is relative to the parent's weight
+        assert_eq!(style_from("font-weight: bold; font-weight: bolder").font_weight, Some(700.0));
+        // Text can't be sized to nothing, which the font rasterizer rejects
+        assert_eq!(style_from("font-size: 150%").font_size, Some(DEFAULT_FONT_SIZE * 1.5));
+        assert_eq!(style_from("font-size: 0%").font_size, None);
+        assert_eq!(style_from("font-size: -20%").font_size, None);
+        // Nor so large that its glyphs overflow the font atlas
+        assert_eq!(style_from("font-size: 20000px").font_size, Some(MAX_FONT_SIZE));
+        assert_eq!(style_from("font-size: 1e30%").font_size, Some(MAX_FONT_SIZE));
+    }
+
+    #[test]
+    fn applies_text_decoration() {
+        let style = style_from("text-decoration: underline line-through red");
+        assert_eq!((style.underline, style.strikethrough), (Some(true), Some(true)));
+        let style = style_from("text-decoration: none");
+        assert_eq!((style.underline, style.strikethrough), (Some(false), Some(false)));
+    }
+
+    #[test]
+    fn applies_box_shorthands_and_sides() {
+        let px = |value| Some(Length::Px(value));
+        let margin = style_from("margin: 10px auto").margin;
+        assert_eq!(margin, Sides { top: px(10.0), right: Some(Length::Auto), bottom: px(10.0), left: Some(Length::Auto) });
+        let padding = style_from("padding: 1px 2px 3px 4px").padding;
+        assert_eq!(padding, Sides { top: px(1.0), right: px(2.0), bottom: px(3.0), left: px(4.0) });
+        let margin = style_from("margin: 5px; margin-top: 10%").margin;
+        assert_eq!((margin.top, margin.left), (Some(Length::Percent(10.0)), px(5.0)));
+        // Padding can't be auto or negative
+        assert_eq!(style_from("padding: auto").padding, Sides::default());
+        assert_eq!(style_from("padding-left: -1px").padding.left, None);
+        assert_eq!(style_from("background: url(x.png) no-repeat #eee").background_color, Some(Color32::from_gray(0xee)));
+    }
+
+    #[test]
+    fn inherits_from_the_parent() {
+        let defaults = create_default_styles();
+        let div = cascade(&ComputedStyle::default(), None, &[], &parse_declarations("color: red; font-size: 20px; background: blue"));
+        let span = cascade(&div, None, &[], &[]);
+        assert_eq!(span.color, Color32::from_rgb(255, 0, 0));
+        assert_eq!(span.font_size, 20.0);
+        // Backgrounds belong to the element's own box
+        assert_eq!(span.background_color, None);
+
+        let strong = cascade(&span, defaults.get("strong"), &[], &parse_declarations("font-size: 1.5em"));
+        assert!(strong.is_bold());
+        assert_eq!(strong.font_size, 30.0);
+        let em = cascade(&strong, defaults.get("em"), &[], &parse_declarations("font-weight: inherit"));
+        assert!(em.italic && em.is_bold());
+
+        // Relative sizes compound down the tree but stay within the cap
+        let mut big = ComputedStyle::default();
+        for _ in 0..10 {
+            big = cascade(&big, None, &[], &parse_declarations("font-size: 10em"));
+        }
+        assert_eq!(big.font_size, MAX_FONT_SIZE);
+    }
+
+    #[test]
+    fn orders_author_inline_and_important_declarations() {
+        let parent = ComputedStyle::default();
+        let author = parse_declarations("color: red; font-size: 20px !important");
+        let author: Vec<&Declaration> = author.iter().collect();
+        let inline = parse_declarations("color: green; font-size: 10px");
+        let style = cascade(&parent, None, &author, &inline);
+        assert_eq!(style.color, Color32::from_rgb(0, 128, 0));
+        assert_eq!(style.font_size, 20.0);
+
+        let inline = parse_declarations("font-size: 10px !important");
+        assert_eq!(cascade(&parent, None, &author, &inline).font_size, 10.0);
+    }
+
+    #[test]
+    fn ignores_invalid_values() {
+        let style = style_from("color: nope; font-size: -3px; font-weight: heavy; margin: 1px 2px 3px 4px 5px");
+        assert_eq!(style.color, None);
+        assert_eq!(style.font_size, None);
+        assert_eq!(style.font_weight, None);
+        assert_eq!(style.margin, Sides::default());
+    }
+
+    #[test]
+    fn computes_borders() {
+        let parent = ComputedStyle::default();
+        let declarations = parse_declarations("color: red; border: 2px solid; border-left: thin dashed rgb(0, 0, 255); border-bottom-style: none");
+        let style = cascade(&parent, None, &[], &declarations);
+        assert_eq!(style.border.top, Border { width: 2.0, color: Color32::from_rgb(255, 0, 0) });
+        assert_eq!(style.border.left, Border { width: 1.0, color: Color32::from_rgb(0, 0, 255) });
+        assert_eq!(style.border.bottom.width, 0.0);
+
+        // Without a style there's no border, whatever its width
+        let style = cascade(&parent, None, &[], &parse_declarations("border-width: 4px"));
+        assert_eq!(style.border.top.width, 0.0);
+    }
+
+    #[test]
+    fn applies_sizes_and_display() {
+        let style = style_from("width: 50%; height: 3em; max-width: 600px; display: none");
+        assert_eq!(style.width, Some(Length::Percent(50.0)));
+        assert_eq!(style.height, Some(Length::Px(42.0)));
+        assert_eq!(style.max_width, Some(Length::Px(600.0)));
+        assert_eq!(style.display_none, Some(true));
+        assert_eq!(style_from("width: -5px").width, None);
+    }
+
+    #[test]
+    fn applies_list_styles() {
+        assert_eq!(style_from("list-style-type: lower-roman").list_style_type, Some(ListStyleType::LowerRoman));
+        assert_eq!(style_from("list-style: square inside").list_style_type, Some(ListStyleType::Square));
+        assert_eq!(style_from("list-style: none").list_style_type, Some(ListStyleType::None));
+        assert_eq!(style_from("list-style-type: hebrew").list_style_type, None);
+    }
+}
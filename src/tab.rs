@@ -0,0 +1,466 @@
+// src/tab.rs
+use crate::css::parse_stylesheet;
+use crate::document::{AboutPage, Document, PageContent};
+use crate::encoding::decode_body;
+use crate::fetch::{fetch_cancellable, CacheMode, CancelFlag, HttpClient, Initiator, LoadPhase, LoadProgress, PageRequest, ResponseInfo};
+use crate::forms::{blocking_control, submission_request, update_validity};
+use crate::url_resolver::is_fetchable;
+use egui::Context;
+use poll_promise::Promise;
+use std::time::{Duration, Instant};
+
+// Navigation history structure
+pub struct NavigationHistory {
+    history: Vec<String>,
+    current_index: usize,
+}
+
+impl NavigationHistory {
+    pub fn new(initial_url: String) -> Self {
+        Self {
+            history: vec![initial_url],
+            current_index: 0,
+        }
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.current_index > 0
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.current_index < self.history.len() - 1
+    }
+
+    pub fn go_back(&mut self) -> Option<&str> {
+        if self.can_go_back() {
+            self.current_index -= 1;
+            Some(&self.history[self.current_index])
+        } else {
+            None
+        }
+    }
+
+    pub fn go_forward(&mut self) -> Option<&str> {
+        if self.can_go_forward() {
+            self.current_index += 1;
+            Some(&self.history[self.current_index])
+        } else {
+            None
+        }
+    }
+
+    pub fn add_url(&mut self, url: String) {
+        // Remove any forward history
+        if self.current_index < self.history.len() - 1 {
+            self.history.truncate(self.current_index + 1);
+        }
+
+        // Don't add if it's the same as the current URL
+        if self.current_url() == url {
+            return;
+        }
+
+        // A blank new tab's starting entry isn't worth going back to
+        if self.current_url().is_empty() {
+            self.replace_current(url);
+            return;
+        }
+
+        self.history.push(url);
+        self.current_index = self.history.len() - 1;
+    }
+
+    // Update the current entry in place, e.g. after a redirect
+    pub fn replace_current(&mut self, url: String) {
+        self.history[self.current_index] = url;
+    }
+
+    pub fn current_url(&self) -> String {
+        self.history[self.current_index].clone()
+    }
+}
+
+// Everything that belongs to one open page: its address, document, history
+// and in-flight request
+pub struct Tab {
+    // Stable identity, used for egui ids so scroll state stays with the tab
+    pub id: u64,
+    // Contents of the address bar
+    pub url: String,
+    // Parsed page, rebuilt only when a new response arrives
+    pub document: Option<Document>,
+    pub error_message: Option<String>,
+    // Promise to store the ongoing HTTP request, tagged with its navigation id
+    pub fetch_promise: Option<Promise<(u64, Result<ehttp::Response, String>)>>,
+    // Bumped by every navigation, reload and stop; responses for older ones are dropped
+    pub navigation_id: u64,
+    // Stops the current navigation's page, stylesheet and image requests
+    cancel: CancelFlag,
+    // Phase of the page request, for the status bar
+    pub progress: LoadProgress,
+    // When the current navigation started, until it and its subresources finish
+    load_started: Option<Instant>,
+    // How long the last finished navigation took
+    pub load_time: Option<Duration>,
+    // Whether the load in flight is a new visit for global history
+    pub new_visit: bool,
+    pub navigation: NavigationHistory,
+    // Linked stylesheets of the current page still being fetched, by URL
+    stylesheet_promises: Vec<(String, Promise<Result<ehttp::Response, String>>)>,
+    // Bumped for every loaded document so a new page starts scrolled to the top
+    load_count: u64,
+}
+
+impl Tab {
+    pub fn new(id: u64, url: String) -> Self {
+        Self {
+            id,
+            url: url.clone(),
+            document: None,
+            error_message: None,
+            fetch_promise: None,
+            navigation_id: 0,
+            cancel: CancelFlag::default(),
+            progress: LoadProgress::default(),
+            load_started: None,
+            load_time: None,
+            new_visit: false,
+            navigation: NavigationHistory::new(url),
+            stylesheet_promises: Vec::new(),
+            load_count: 0,
+        }
+    }
+
+    // Start a new HTTP request for the address bar URL, replacing any in-flight one
+    pub fn fetch(&mut self, ctx: &Context, client: &HttpClient) {
+        self.start_request(ctx, PageRequest::get(self.url.clone()), client, true);
+    }
+
+    // `new_visit` is false for reloads and back/forward, which global history
+    // doesn't count
+    fn start_request(&mut self, ctx: &Context, request: PageRequest, client: &HttpClient, new_visit: bool) {
+        self.stop();
+        self.new_visit = new_visit;
+        // Browser pages show up at once
+        if let Some(page) = AboutPage::from_url(&request.url) {
+            self.set_document(Document::about(page, &request.url));
+            return;
+        }
+        self.cancel = CancelFlag::default();
+        self.progress = LoadProgress::default();
+        self.load_started = Some(Instant::now());
+        self.load_time = None;
+        let client = client.clone();
+        let (navigation_id, cancel, progress) = (self.navigation_id, self.cancel.clone(), self.progress.clone());
+
+        // Add a timeout to prevent freezing
+        let ctx_clone = ctx.clone();
+        let promise = Promise::spawn_thread("fetch_url", move || {
+            let result = fetch_cancellable(&request, &client, &cancel, &progress);
+            ctx_clone.request_repaint();
+            (navigation_id, result)
+        });
+
+        self.fetch_promise = Some(promise);
+        ctx.request_repaint(); // Request a repaint to show the spinner
+    }
+
+    // Cancel the page load and the current page's stylesheet and image
+    // requests. Whatever they return later is dropped.
+    pub fn stop(&mut self) {
+        self.cancel.cancel();
+        self.navigation_id += 1;
+        self.fetch_promise = None;
+        self.stylesheet_promises.clear();
+        self.load_started = None;
+    }
+
+    // Load the current history entry again, checking with the server that
+    // cached copies are current, or with `hard` not using the cache at all
+    pub fn reload(&mut self, ctx: &Context, client: &HttpClient, hard: bool) {
+        self.url = self.navigation.current_url();
+        if self.url.is_empty() {
+            return;
+        }
+        let cache_mode = if hard { CacheMode::Reload } else { CacheMode::NoCache };
+        self.start_request(ctx, PageRequest::get(self.url.clone()).with_cache_mode(cache_mode), client, false);
+    }
+
+    // Flag that cancels requests made for the current navigation, such as its images
+    pub fn cancel_flag(&self) -> CancelFlag {
+        self.cancel.clone()
+    }
+
+    // Back and forward show pages as they were, from the cache when it has them
+    pub fn go_back(&mut self, ctx: &Context, client: &HttpClient) {
+        if let Some(url) = self.navigation.go_back() {
+            self.url = url.to_string();
+            self.load_history_entry(ctx, client);
+        }
+    }
+
+    pub fn go_forward(&mut self, ctx: &Context, client: &HttpClient) {
+        if let Some(url) = self.navigation.go_forward() {
+            self.url = url.to_string();
+            self.load_history_entry(ctx, client);
+        }
+    }
+
+    fn load_history_entry(&mut self, ctx: &Context, client: &HttpClient) {
+        let request = PageRequest::get(self.url.clone()).with_cache_mode(CacheMode::ForceCache);
+        self.start_request(ctx, request, client, false);
+    }
+
+    // Record a new address in history and load it
+    pub fn navigate(&mut self, ctx: &Context, url: String, client: &HttpClient) {
+        self.navigate_to(ctx, PageRequest::get(url), client);
+    }
+
+    // Follow a link on the current page, which decides the cookies sent along
+    pub fn follow_link(&mut self, ctx: &Context, url: String, client: &HttpClient) {
+        let request = PageRequest::get(url).initiated_by(self.initiator());
+        self.navigate_to(ctx, request, client);
+    }
+
+    // Load a request, such as a submitted form, as a new history entry. History
+    // keeps only the URL, so going back to a POST result loads it with GET.
+    pub fn navigate_to(&mut self, ctx: &Context, request: PageRequest, client: &HttpClient) {
+        self.url = request.url.clone();
+        self.navigation.add_url(request.url.clone());
+        self.start_request(ctx, request, client, true);
+    }
+
+    // Requests the current page makes come from its URL
+    fn initiator(&self) -> Initiator {
+        match &self.document {
+            Some(document) => Initiator::Page(document.response.url.clone()),
+            None => Initiator::User,
+        }
+    }
+
+    // The request for a form the user submitted on the current page, if any.
+    // Nothing is sent while one of the form's controls is invalid.
+    pub fn take_form_submission(&mut self) -> Option<PageRequest> {
+        let Some(PageContent::Html(page)) = self.document.as_ref().map(|document| &document.content) else {
+            return None;
+        };
+        let mut state = page.form_state.borrow_mut();
+        let submission = state.submission.take()?;
+        if blocking_control(page, &submission, &mut state).is_some() {
+            return None;
+        }
+        submission_request(page, &submission, &state)
+    }
+
+    // Recheck the current page's form controls after the user changed one
+    pub fn update_form_validity(&self) {
+        if let Some(PageContent::Html(page)) = self.document.as_ref().map(|document| &document.content) {
+            update_validity(page, &mut page.form_state.borrow_mut());
+        }
+    }
+
+    pub fn set_document(&mut self, document: Document) {
+        self.document = Some(document);
+        self.error_message = None;
+        self.load_count += 1;
+    }
+
+    // Start fetching the current page's linked stylesheets
+    pub fn fetch_stylesheets(&mut self, ctx: &Context, client: &HttpClient) {
+        let Some(PageContent::Html(page)) = self.document.as_ref().map(|document| &document.content) else {
+            self.stylesheet_promises.clear();
+            return;
+        };
+
+        let initiator = Initiator::Subresource(page.url.clone());
+        self.stylesheet_promises = page
+            .pending_stylesheets()
+            .into_iter()
+            .filter(|url| is_fetchable(url))
+            .map(|url| {
+                let request = PageRequest::get(url.clone()).initiated_by(initiator.clone());
+                let client = client.clone();
+                let cancel = self.cancel.clone();
+                let ctx_clone = ctx.clone();
+                let promise = Promise::spawn_thread("fetch_stylesheet", move || {
+                    let result = fetch_cancellable(&request, &client, &cancel, &LoadProgress::default());
+                    ctx_clone.request_repaint();
+                    result
+                });
+                (url, promise)
+            })
+            .collect();
+    }
+
+    // Parse stylesheets that finished loading into the current page
+    pub fn poll_stylesheets(&mut self) {
+        for (url, promise) in std::mem::take(&mut self.stylesheet_promises) {
+            let response = match promise.try_take() {
+                Ok(Ok(response)) => response,
+                // A missing stylesheet just leaves the page unstyled
+                Ok(Err(_)) => continue,
+                Err(promise) => {
+                    self.stylesheet_promises.push((url, promise));
+                    continue;
+                }
+            };
+
+            let info = ResponseInfo::from_response(&response);
+            if info.is_error() {
+                continue;
+            }
+            let css = decode_body(&response.bytes, info.content_type()).text;
+            if let Some(PageContent::Html(page)) = self.document.as_mut().map(|document| &mut document.content) {
+                page.set_linked_stylesheet(&url, parse_stylesheet(&css));
+            }
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.fetch_promise.is_some()
+    }
+
+    pub fn pending_stylesheets(&self) -> usize {
+        self.stylesheet_promises.len()
+    }
+
+    // Note the load time once the page and its subresources are all in
+    pub fn finish_load(&mut self, pending_images: usize) {
+        if self.is_loading() || !self.stylesheet_promises.is_empty() || pending_images > 0 {
+            return;
+        }
+        if let Some(started) = self.load_started.take() {
+            self.load_time = Some(started.elapsed());
+        }
+    }
+
+    // How far along the current navigation is, for the progress bar under the
+    // address bar: the page request takes it to 80%, subresources the rest.
+    // None when nothing is loading.
+    pub fn load_fraction(&self, pending_subresources: usize) -> Option<f32> {
+        self.load_started?;
+        if !self.is_loading() {
+            return Some(if pending_subresources > 0 { 0.9 } else { 1.0 });
+        }
+        let phase = self.progress.phase();
+        let page = phase.fraction().unwrap_or(match phase {
+            LoadPhase::Starting => 0.0,
+            LoadPhase::Resolving(_) => 0.05,
+            LoadPhase::Connecting(_) => 0.1,
+            LoadPhase::Receiving { .. } => 0.5,
+        });
+        Some(0.05 + 0.75 * page)
+    }
+
+    // Label for the tab strip: the page title, or the URL while there is none
+    pub fn title(&self) -> String {
+        let title = match self.document.as_ref().map(|document| &document.content) {
+            Some(PageContent::Html(page)) => page.title.clone(),
+            Some(PageContent::About(page)) => Some(page.title().to_string()),
+            _ => None,
+        };
+        title.unwrap_or_else(|| {
+            if self.url.is_empty() {
+                "New Tab".to_string()
+            } else {
+                self.url.clone()
+            }
+        })
+    }
+
+    // Scroll area id for the current page
+    pub fn scroll_id(&self) -> egui::Id {
+        egui::Id::new(("page_scroll", self.id, self.load_count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cookies::CookieJar;
+
+    #[test]
+    fn history_moves_back_and_forward() {
+        let mut history = NavigationHistory::new("http://a/".to_string());
+        history.add_url("http://b/".to_string());
+        history.add_url("http://c/".to_string());
+
+        assert_eq!(history.go_back(), Some("http://b/"));
+        assert_eq!(history.go_back(), Some("http://a/"));
+        assert_eq!(history.go_back(), None);
+        assert_eq!(history.go_forward(), Some("http://b/"));
+    }
+
+    #[test]
+    fn new_navigation_drops_forward_entries() {
+        let mut history = NavigationHistory::new("http://a/".to_string());
+        history.add_url("http://b/".to_string());
+        history.go_back();
+        history.add_url("http://c/".to_string());
+
+        assert!(!history.can_go_forward());
+        assert_eq!(history.go_back(), Some("http://a/"));
+    }
+
+    #[test]
+    fn blank_start_entry_is_replaced() {
+        let mut history = NavigationHistory::new(String::new());
+        history.add_url("http://a/".to_string());
+
+        assert!(!history.can_go_back());
+    }
+
+    #[test]
+    fn tabs_are_titled_by_url_until_loaded() {
+        assert_eq!(Tab::new(0, String::new()).title(), "New Tab");
+        assert_eq!(Tab::new(1, "http://a/".to_string()).title(), "http://a/");
+    }
+
+    #[test]
+    fn about_pages_load_without_a_request() {
+        let mut tab = Tab::new(0, String::new());
+        let client = HttpClient::new("test", CookieJar::default().shared());
+        tab.navigate(&Context::default(), "about:history".to_string(), &client);
+
+        assert!(!tab.is_loading());
+        assert_eq!(tab.title(), "History");
+    }
+
+    #[test]
+    fn stop_cancels_the_navigation() {
+        let mut tab = Tab::new(0, "http://127.0.0.1:9/".to_string());
+        let client = HttpClient::new("test", CookieJar::default().shared());
+        tab.fetch(&Context::default(), &client);
+        let (navigation_id, cancel) = (tab.navigation_id, tab.cancel_flag());
+        assert!(tab.is_loading() && tab.new_visit);
+
+        tab.stop();
+        assert!(!tab.is_loading());
+        assert!(cancel.is_cancelled());
+        assert_ne!(tab.navigation_id, navigation_id);
+
+        // The next load gets requests of its own
+        tab.reload(&Context::default(), &client, true);
+        assert!(tab.is_loading() && !tab.cancel_flag().is_cancelled());
+        // Reloading doesn't count as another visit
+        assert!(!tab.new_visit);
+    }
+
+    #[test]
+    fn load_time_waits_for_subresources() {
+        let mut tab = Tab::new(0, "http://127.0.0.1:9/".to_string());
+        let client = HttpClient::new("test", CookieJar::default().shared());
+        tab.fetch(&Context::default(), &client);
+        assert!(tab.load_fraction(0).is_some_and(|fraction| fraction < 0.8));
+
+        // The page is in, but an image isn't
+        tab.fetch_promise = None;
+        assert_eq!(tab.load_fraction(1), Some(0.9));
+        tab.finish_load(1);
+        assert_eq!(tab.load_time, None);
+
+        tab.finish_load(0);
+        assert!(tab.load_time.is_some());
+        assert_eq!(tab.load_fraction(0), None);
+    }
+}
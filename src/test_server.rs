@@ -0,0 +1,112 @@
+// src/test_server.rs
+// A stand-in HTTP server for tests. It listens on a free local port, answers
+// every request with whatever the handler returns, and records the requests
+// so tests can check what the browser sent.
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    // Path and query, as sent on the request line
+    pub target: String,
+    // Names are lower-case
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct TestResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl TestResponse {
+    pub fn html(body: &str) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), "text/html; charset=utf-8".to_string())],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+}
+
+pub struct TestServer {
+    // e.g. "http://127.0.0.1:40123"
+    pub url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl TestServer {
+    pub fn start(handler: impl Fn(&RecordedRequest) -> TestResponse + Send + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind test server");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        // The thread ends with the test process
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Some(request) = read_request(&stream) {
+                    let response = handler(&request);
+                    recorded.lock().unwrap().push(request);
+                    let _ = write_response(stream, &response);
+                }
+            }
+        });
+        Self { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn read_request(stream: &TcpStream) -> Option<RecordedRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':')?;
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+
+    let length = headers
+        .iter()
+        .find(|(name, _)| name == "content-length")
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    Some(RecordedRequest { method, target, headers, body })
+}
+
+fn write_response(mut stream: TcpStream, response: &TestResponse) -> std::io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} Test\r\n", response.status);
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", response.body.len()));
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(&response.body)
+}
@@ -1,8 +1,18 @@
 // src/ui_components.rs
 use egui::{ScrollArea, TextEdit, Ui};
+use crate::address_bar::{SearchEngine, SearchEngines, Suggestion, SuggestionKind};
+use crate::app::LinkClick;
+use crate::tab::Tab;
+use crate::bookmarks::{BookmarkItem, Bookmarks, Folder, OTHER_ID};
+use crate::cookies::SharedCookieJar;
+use crate::document::{Document, HtmlPage};
+use crate::fetch::ResponseInfo;
+use crate::dates::{civil_from_days, days_from_civil};
+use crate::forms::{format_date, parse_date};
+use crate::history::GlobalHistory;
 use crate::html_renderer::HtmlRenderer;
 
-pub fn render_html_content(ui: &mut Ui, html_parser: &html_parser::Dom, html_renderer: &HtmlRenderer) {
+pub fn render_html_content(ui: &mut Ui, document: &Document, page: &HtmlPage, html_renderer: &HtmlRenderer, scroll_id: egui::Id) {
     ui.separator();
     
     // Create a frame with white background for rendered HTML
@@ -11,19 +21,97 @@ pub fn render_html_content(ui: &mut Ui, html_parser: &html_parser::Dom, html_ren
         .inner_margin(egui::style::Margin::same(10.0));
         
     html_frame.show(ui, |ui| {
-        ScrollArea::vertical().show(ui, |ui| {
-            // Find the body tag and get filtered content for rendering
-            if let Some(filtered_body) = html_renderer.find_body_element(&html_parser.children) {
-                html_renderer.render_html_node(ui, &filtered_body);
-            } else {
-                // If no body tag is found, filter and render everything
-                let filtered_content = html_renderer.filter_nodes(&html_parser.children);
-                html_renderer.render_html_node(ui, &filtered_content);
-            }
+        // Error statuses get a banner, but the server's error page is still rendered
+        if document.response.is_error() {
+            ui.colored_label(
+                egui::Color32::from_rgb(178, 34, 34),
+                egui::RichText::new(format!("HTTP {}", document.response.status_line())).strong(),
+            );
+            ui.separator();
+        }
+        
+        ScrollArea::vertical().id_source(scroll_id).show(ui, |ui| {
+            // Body was found and filtered once when the document was loaded
+            html_renderer.render_document(ui, page);
         });
     });
 }
 
+// Viewer for a response that is itself an image
+pub fn render_image_view(ui: &mut Ui, response: &ResponseInfo, image: Option<(egui::TextureId, egui::Vec2)>, scroll_id: egui::Id) {
+    ui.separator();
+    
+    let Some((texture_id, size)) = image else {
+        ui.label("[Image not available]");
+        return;
+    };
+    
+    ui.label(format!("Image, {} × {} pixels — {}", size.x, size.y, response.url));
+    
+    ScrollArea::both().id_source(scroll_id).show(ui, |ui| {
+        // Scale down to fit the available width, never up
+        let available_width = ui.available_width();
+        let display_size = if size.x > available_width {
+            size * (available_width / size.x)
+        } else {
+            size
+        };
+        ui.add(egui::Image::new((texture_id, display_size)));
+    });
+}
+
+// Viewer for plain text, JSON and other textual responses
+pub fn render_text_view(ui: &mut Ui, text: &str, scroll_id: egui::Id) {
+    ui.separator();
+    
+    ScrollArea::both().id_source(scroll_id).show(ui, |ui| {
+        // A &str buffer keeps the text selectable but read-only
+        let mut text = text;
+        ui.add(
+            TextEdit::multiline(&mut text)
+                .font(egui::TextStyle::Monospace)
+                .desired_width(f32::INFINITY),
+        );
+    });
+}
+
+// Shown for content types no viewer handles, with an offer to save the bytes
+pub fn render_unsupported_view(ui: &mut Ui, response: &ResponseInfo, mime: &str, bytes: &[u8]) {
+    ui.separator();
+    
+    let mime = if mime.is_empty() { "unknown type" } else { mime };
+    ui.label(format!("Cannot display content of type {} ({} bytes).", mime, bytes.len()));
+    
+    let status_id = ui.id().with("save_status");
+    if ui.button("Save to disk...").clicked() {
+        let status = match rfd::FileDialog::new()
+            .set_file_name(suggested_file_name(&response.url))
+            .save_file()
+        {
+            Some(path) => match std::fs::write(&path, bytes) {
+                Ok(()) => format!("Saved to {}", path.display()),
+                Err(err) => format!("Failed to save: {}", err),
+            },
+            None => String::new(),
+        };
+        ui.data_mut(|data| data.insert_temp(status_id, status));
+    }
+    
+    if let Some(status) = ui.data(|data| data.get_temp::<String>(status_id)) {
+        ui.label(status);
+    }
+}
+
+// Last path segment of a URL, used as the default file name when saving
+fn suggested_file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or("");
+    match path.rsplit('/').next() {
+        // More than the two slashes of "scheme://" means there is a path
+        Some(name) if !name.is_empty() && path.matches('/').count() > 2 => name.to_string(),
+        _ => "download".to_string(),
+    }
+}
+
 pub fn render_raw_html_view(ui: &mut Ui, html: &str, show_html: &mut bool) {
     ui.separator();
     
@@ -38,4 +126,498 @@ pub fn render_raw_html_view(ui: &mut Ui, html: &str, show_html: &mut bool) {
         let mut text = html.to_string();
         ui.add(TextEdit::multiline(&mut text).desired_width(f32::INFINITY).desired_rows(10));
     }
-}
\ No newline at end of file
+}
+
+pub fn render_response_info(ui: &mut Ui, response: &ResponseInfo) {
+    // Collapsed by default so it doesn't take space from the page
+    ui.collapsing("Response Headers", |ui| {
+        ui.label(format!("URL: {}", response.url));
+        ui.label(format!("Status: {}", response.status_line()));
+        egui::Grid::new("response_headers").striped(true).show(ui, |ui| {
+            for (name, value) in &response.headers {
+                ui.monospace(name);
+                ui.label(value);
+                ui.end_row();
+            }
+        });
+    });
+}
+
+// Settings window listing stored cookies by site, with buttons to delete
+// single cookies, a whole site's, or all of them
+pub fn render_cookie_settings(ctx: &egui::Context, open: &mut bool, cookies: &SharedCookieJar) {
+    egui::Window::new("Cookies").open(open).default_width(520.0).show(ctx, |ui| {
+        let mut jar = cookies.lock().unwrap();
+        let sites = jar.sites();
+        ui.horizontal(|ui| {
+            ui.label(format!("{} cookies from {} sites", sites.iter().map(|(_, cookies)| cookies.len()).sum::<usize>(), sites.len()));
+            if ui.add_enabled(!sites.is_empty(), egui::Button::new("Remove All")).clicked() {
+                jar.clear();
+            }
+        });
+        ui.separator();
+
+        ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            for (site, cookies) in &sites {
+                ui.horizontal(|ui| {
+                    ui.strong(site);
+                    ui.label(format!("({})", cookies.len()));
+                    if ui.small_button("Remove").clicked() {
+                        jar.remove_site(site);
+                    }
+                });
+                egui::CollapsingHeader::new("Details").id_source(("cookie_site", site)).show(ui, |ui| {
+                    egui::Grid::new(("cookies", site)).striped(true).show(ui, |ui| {
+                        for cookie in cookies {
+                            ui.monospace(&cookie.name);
+                            // Values can be long tokens; the full one is in the tooltip
+                            let value: String = cookie.value.chars().take(24).collect();
+                            ui.label(value).on_hover_text(&cookie.value);
+                            let domain = if cookie.host_only { cookie.domain.clone() } else { format!(".{}", cookie.domain) };
+                            ui.label(format!("{}{}", domain, cookie.path));
+                            let expires = match cookie.expires {
+                                Some(expires) => format_date(civil_from_days(expires.div_euclid(86_400))),
+                                None => "Session".to_string(),
+                            };
+                            ui.label(expires);
+                            let mut flags = vec![format!("SameSite={:?}", cookie.same_site)];
+                            if cookie.secure {
+                                flags.push("Secure".to_string());
+                            }
+                            if cookie.http_only {
+                                flags.push("HttpOnly".to_string());
+                            }
+                            ui.label(flags.join(" "));
+                            if ui.small_button("x").on_hover_text("Remove cookie").clicked() {
+                                jar.remove(cookie);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+        });
+    });
+}
+
+// Which visits the history page's delete button removes
+#[derive(Clone, Copy, PartialEq)]
+pub enum HistoryRange {
+    LastHour,
+    Today,
+    LastWeek,
+    LastFourWeeks,
+    Custom,
+    Everything,
+}
+
+impl HistoryRange {
+    const ALL: [HistoryRange; 6] = [
+        HistoryRange::LastHour,
+        HistoryRange::Today,
+        HistoryRange::LastWeek,
+        HistoryRange::LastFourWeeks,
+        HistoryRange::Custom,
+        HistoryRange::Everything,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            HistoryRange::LastHour => "Last hour",
+            HistoryRange::Today => "Today",
+            HistoryRange::LastWeek => "Last 7 days",
+            HistoryRange::LastFourWeeks => "Last 4 weeks",
+            HistoryRange::Custom => "Dates...",
+            HistoryRange::Everything => "Everything",
+        }
+    }
+}
+
+// What the user has typed and picked on about:history
+pub struct HistoryView {
+    pub query: String,
+    pub range: HistoryRange,
+    // Inclusive dates for a custom range, as YYYY-MM-DD
+    pub from: String,
+    pub to: String,
+}
+
+impl Default for HistoryView {
+    fn default() -> Self {
+        Self {
+            query: String::new(),
+            range: HistoryRange::LastHour,
+            from: String::new(),
+            to: String::new(),
+        }
+    }
+}
+
+impl HistoryView {
+    // Unix time span the selected range covers, end exclusive
+    fn span(&self, now: i64) -> Option<(i64, i64)> {
+        let midnight = now - now.rem_euclid(86_400);
+        let day_start = |date: &str| parse_date(date).map(|(year, month, day)| days_from_civil(year as i64, month, day) * 86_400);
+        match self.range {
+            HistoryRange::LastHour => Some((now - 3600, i64::MAX)),
+            HistoryRange::Today => Some((midnight, i64::MAX)),
+            HistoryRange::LastWeek => Some((midnight - 6 * 86_400, i64::MAX)),
+            HistoryRange::LastFourWeeks => Some((midnight - 27 * 86_400, i64::MAX)),
+            HistoryRange::Custom => Some((day_start(&self.from)?, day_start(&self.to)? + 86_400)),
+            HistoryRange::Everything => Some((i64::MIN, i64::MAX)),
+        }
+    }
+}
+
+// The about:history page: search, per-entry and date-range deletion, and the
+// visited pages grouped by day. Returns a URL the user clicked.
+pub fn render_history_page(ui: &mut Ui, history: &mut GlobalHistory, view: &mut HistoryView, now: i64, scroll_id: egui::Id) -> Option<String> {
+    ui.separator();
+    ui.heading("History");
+    ui.horizontal(|ui| {
+        ui.label("Search:");
+        ui.add(TextEdit::singleline(&mut view.query).hint_text("Title or address").desired_width(300.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Delete visits from:");
+        egui::ComboBox::from_id_source("history_range")
+            .selected_text(view.range.label())
+            .show_ui(ui, |ui| {
+                for range in HistoryRange::ALL {
+                    ui.selectable_value(&mut view.range, range, range.label());
+                }
+            });
+        if view.range == HistoryRange::Custom {
+            ui.add(TextEdit::singleline(&mut view.from).hint_text("YYYY-MM-DD").desired_width(90.0));
+            ui.label("to");
+            ui.add(TextEdit::singleline(&mut view.to).hint_text("YYYY-MM-DD").desired_width(90.0));
+        }
+        let span = view.span(now);
+        if ui.add_enabled(span.is_some(), egui::Button::new("Delete")).clicked() {
+            if let Some((from, to)) = span {
+                history.remove_range(from, to);
+            }
+        }
+    });
+    ui.weak("Times are UTC.");
+    ui.separator();
+
+    let mut opened = None;
+    let mut removed = None;
+    ScrollArea::vertical().id_source(scroll_id).auto_shrink([false, false]).show(ui, |ui| {
+        let entries = history.search(&view.query);
+        if entries.is_empty() {
+            ui.label(if view.query.trim().is_empty() { "No pages visited yet." } else { "No pages match." });
+        }
+        let mut current_day = None;
+        for entry in entries {
+            let visit = entry.last_visit();
+            let day = visit.div_euclid(86_400);
+            if current_day != Some(day) {
+                current_day = Some(day);
+                ui.add_space(6.0);
+                ui.strong(format_date(civil_from_days(day)));
+            }
+            ui.horizontal(|ui| {
+                let minutes = visit.rem_euclid(86_400) / 60;
+                ui.monospace(format!("{:02}:{:02}", minutes / 60, minutes % 60));
+                if ui.link(entry.title.as_deref().unwrap_or(&entry.url)).on_hover_text(&entry.url).clicked() {
+                    opened = Some(entry.url.clone());
+                }
+                if entry.title.is_some() {
+                    ui.weak(&entry.url);
+                }
+                let count = entry.visit_count();
+                ui.weak(if count == 1 { "1 visit".to_string() } else { format!("{} visits", count) });
+                if ui.small_button("x").on_hover_text("Remove from history").clicked() {
+                    removed = Some(entry.url.clone());
+                }
+            });
+        }
+    });
+    if let Some(url) = removed {
+        history.remove(&url);
+    }
+    opened
+}
+
+// The bookmarks bar: a button per bookmark and a menu per folder. Returns
+// the bookmark the user clicked.
+pub fn render_bookmarks_bar(ui: &mut Ui, bar: &Folder) -> Option<LinkClick> {
+    let mut clicked = None;
+    if bar.children.is_empty() {
+        ui.weak("Bookmark pages to show them here");
+    }
+    for item in &bar.children {
+        bookmark_button(ui, item, &mut clicked);
+    }
+    clicked
+}
+
+fn bookmark_button(ui: &mut Ui, item: &BookmarkItem, clicked: &mut Option<LinkClick>) {
+    match item {
+        BookmarkItem::Bookmark(bookmark) => {
+            let title = if bookmark.title.trim().is_empty() { &bookmark.url } else { &bookmark.title };
+            let label: String = title.chars().take(24).collect();
+            let response = ui.button(label).on_hover_text(&bookmark.url);
+            // Middle-click or Ctrl+click opens a background tab, as links do
+            let new_tab = response.middle_clicked() || (response.clicked() && ui.input(|i| i.modifiers.command));
+            if response.clicked() || new_tab {
+                *clicked = Some(LinkClick {
+                    url: bookmark.url.clone(),
+                    new_tab,
+                });
+                ui.close_menu();
+            }
+        }
+        BookmarkItem::Folder(folder) => {
+            ui.menu_button(&folder.title, |ui| {
+                if folder.children.is_empty() {
+                    ui.weak("(empty)");
+                }
+                for child in &folder.children {
+                    bookmark_button(ui, child, clicked);
+                }
+            });
+        }
+    }
+}
+
+// Changes picked in the bookmark manager, applied once the tree is drawn
+enum BookmarkAction {
+    Open(String),
+    MoveBy(u64, isize),
+    MoveTo(u64, u64),
+    Remove(u64),
+}
+
+// The about:bookmarks manager: rename, edit, move and delete bookmarks and
+// folders, and import or export bookmark files. Returns a URL to open.
+pub fn render_bookmarks_page(ui: &mut Ui, bookmarks: &mut Bookmarks, scroll_id: egui::Id) -> Option<String> {
+    ui.separator();
+    ui.heading("Bookmarks");
+
+    let status_id = ui.id().with("bookmarks_status");
+    ui.horizontal(|ui| {
+        if ui.button("New Folder").clicked() {
+            bookmarks.add_folder(OTHER_ID, "New Folder", crate::cookies::unix_now());
+        }
+        if ui.button("Import...").clicked() {
+            let file = rfd::FileDialog::new().add_filter("Bookmark files", &["html", "htm"]).pick_file();
+            if let Some(file) = file {
+                let status = match std::fs::read(&file) {
+                    Ok(bytes) => format!("Imported {} bookmarks", bookmarks.import_html(&String::from_utf8_lossy(&bytes))),
+                    Err(err) => format!("Failed to import: {}", err),
+                };
+                ui.data_mut(|data| data.insert_temp(status_id, status));
+            }
+        }
+        if ui.button("Export...").clicked() {
+            if let Some(file) = rfd::FileDialog::new().set_file_name("bookmarks.html").save_file() {
+                let status = match std::fs::write(&file, bookmarks.export_html()) {
+                    Ok(()) => format!("Exported to {}", file.display()),
+                    Err(err) => format!("Failed to export: {}", err),
+                };
+                ui.data_mut(|data| data.insert_temp(status_id, status));
+            }
+        }
+        if let Some(status) = ui.data(|data| data.get_temp::<String>(status_id)) {
+            ui.label(status);
+        }
+    });
+    ui.separator();
+
+    let folders = bookmarks.folders();
+    let mut actions = Vec::new();
+    let mut changed = false;
+    ScrollArea::vertical().id_source(scroll_id).auto_shrink([false, false]).show(ui, |ui| {
+        for root in [&mut bookmarks.bar, &mut bookmarks.other] {
+            ui.strong(&root.title);
+            bookmark_rows(ui, &mut root.children, 1, &folders, &mut actions, &mut changed);
+            ui.add_space(8.0);
+        }
+    });
+    if changed {
+        bookmarks.save();
+    }
+
+    let mut opened = None;
+    for action in actions {
+        match action {
+            BookmarkAction::Open(url) => opened = Some(url),
+            BookmarkAction::MoveBy(id, offset) => bookmarks.move_by(id, offset),
+            BookmarkAction::MoveTo(id, folder) => bookmarks.move_to(id, folder),
+            BookmarkAction::Remove(id) => bookmarks.remove(id),
+        }
+    }
+    opened
+}
+
+// One editable row per item, folders followed by their contents
+fn bookmark_rows(
+    ui: &mut Ui,
+    items: &mut [BookmarkItem],
+    depth: usize,
+    folders: &[(u64, String, usize)],
+    actions: &mut Vec<BookmarkAction>,
+    changed: &mut bool,
+) {
+    for item in items {
+        let id = item.id();
+        ui.horizontal(|ui| {
+            ui.add_space(depth as f32 * 16.0);
+            match item {
+                BookmarkItem::Bookmark(bookmark) => {
+                    *changed |= ui.add(TextEdit::singleline(&mut bookmark.title).hint_text("Title").desired_width(180.0)).changed();
+                    *changed |= ui.add(TextEdit::singleline(&mut bookmark.url).hint_text("URL").desired_width(260.0)).changed();
+                    *changed |= ui
+                        .add(TextEdit::singleline(&mut bookmark.keyword).hint_text("Keyword").desired_width(60.0))
+                        .on_hover_text("Type the keyword in the address bar to open this bookmark; %s in the URL is replaced by what follows it")
+                        .changed();
+                    if ui.small_button("Open").clicked() {
+                        actions.push(BookmarkAction::Open(bookmark.url.clone()));
+                    }
+                }
+                BookmarkItem::Folder(folder) => {
+                    ui.label("Folder:");
+                    *changed |= ui.add(TextEdit::singleline(&mut folder.title).hint_text("Folder name").desired_width(180.0)).changed();
+                }
+            }
+            if ui.small_button("Up").clicked() {
+                actions.push(BookmarkAction::MoveBy(id, -1));
+            }
+            if ui.small_button("Down").clicked() {
+                actions.push(BookmarkAction::MoveBy(id, 1));
+            }
+            egui::ComboBox::from_id_source(("move_bookmark", id)).selected_text("Move to").show_ui(ui, |ui| {
+                for (folder, title, folder_depth) in folders {
+                    if *folder != id && ui.selectable_label(false, format!("{}{}", "   ".repeat(*folder_depth), title)).clicked() {
+                        actions.push(BookmarkAction::MoveTo(id, *folder));
+                    }
+                }
+            });
+            if ui.small_button("Delete").clicked() {
+                actions.push(BookmarkAction::Remove(id));
+            }
+        });
+        if let BookmarkItem::Folder(folder) = item {
+            bookmark_rows(ui, &mut folder.children, depth + 1, folders, actions, changed);
+        }
+    }
+}
+
+// The address bar's suggestion dropdown, drawn over the page below the text
+// field. Returns the suggestion clicked, and whether the pointer is over it.
+pub fn render_suggestions(ctx: &egui::Context, below: egui::Rect, suggestions: &[Suggestion], selected: Option<usize>) -> (Option<usize>, bool) {
+    if suggestions.is_empty() || !below.is_positive() {
+        return (None, false);
+    }
+    let mut clicked = None;
+    let area = egui::Area::new("address_suggestions")
+        .order(egui::Order::Foreground)
+        .fixed_pos(below.left_bottom())
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_width(below.width().max(400.0));
+                for (index, suggestion) in suggestions.iter().enumerate() {
+                    let kind = match suggestion.kind {
+                        SuggestionKind::Navigate => "Go to",
+                        SuggestionKind::Search => "Search",
+                        SuggestionKind::Tab(_) => "Switch to tab",
+                        SuggestionKind::Bookmark => "Bookmark",
+                        SuggestionKind::History => "History",
+                    };
+                    let style = ui.style();
+                    let font = egui::TextStyle::Body.resolve(style);
+                    let mut text = egui::text::LayoutJob::default();
+                    let title = if suggestion.title.is_empty() { &suggestion.url } else { &suggestion.title };
+                    text.append(title, 0.0, egui::TextFormat::simple(font.clone(), style.visuals.text_color()));
+                    if !matches!(suggestion.kind, SuggestionKind::Navigate | SuggestionKind::Search) && !suggestion.title.is_empty() {
+                        text.append(&suggestion.url, 12.0, egui::TextFormat::simple(font.clone(), style.visuals.weak_text_color()));
+                    }
+                    text.append(kind, 12.0, egui::TextFormat::simple(font, style.visuals.weak_text_color()));
+                    if ui.selectable_label(selected == Some(index), text).clicked() {
+                        clicked = Some(index);
+                    }
+                }
+            });
+        });
+    let hovered = ctx.input(|i| i.pointer.hover_pos().is_some_and(|pointer| area.response.rect.contains(pointer)));
+    (clicked, hovered)
+}
+
+// Settings window for the address bar's search engines: name, query template
+// with %s for the terms, and which one is used
+pub fn render_search_engine_settings(ctx: &egui::Context, open: &mut bool, engines: &mut SearchEngines) {
+    egui::Window::new("Search Engines").open(open).default_width(560.0).show(ctx, |ui| {
+        let mut changed = false;
+        let mut removed = None;
+        egui::Grid::new("search_engines").striped(true).show(ui, |ui| {
+            ui.strong("Default");
+            ui.strong("Name");
+            ui.strong("Query URL (%s = search terms)");
+            ui.end_row();
+            for (index, engine) in engines.engines.iter_mut().enumerate() {
+                changed |= ui.radio_value(&mut engines.default, index, "").changed();
+                changed |= ui.add(TextEdit::singleline(&mut engine.name).desired_width(120.0)).changed();
+                changed |= ui.add(TextEdit::singleline(&mut engine.template).desired_width(320.0)).changed();
+                if ui.small_button("Remove").clicked() {
+                    removed = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = removed.filter(|_| engines.engines.len() > 1) {
+            engines.engines.remove(index);
+            if engines.default >= index && engines.default > 0 {
+                engines.default -= 1;
+            }
+            changed = true;
+        }
+        if ui.button("Add Search Engine").clicked() {
+            engines.engines.push(SearchEngine {
+                name: "New engine".to_string(),
+                template: "https://example.com/search?q=%s".to_string(),
+            });
+            changed = true;
+        }
+        if changed {
+            engines.save();
+        }
+    });
+}
+
+// Status bar under the page: a message such as the hovered link's URL, or
+// the load phase, then the time the last load took; subresources still
+// loading on the right
+pub fn render_status_bar(ui: &mut egui::Ui, tab: &Tab, message: Option<&str>, pending_subresources: usize) {
+    ui.horizontal(|ui| {
+        let status = match message {
+            Some(message) => message.to_string(),
+            None if tab.is_loading() => format!("{}...", tab.progress.phase().description()),
+            None => match (&tab.error_message, tab.load_time) {
+                (Some(_), _) => "Failed".to_string(),
+                (None, Some(time)) => format!("Done in {:.2} s", time.as_secs_f64()),
+                (None, None) => String::new(),
+            },
+        };
+        ui.add(egui::Label::new(egui::RichText::new(status).small()).truncate(true));
+        if pending_subresources > 0 {
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let plural = if pending_subresources == 1 { "" } else { "s" };
+                ui.small(format!("{} resource{} loading", pending_subresources, plural));
+            });
+        }
+    });
+}
+
+// A thin bar across the window, filled to `fraction`; the space stays when
+// there's nothing to show so the page doesn't move
+pub fn render_progress_line(ui: &mut egui::Ui, fraction: Option<f32>) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 2.0), egui::Sense::hover());
+    if let Some(fraction) = fraction {
+        let mut filled = rect;
+        filled.set_width(rect.width() * fraction.clamp(0.0, 1.0));
+        ui.painter().rect_filled(filled, 0.0, ui.visuals().selection.bg_fill);
+    }
+}
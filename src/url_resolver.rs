@@ -0,0 +1,471 @@
+// src/url_resolver.rs
+// Reference resolution as described in RFC 3986 section 5. Every place that turns
+// an attribute (href, src, action, <base href>) into a fetchable URL goes through here.
+use std::path::{Path, PathBuf};
+
+// The five components of a URI reference (RFC 3986 appendix B)
+#[derive(Debug, Default, PartialEq)]
+pub struct UrlParts<'a> {
+    pub scheme: Option<&'a str>,
+    pub authority: Option<&'a str>,
+    pub path: &'a str,
+    pub query: Option<&'a str>,
+    pub fragment: Option<&'a str>,
+}
+
+impl<'a> UrlParts<'a> {
+    // Split a reference into its components, mirroring the regex from appendix B:
+    // ^(([^:/?#]+):)?(//([^/?#]*))?([^?#]*)(\?([^#]*))?(#(.*))?
+    pub fn parse(input: &'a str) -> Self {
+        let mut rest = input;
+        let mut parts = UrlParts::default();
+
+        if let Some(hash) = rest.find('#') {
+            parts.fragment = Some(&rest[hash + 1..]);
+            rest = &rest[..hash];
+        }
+
+        if let Some(question) = rest.find('?') {
+            parts.query = Some(&rest[question + 1..]);
+            rest = &rest[..question];
+        }
+
+        if let Some(colon) = rest.find(':') {
+            let candidate = &rest[..colon];
+            if is_valid_scheme(candidate) {
+                parts.scheme = Some(candidate);
+                rest = &rest[colon + 1..];
+            }
+        }
+
+        if let Some(after_slashes) = rest.strip_prefix("//") {
+            let end = after_slashes.find('/').unwrap_or(after_slashes.len());
+            parts.authority = Some(&after_slashes[..end]);
+            rest = &after_slashes[end..];
+        }
+
+        parts.path = rest;
+        parts
+    }
+}
+
+// Recompose components into a string (RFC 3986 section 5.3)
+fn recompose(
+    scheme: Option<&str>,
+    authority: Option<&str>,
+    path: &str,
+    query: Option<&str>,
+    fragment: Option<&str>,
+) -> String {
+    let mut result = String::new();
+    if let Some(scheme) = scheme {
+        result.push_str(&scheme.to_ascii_lowercase());
+        result.push(':');
+    }
+    if let Some(authority) = authority {
+        result.push_str("//");
+        result.push_str(authority);
+    }
+    result.push_str(path);
+    if let Some(query) = query {
+        result.push('?');
+        result.push_str(query);
+    }
+    if let Some(fragment) = fragment {
+        result.push('#');
+        result.push_str(fragment);
+    }
+    result
+}
+
+fn is_valid_scheme(candidate: &str) -> bool {
+    let mut chars = candidate.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+}
+
+// Remove "." and ".." segments from a path (RFC 3986 section 5.2.4)
+pub fn remove_dot_segments(path: &str) -> String {
+    let mut input = path;
+    let mut output: Vec<&str> = Vec::new();
+
+    while !input.is_empty() {
+        if let Some(rest) = input.strip_prefix("../") {
+            input = rest;
+        } else if let Some(rest) = input.strip_prefix("./") {
+            input = rest;
+        } else if input.starts_with("/./") {
+            input = &input[2..];
+        } else if input == "/." {
+            input = "/";
+        } else if input.starts_with("/../") {
+            input = &input[3..];
+            output.pop();
+        } else if input == "/.." {
+            input = "/";
+            output.pop();
+        } else if input == "." || input == ".." {
+            input = "";
+        } else {
+            // Move the first path segment (including any leading "/") to the output
+            let start = usize::from(input.starts_with('/'));
+            let end = input[start..].find('/').map(|i| i + start).unwrap_or(input.len());
+            output.push(&input[..end]);
+            input = &input[end..];
+        }
+    }
+
+    output.concat()
+}
+
+// Merge a relative-path reference with the base path (RFC 3986 section 5.2.3)
+fn merge_paths(base: &UrlParts, reference_path: &str) -> String {
+    if base.authority.is_some() && base.path.is_empty() {
+        format!("/{}", reference_path)
+    } else {
+        match base.path.rfind('/') {
+            Some(last_slash) => format!("{}{}", &base.path[..=last_slash], reference_path),
+            None => reference_path.to_string(),
+        }
+    }
+}
+
+// Strip characters browsers ignore in attribute URLs: surrounding whitespace and
+// embedded tabs/newlines
+fn clean_reference(reference: &str) -> String {
+    reference
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .collect()
+}
+
+// Resolve a reference against a base URL (RFC 3986 section 5.2.2)
+pub fn resolve_url(base: &str, reference: &str) -> String {
+    let reference = clean_reference(reference);
+    let base = UrlParts::parse(base);
+    let mut r = UrlParts::parse(&reference);
+
+    // Backward-compatible behaviour allowed by section 5.2.2 and used by browsers:
+    // "http:g" against an http base is treated as a relative reference
+    if r.scheme.is_some()
+        && r.authority.is_none()
+        && r.scheme.map(str::to_ascii_lowercase) == base.scheme.map(str::to_ascii_lowercase)
+        && matches!(base.scheme.map(str::to_ascii_lowercase).as_deref(), Some("http") | Some("https"))
+    {
+        r.scheme = None;
+    }
+
+    if r.scheme.is_some() {
+        let path = remove_dot_segments(r.path);
+        return recompose(r.scheme, r.authority, &path, r.query, r.fragment);
+    }
+
+    if r.authority.is_some() {
+        let path = remove_dot_segments(r.path);
+        return recompose(base.scheme, r.authority, &path, r.query, r.fragment);
+    }
+
+    if r.path.is_empty() {
+        let query = r.query.or(base.query);
+        return recompose(base.scheme, base.authority, base.path, query, r.fragment);
+    }
+
+    let path = if r.path.starts_with('/') {
+        remove_dot_segments(r.path)
+    } else {
+        remove_dot_segments(&merge_paths(&base, r.path))
+    };
+    recompose(base.scheme, base.authority, &path, r.query, r.fragment)
+}
+
+// Scheme of an absolute URL, lowercased
+pub fn scheme_of(url: &str) -> Option<String> {
+    UrlParts::parse(url).scheme.map(str::to_ascii_lowercase)
+}
+
+// Host of an absolute URL, lowercased, without userinfo or port. IPv6
+// addresses keep their brackets.
+pub fn host_of(url: &str) -> Option<String> {
+    let authority = UrlParts::parse(url).authority?;
+    let host_port = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let host = if host_port.starts_with('[') {
+        host_port.find(']').map_or(host_port, |end| &host_port[..=end])
+    } else {
+        host_port.split(':').next().unwrap_or_default()
+    };
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+// Whether a resolved URL is something our fetch pipeline can load
+pub fn is_fetchable(url: &str) -> bool {
+    matches!(scheme_of(url).as_deref(), Some("http") | Some("https"))
+}
+
+// Bytes of a data: URL (RFC 2397), base64 or percent-encoded
+pub fn decode_data_url(url: &str) -> Option<Vec<u8>> {
+    if scheme_of(url).as_deref() != Some("data") {
+        return None;
+    }
+    let (meta, data) = url[5..].split_once(',')?;
+    if meta.trim().to_ascii_lowercase().ends_with(";base64") {
+        decode_base64(data)
+    } else {
+        Some(percent_decode(data))
+    }
+}
+
+// Standard base64, ignoring whitespace and padding
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for byte in text.bytes().filter(|byte| !byte.is_ascii_whitespace() && *byte != b'=') {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+// Replace %XX escapes with the bytes they stand for
+pub fn percent_decode(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok()).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+// The file: URL of an absolute local path, each segment percent-encoded so
+// spaces, `%` and `#` in names survive resolution
+pub fn file_url_from_path(path: &Path) -> String {
+    path_to_file_url(&path.to_string_lossy(), cfg!(windows))
+}
+
+fn path_to_file_url(path: &str, windows: bool) -> String {
+    let path = if windows {
+        // canonicalize gives verbatim paths like \\?\C:\dir and \\?\UNC\server\share
+        let path = match path.strip_prefix(r"\\?\UNC\") {
+            Some(share) => format!(r"\\{}", share),
+            None => path.strip_prefix(r"\\?\").unwrap_or(path).to_string(),
+        };
+        let path = path.replace('\\', "/");
+        // C:/dir becomes /C:/dir; shares keep their leading //server
+        if path.starts_with('/') { path } else { format!("/{}", path) }
+    } else {
+        path.to_string()
+    };
+
+    let mut url = String::from(if path.starts_with("//") { "file:" } else { "file://" });
+    for byte in path.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => url.push(byte as char),
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+// The local path a file: URL names, without its query or fragment; None for
+// other schemes and for hosts other than this machine
+pub fn file_url_to_path(url: &str) -> Option<PathBuf> {
+    file_url_to_path_text(url, cfg!(windows)).map(PathBuf::from)
+}
+
+fn file_url_to_path_text(url: &str, windows: bool) -> Option<String> {
+    if scheme_of(url).as_deref() != Some("file") {
+        return None;
+    }
+    let rest = &url[5..];
+    let rest = &rest[..rest.find(['?', '#']).unwrap_or(rest.len())];
+    let (host, path) = match rest.strip_prefix("//") {
+        Some(authority) => authority.split_at(authority.find('/').unwrap_or(authority.len())),
+        None => ("", rest),
+    };
+    let path = String::from_utf8(percent_decode(path)).ok()?;
+    if !windows {
+        return (host.is_empty() || host.eq_ignore_ascii_case("localhost")).then_some(path);
+    }
+    let path = path.replace('/', "\\");
+    if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+        return Some(format!(r"\\{}{}", host, path));
+    }
+    // \C:\dir is C:\dir
+    let drive = path.as_bytes().get(1..3).is_some_and(|drive| drive[0].is_ascii_alphabetic() && drive[1] == b':');
+    Some(if drive { path[1..].to_string() } else { path })
+}
+
+// Compute the base URL of a document: the first <base href> resolved against the
+// document URL, or the document URL itself
+pub fn document_base_url(document_url: &str, nodes: &[html_parser::Node]) -> String {
+    match find_base_href(nodes) {
+        Some(href) => resolve_url(document_url, &href),
+        None => document_url.to_string(),
+    }
+}
+
+fn find_base_href(nodes: &[html_parser::Node]) -> Option<String> {
+    for node in nodes {
+        if let html_parser::Node::Element(element) = node {
+            if element.name.eq_ignore_ascii_case("base") {
+                if let Some(Some(href)) = element.attributes.get("href") {
+                    return Some(href.clone());
+                }
+            }
+            if let Some(href) = find_base_href(&element.children) {
+                return Some(href);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "http://a/b/c/d;p?q";
+
+    // RFC 3986 section 5.4.1
+    #[test]
+    fn resolves_normal_examples() {
+        let cases = [
+            ("g:h", "g:h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("g#s", "http://a/b/c/g#s"),
+            ("g?y#s", "http://a/b/c/g?y#s"),
+            (";x", "http://a/b/c/;x"),
+            ("g;x", "http://a/b/c/g;x"),
+            ("g;x?y#s", "http://a/b/c/g;x?y#s"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("./", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../", "http://a/"),
+            ("../../g", "http://a/g"),
+        ];
+        for (reference, expected) in cases {
+            assert_eq!(resolve_url(BASE, reference), expected, "reference {:?}", reference);
+        }
+    }
+
+    // RFC 3986 section 5.4.2
+    #[test]
+    fn resolves_abnormal_examples() {
+        let cases = [
+            ("../../../g", "http://a/g"),
+            ("../../../../g", "http://a/g"),
+            ("/./g", "http://a/g"),
+            ("/../g", "http://a/g"),
+            ("g.", "http://a/b/c/g."),
+            (".g", "http://a/b/c/.g"),
+            ("g..", "http://a/b/c/g.."),
+            ("..g", "http://a/b/c/..g"),
+            ("./../g", "http://a/b/g"),
+            ("./g/.", "http://a/b/c/g/"),
+            ("g/./h", "http://a/b/c/g/h"),
+            ("g/../h", "http://a/b/c/h"),
+            ("g;x=1/./y", "http://a/b/c/g;x=1/y"),
+            ("g;x=1/../y", "http://a/b/c/y"),
+            ("g?y/./x", "http://a/b/c/g?y/./x"),
+            ("g?y/../x", "http://a/b/c/g?y/../x"),
+            ("g#s/./x", "http://a/b/c/g#s/./x"),
+            ("g#s/../x", "http://a/b/c/g#s/../x"),
+            // Backward-compatible result, as browsers do
+            ("http:g", "http://a/b/c/g"),
+        ];
+        for (reference, expected) in cases {
+            assert_eq!(resolve_url(BASE, reference), expected, "reference {:?}", reference);
+        }
+    }
+
+    #[test]
+    fn resolves_against_base_without_path() {
+        assert_eq!(resolve_url("https://example.com", "img.png"), "https://example.com/img.png");
+        assert_eq!(resolve_url("https://example.com", "//cdn.example.com/x.js"), "https://cdn.example.com/x.js");
+    }
+
+    #[test]
+    fn ignores_whitespace_in_attributes() {
+        assert_eq!(resolve_url(BASE, "  g\n/h\t "), "http://a/b/c/g/h");
+    }
+
+    #[test]
+    fn uses_base_element_when_present() {
+        let dom = html_parser::Dom::parse(
+            "<html><head><base href=\"/docs/\"></head><body><a href=\"x\">x</a></body></html>",
+        )
+        .unwrap();
+        let base = document_base_url("http://example.com/index.html", &dom.children);
+        assert_eq!(base, "http://example.com/docs/");
+        assert_eq!(resolve_url(&base, "page.html"), "http://example.com/docs/page.html");
+    }
+
+    #[test]
+    fn reports_fetchable_schemes() {
+        assert!(is_fetchable("https://example.com/"));
+        assert!(is_fetchable("HTTP://example.com/"));
+        assert!(!is_fetchable("mailto:someone@example.com"));
+        assert!(!is_fetchable("javascript:void(0)"));
+    }
+
+    #[test]
+    fn decodes_data_urls() {
+        assert_eq!(decode_data_url("data:text/plain;base64,SGVsbG8sIHdvcmxk").as_deref(), Some(b"Hello, world".as_slice()));
+        assert_eq!(decode_data_url("DATA:,a%20b%2").as_deref(), Some(b"a b%2".as_slice()));
+        assert_eq!(decode_data_url("data:image/png;base64,***"), None);
+        assert_eq!(decode_data_url("http://a.test/,x"), None);
+    }
+
+    #[test]
+    fn converts_file_urls_and_paths() {
+        assert_eq!(path_to_file_url("/home/me/my docs/50% #1.html", false), "file:///home/me/my%20docs/50%25%20%231.html");
+        assert_eq!(path_to_file_url(r"\\?\C:\My Site\index.html", true), "file:///C:/My%20Site/index.html");
+        assert_eq!(path_to_file_url(r"\\?\UNC\server\share\a.html", true), "file://server/share/a.html");
+
+        let url = path_to_file_url("/home/me/my docs/50% #1.html", false);
+        assert_eq!(file_url_to_path_text(&url, false).as_deref(), Some("/home/me/my docs/50% #1.html"));
+        let css = resolve_url(&url, "style sheets/site.css#top");
+        assert_eq!(file_url_to_path_text(&css, false).as_deref(), Some("/home/me/my docs/style sheets/site.css"));
+        assert_eq!(file_url_to_path_text("file:///C:/My%20Site/a.png", true).as_deref(), Some(r"C:\My Site\a.png"));
+        assert_eq!(file_url_to_path_text("file://server/share/a.png", true).as_deref(), Some(r"\\server\share\a.png"));
+        assert_eq!(file_url_to_path_text("file://server/share/a.png", false), None);
+        assert_eq!(file_url_to_path_text("http://a.test/x", false), None);
+    }
+}